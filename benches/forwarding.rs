@@ -0,0 +1,213 @@
+//! Throughput and latency of the forwarding path.
+//!
+//! `single` calls `handle_packet` of one drone directly. `chain` runs chains of 2 to 50 drones,
+//! each in its own thread like in a simulation: `throughput` sends a burst of packets into the
+//! first drone and waits for all of them at the server, `latency` sends one packet at a time and
+//! prints the distribution of the time it takes to cross the chain.
+//!
+//! ```shell
+//! cargo bench --bench forwarding
+//! cargo bench --bench forwarding -- chain/latency/fragment
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::drone::Drone;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{NodeType, Packet};
+use drone_bettercalldrone::testing::{ack, flood_request, fragment};
+use drone_bettercalldrone::BetterCallDrone;
+
+/// The numbers of drones of the chains.
+const CHAIN_LENGTHS: [u8; 5] = [2, 5, 10, 25, 50];
+
+/// The packets measured.
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Fragment,
+    Ack,
+    FloodRequest,
+}
+
+impl Kind {
+    const ALL: [Kind; 3] = [Kind::Fragment, Kind::Ack, Kind::FloodRequest];
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Fragment => "fragment",
+            Kind::Ack => "ack",
+            Kind::FloodRequest => "flood_request",
+        }
+    }
+
+    /// Returns a packet from node 0 to the last node of `hops`. Flood requests need a new
+    /// `flood_id` every time, or the drones answer them instead of forwarding them.
+    fn packet(self, hops: &[NodeId], flood_id: u64) -> Packet {
+        match self {
+            Kind::Fragment => fragment(1, hops),
+            Kind::Ack => ack(1, hops),
+            Kind::FloodRequest => flood_request(flood_id, &[(0, NodeType::Client)]),
+        }
+    }
+}
+
+/// A drone with ID 1 between node 0 and node 2, called in the calling thread.
+fn single(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single");
+    group.throughput(Throughput::Elements(1));
+    for kind in Kind::ALL {
+        let (event_send, events) = unbounded();
+        let (_, command_recv) = unbounded();
+        let (_, packet_recv) = unbounded();
+        let (previous_send, _previous) = unbounded();
+        let (next_send, next) = unbounded();
+        let senders = HashMap::from([(0, previous_send), (2, next_send)]);
+        let mut drone = BetterCallDrone::new(1, event_send, command_recv, packet_recv, senders, 0.0);
+        let hops = [0, 1, 2];
+        let mut flood_id = 0;
+
+        group.bench_function(kind.name(), |b| {
+            b.iter(|| {
+                flood_id += 1;
+                drone.handle_packet(kind.packet(&hops, flood_id));
+                next.try_recv().expect("the drone forwards the packet");
+                while events.try_recv().is_ok() {}
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Drones 1 to `length` in a line, running in their own threads, between client 0 and server
+/// `length + 1`.
+struct Chain {
+    hops: Vec<NodeId>,
+    input: Sender<Packet>,
+    output: Receiver<Packet>,
+    commands: Vec<Sender<DroneCommand>>,
+    handles: Vec<JoinHandle<()>>,
+    _client: Receiver<Packet>,
+}
+
+impl Chain {
+    fn spawn(length: u8) -> Self {
+        let hops: Vec<NodeId> = (0..=length + 1).collect();
+        let channels: Vec<(Sender<Packet>, Receiver<Packet>)> = hops.iter().map(|_| unbounded()).collect();
+        let (event_send, events) = unbounded::<DroneEvent>();
+        thread::spawn(move || for _ in events.iter() {});
+
+        let mut commands = Vec::new();
+        let mut handles = Vec::new();
+        for id in 1..=length {
+            let index = usize::from(id);
+            let (command_send, command_recv) = unbounded();
+            let senders = HashMap::from([
+                (id - 1, channels[index - 1].0.clone()),
+                (id + 1, channels[index + 1].0.clone()),
+            ]);
+            let mut drone = BetterCallDrone::new(id, event_send.clone(), command_recv, channels[index].1.clone(), senders, 0.0);
+            handles.push(thread::spawn(move || drone.run()));
+            commands.push(command_send);
+        }
+        Self {
+            input: channels[1].0.clone(),
+            output: channels[hops.len() - 1].1.clone(),
+            _client: channels[0].1.clone(),
+            hops,
+            commands,
+            handles,
+        }
+    }
+
+    fn send(&self, packet: Packet) {
+        self.input.send(packet).expect("the first drone is running");
+    }
+
+    fn recv(&self) -> Packet {
+        self.output.recv_timeout(Duration::from_secs(10)).expect("the packet crosses the chain")
+    }
+
+    fn stop(self) {
+        for command in &self.commands {
+            let _ = command.send(DroneCommand::Crash);
+        }
+        for handle in self.handles {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Prints the percentiles of the latencies measured by a benchmark.
+fn print_distribution(name: &str, latencies: &mut [Duration]) {
+    if latencies.is_empty() {
+        return;
+    }
+    latencies.sort_unstable();
+    let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+    println!("{name}: {} packets, p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+        latencies.len(), percentile(50), percentile(90), percentile(99), latencies[latencies.len() - 1]);
+}
+
+fn chain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chain/throughput");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(1));
+    for kind in Kind::ALL {
+        for length in CHAIN_LENGTHS {
+            let chain = Chain::spawn(length);
+            let mut flood_id = 0;
+            group.bench_with_input(BenchmarkId::new(kind.name(), length), &length, |b, _| {
+                b.iter_custom(|iters| {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        flood_id += 1;
+                        chain.send(kind.packet(&chain.hops, flood_id));
+                    }
+                    for _ in 0..iters {
+                        chain.recv();
+                    }
+                    start.elapsed()
+                });
+            });
+            chain.stop();
+        }
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("chain/latency");
+    group.sample_size(20);
+    for kind in Kind::ALL {
+        for length in CHAIN_LENGTHS {
+            let chain = Chain::spawn(length);
+            let latencies = RefCell::new(Vec::new());
+            let mut flood_id = 0;
+            group.bench_with_input(BenchmarkId::new(kind.name(), length), &length, |b, _| {
+                b.iter_custom(|iters| {
+                    let mut total = Duration::ZERO;
+                    for _ in 0..iters {
+                        flood_id += 1;
+                        let packet = kind.packet(&chain.hops, flood_id);
+                        let start = Instant::now();
+                        chain.send(packet);
+                        chain.recv();
+                        let latency = start.elapsed();
+                        latencies.borrow_mut().push(latency);
+                        total += latency;
+                    }
+                    total
+                });
+            });
+            print_distribution(&format!("chain/latency/{}/{length}", kind.name()), &mut latencies.borrow_mut());
+            chain.stop();
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, single, chain);
+criterion_main!(benches);
@@ -0,0 +1,10 @@
+#![no_main]
+
+use drone_bettercalldrone::testing::fuzz::{run, FuzzOp};
+use libfuzzer_sys::fuzz_target;
+
+// Runs the drone in its own thread; a drone that does not stop shows up as a timeout.
+fuzz_target!(|input: (Vec<FuzzOp>, bool)| {
+    let (ops, disconnect) = input;
+    run(ops, disconnect);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use drone_bettercalldrone::testing::fuzz::{drive, FuzzOp};
+use libfuzzer_sys::fuzz_target;
+
+// Feeds packets and commands to `handle_packet` and `handle_command`.
+fuzz_target!(|ops: Vec<FuzzOp>| {
+    drive(ops);
+});
@@ -0,0 +1,37 @@
+use std::time::Duration;
+use std::{env, fs};
+use colored::Colorize;
+use drone_bettercalldrone::chat::run_scenario;
+use drone_bettercalldrone::topology::{generators, parse_config, GeneratorOptions, Placement};
+
+/// Runs the chat scenario over a network of `BetterCallDrone`s and reports whether every message arrived.
+///
+/// Usage: `bcd_chat [config.toml] [messages]`. Without a configuration file a ring of eight
+/// drones with PDR between 0.1 and 0.3, three clients and one server is used.
+fn main() {
+    let mut args = env::args().skip(1);
+    let config = match args.next() {
+        Some(path) => {
+            let content = fs::read_to_string(&path).unwrap_or_else(|e| exit(&format!("cannot read {path}: {e}")));
+            parse_config(&content).unwrap_or_else(|e| exit(&format!("invalid configuration: {e}")))
+        }
+        None => generators::ring(8, &GeneratorOptions {
+            pdr: (0.1, 0.3),
+            clients: 3,
+            servers: 1,
+            placement: Placement::Random,
+            seed: 0,
+        }),
+    };
+    let messages = args.next().map_or(5, |n| n.parse().unwrap_or_else(|_| exit("messages must be a number")));
+
+    match run_scenario(config, messages, Duration::from_secs(30)) {
+        Ok(report) => println!("{}/{} messages delivered in {:?}", report.received, report.sent, report.elapsed),
+        Err(e) => exit(&e.to_string()),
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{} {message}", "Error:".red());
+    std::process::exit(1)
+}
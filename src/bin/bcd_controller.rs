@@ -0,0 +1,81 @@
+use std::io::{self, BufRead, Write};
+use std::{env, fs, thread};
+use colored::Colorize;
+use crossbeam_channel::Select;
+use drone_bettercalldrone::repl::{describe_event, describe_packet, ReplCommand, HELP};
+use drone_bettercalldrone::simulation::Simulation;
+use drone_bettercalldrone::topology::{generators, parse_config, GeneratorOptions};
+
+/// Starts a network of `BetterCallDrone`s and a REPL to control it.
+///
+/// Usage: `bcd_controller [config.toml]`. Without a configuration file a ring of
+/// five drones with one client and one server is used.
+fn main() {
+    let config = match env::args().nth(1) {
+        Some(path) => {
+            let content = fs::read_to_string(&path).unwrap_or_else(|e| exit(&format!("cannot read {path}: {e}")));
+            parse_config(&content).unwrap_or_else(|e| exit(&format!("invalid configuration: {e}")))
+        }
+        None => generators::ring(5, &GeneratorOptions::default()),
+    };
+    let mut simulation = Simulation::spawn(config).unwrap_or_else(|e| exit(&e.to_string()));
+
+    let events = simulation.events().clone();
+    let endpoints: Vec<_> = simulation.endpoint_packets().iter().map(|(id, recv)| (*id, recv.clone())).collect();
+    thread::spawn(move || {
+        let mut select = Select::new();
+        select.recv(&events);
+        for (_, recv) in &endpoints {
+            select.recv(recv);
+        }
+        loop {
+            let operation = select.select();
+            let index = operation.index();
+            if index == 0 {
+                match operation.recv(&events) {
+                    Ok(event) => println!("{} {}", "[EVENT]".purple(), describe_event(&event)),
+                    Err(_) => break,
+                }
+            } else {
+                let (id, recv) = &endpoints[index - 1];
+                match operation.recv(recv) {
+                    Ok(packet) => println!("{} {}", format!("[NODE #{id}]").cyan(), describe_packet(&packet)),
+                    Err(_) => break,
+                }
+            }
+        }
+    });
+
+    println!("{HELP}");
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        match ReplCommand::parse(&line) {
+            Ok(command) => {
+                match command.execute(&mut simulation) {
+                    Ok(output) => println!("{output}"),
+                    Err(error) => println!("{} {error}", "Rejected:".red()),
+                }
+                if command == ReplCommand::Quit {
+                    break;
+                }
+            }
+            Err(error) => println!("{} {error}", "Error:".red()),
+        }
+    }
+    simulation.shutdown();
+}
+
+/// Prints an error and exits.
+fn exit(message: &str) -> ! {
+    eprintln!("{} {message}", "Error:".red());
+    std::process::exit(1)
+}
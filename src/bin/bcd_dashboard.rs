@@ -0,0 +1,157 @@
+use std::time::Duration;
+use std::{env, fs, io};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use drone_bettercalldrone::monitor::NetworkMonitor;
+use drone_bettercalldrone::repl::{describe_packet, ReplCommand};
+use drone_bettercalldrone::simulation::Simulation;
+use drone_bettercalldrone::topology::{generators, parse_config, GeneratorOptions};
+
+/// Live dashboard of a network of `BetterCallDrone`s.
+///
+/// Usage: `bcd_dashboard [config.toml]`. Controller commands (see `help` in `bcd_controller`)
+/// are typed in the bottom line and executed with Enter, Esc quits.
+struct Dashboard {
+    simulation: Simulation,
+    monitor: NetworkMonitor,
+    input: String,
+    status: String,
+}
+
+fn main() -> io::Result<()> {
+    let config = match env::args().nth(1) {
+        Some(path) => {
+            let content = fs::read_to_string(&path)?;
+            parse_config(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        }
+        None => generators::ring(5, &GeneratorOptions::default()),
+    };
+    let simulation = Simulation::spawn(config).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let monitor = NetworkMonitor::new(simulation.config());
+    let mut dashboard = Dashboard {
+        simulation,
+        monitor,
+        input: String::new(),
+        status: "Type a command and press Enter, Esc to quit".to_string(),
+    };
+
+    let terminal = ratatui::init();
+    let result = dashboard.run(terminal);
+    ratatui::restore();
+    dashboard.simulation.shutdown();
+    result
+}
+
+impl Dashboard {
+    /// Redraws the dashboard until the user quits.
+    fn run(&mut self, mut terminal: DefaultTerminal) -> io::Result<()> {
+        loop {
+            self.poll_network();
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char(c) => self.input.push(c),
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::Enter => {
+                    let line = std::mem::take(&mut self.input);
+                    if self.execute(&line) {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Moves the pending events and endpoint packets into the monitor.
+    fn poll_network(&mut self) {
+        let events: Vec<_> = self.simulation.events().try_iter().collect();
+        for event in &events {
+            self.monitor.record(event);
+        }
+        let mut delivered = Vec::new();
+        for (id, recv) in self.simulation.endpoint_packets() {
+            delivered.extend(recv.try_iter().map(|packet| format!("[node #{id}] received {}", describe_packet(&packet))));
+        }
+        for line in delivered {
+            self.monitor.push_log(line);
+        }
+    }
+
+    /// Executes a controller command, returns `true` if the user asked to quit.
+    fn execute(&mut self, line: &str) -> bool {
+        match ReplCommand::parse(line) {
+            Ok(ReplCommand::Quit) => return true,
+            Ok(command) => {
+                self.status = match command.execute(&mut self.simulation) {
+                    Ok(output) => output.lines().next().unwrap_or_default().to_string(),
+                    Err(error) => format!("Rejected: {}", error.replace('\n', " ")),
+                };
+                self.monitor.update_topology(self.simulation.config());
+            }
+            Err(error) => self.status = format!("Error: {error}"),
+        }
+        false
+    }
+
+    /// Draws the drone table, the event log and the command line.
+    fn draw(&self, frame: &mut Frame) {
+        let [drones_area, log_area, input_area] = Layout::vertical([
+            Constraint::Length(self.monitor.drones().len() as u16 + 3),
+            Constraint::Min(5),
+            Constraint::Length(3),
+        ]).areas(frame.area());
+
+        let header = Row::new(["Drone", "Neighbors", "PDR", "Sent", "Dropped", "Nacks"])
+            .style(Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD));
+        let rows = self.monitor.drones().iter().map(|(id, stats)| {
+            Row::new([
+                id.to_string(),
+                format!("{:?}", stats.neighbors),
+                format!("{:.2}", stats.pdr),
+                stats.sent.to_string(),
+                stats.dropped.to_string(),
+                stats.nacks.to_string(),
+            ])
+        });
+        let widths = [
+            Constraint::Length(6),
+            Constraint::Min(16),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(format!(" BetterCallDrone network, {} controller shortcuts ", self.monitor.shortcuts())));
+        frame.render_widget(table, drones_area);
+
+        let visible = log_area.height.saturating_sub(2) as usize;
+        let items: Vec<ListItem> = self.monitor.log().iter()
+            .rev()
+            .take(visible)
+            .rev()
+            .map(|line| ListItem::new(line.as_str()))
+            .collect();
+        frame.render_widget(List::new(items).block(Block::bordered().title(" Events ")), log_area);
+
+        let input = Paragraph::new(format!("> {}", self.input))
+            .block(Block::bordered().title(format!(" {} ", self.status)));
+        frame.render_widget(input, input_area);
+    }
+}
@@ -0,0 +1,47 @@
+use std::env;
+use colored::Colorize;
+use drone_bettercalldrone::testing::scenario::Scenario;
+use drone_bettercalldrone::testing::trace::{golden_path, update_golden};
+
+/// Runs scenario files against `BetterCallDrone` and reports the expectations that were not met.
+///
+/// Usage: `bcd_scenario [--update-golden] scenario.toml...`. Exits with an error if any scenario
+/// fails. With `--update-golden` the scenarios are not checked: their golden traces are rewritten.
+fn main() {
+    let mut paths: Vec<String> = env::args().skip(1).collect();
+    let update = paths.first().is_some_and(|arg| arg == "--update-golden");
+    if update {
+        paths.remove(0);
+    }
+    if paths.is_empty() {
+        exit("usage: bcd_scenario [--update-golden] scenario.toml...");
+    }
+
+    if update {
+        for path in &paths {
+            update_golden(path).unwrap_or_else(|e| exit(&format!("{path}: {e}")));
+            println!("{} {}", "Updated".green(), golden_path(path.as_ref()).display());
+        }
+        return;
+    }
+
+    let mut failed = 0;
+    for path in &paths {
+        let scenario = Scenario::load(path).unwrap_or_else(|e| exit(&format!("{path}: {e}")));
+        let report = scenario.run();
+        if report.passed() {
+            println!("{} {report}", "PASS".green());
+        } else {
+            failed += 1;
+            println!("{} {report}", "FAIL".red());
+        }
+    }
+    if failed > 0 {
+        exit(&format!("{failed} of {} scenarios failed", paths.len()));
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{} {message}", "Error:".red());
+    std::process::exit(1)
+}
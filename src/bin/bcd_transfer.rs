@@ -0,0 +1,48 @@
+use std::time::Duration;
+use std::{env, fs};
+use colored::Colorize;
+use drone_bettercalldrone::file_transfer::run_transfer;
+use drone_bettercalldrone::topology::{generators, parse_config, GeneratorOptions, Placement};
+use wg_2024::network::NodeId;
+
+/// Uploads a random file over a network of `BetterCallDrone`s and reports throughput and retransmissions.
+///
+/// Usage: `bcd_transfer [config.toml] [megabytes] [drones to crash...]`. Without a configuration
+/// file a 4x4 grid of drones with PDR between 0.05 and 0.2 is used.
+fn main() {
+    let mut args = env::args().skip(1);
+    let config = match args.next().filter(|path| path != "-") {
+        Some(path) => {
+            let content = fs::read_to_string(&path).unwrap_or_else(|e| exit(&format!("cannot read {path}: {e}")));
+            parse_config(&content).unwrap_or_else(|e| exit(&format!("invalid configuration: {e}")))
+        }
+        None => generators::grid(4, 4, &GeneratorOptions {
+            pdr: (0.05, 0.2),
+            clients: 1,
+            servers: 1,
+            placement: Placement::Ends,
+            seed: 0,
+        }),
+    };
+    let megabytes: usize = args.next().map_or(4, |n| n.parse().unwrap_or_else(|_| exit("megabytes must be a number")));
+    let crashes: Vec<NodeId> = args.map(|id| id.parse().unwrap_or_else(|_| exit("drones must be node ids"))).collect();
+
+    let data: Vec<u8> = (0..megabytes << 20).map(|_| rand::random()).collect();
+    match run_transfer(config, data, &crashes, Duration::from_secs(300)) {
+        Ok(report) => {
+            let status = if report.verified { "verified".green() } else { "corrupted".red() };
+            println!("{} bytes {status} in {:?}", report.bytes, report.elapsed);
+            println!("throughput: {:.1} KiB/s", report.throughput() / 1024.0);
+            println!(
+                "fragments: {} sent, {} retransmitted ({} timeouts, {} nacks)",
+                report.stats.fragments_sent, report.stats.retransmissions, report.stats.timeouts, report.stats.nacks,
+            );
+        }
+        Err(e) => exit(&e.to_string()),
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{} {message}", "Error:".red());
+    std::process::exit(1)
+}
@@ -0,0 +1,377 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::thread;
+use std::time::{Duration, Instant};
+use colored::Colorize;
+use crossbeam_channel::{Receiver, Sender};
+use wg_2024::config::Config;
+use wg_2024::network::NodeId;
+use wg_2024::packet::NodeType;
+use crate::client::{ClientCommand, ClientEvent};
+use crate::codec::{put_len, put_text, Reader};
+use crate::simulation::{ClientHandle, Simulation, SimulationError};
+pub use crate::codec::DecodeError;
+
+/// Messages exchanged between chat clients and the chat server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatMessage {
+    /// Client to server: registers the sender under `name`.
+    Register { name: String },
+    /// Client to server: asks for the registered clients.
+    ListClients,
+    /// Client to server: forwards `text` to `peer`.
+    SendTo { peer: NodeId, text: String },
+    /// Server to client: the registration succeeded.
+    Registered,
+    /// Server to client: the registered clients and their names.
+    ClientList(Vec<(NodeId, String)>),
+    /// Server to client: `text` sent by `from`.
+    Message { from: NodeId, text: String },
+    /// Server to client: the request failed.
+    Error(String),
+}
+
+impl ChatMessage {
+    /// Serializes the message: a tag byte followed by its fields. Node ids take one byte,
+    /// texts and lists are prefixed by their length as a little-endian `u32`.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            ChatMessage::Register { name } => {
+                bytes.push(0);
+                put_text(&mut bytes, name);
+            }
+            ChatMessage::ListClients => bytes.push(1),
+            ChatMessage::SendTo { peer, text } => {
+                bytes.extend([2, *peer]);
+                put_text(&mut bytes, text);
+            }
+            ChatMessage::Registered => bytes.push(3),
+            ChatMessage::ClientList(clients) => {
+                bytes.push(4);
+                put_len(&mut bytes, clients.len());
+                for (id, name) in clients {
+                    bytes.push(*id);
+                    put_text(&mut bytes, name);
+                }
+            }
+            ChatMessage::Message { from, text } => {
+                bytes.extend([5, *from]);
+                put_text(&mut bytes, text);
+            }
+            ChatMessage::Error(reason) => {
+                bytes.push(6);
+                put_text(&mut bytes, reason);
+            }
+        }
+        bytes
+    }
+
+    /// Parses a message serialized by [`ChatMessage::encode`].
+    ///
+    /// # Errors
+    /// Returns a `DecodeError` if `bytes` is not a valid message.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let message = match reader.byte()? {
+            0 => ChatMessage::Register { name: reader.text()? },
+            1 => ChatMessage::ListClients,
+            2 => ChatMessage::SendTo { peer: reader.byte()?, text: reader.text()? },
+            3 => ChatMessage::Registered,
+            4 => {
+                let len = reader.len()?;
+                let mut clients = Vec::new();
+                for _ in 0..len {
+                    clients.push((reader.byte()?, reader.text()?));
+                }
+                ChatMessage::ClientList(clients)
+            }
+            5 => ChatMessage::Message { from: reader.byte()?, text: reader.text()? },
+            6 => ChatMessage::Error(reader.text()?),
+            tag => return Err(DecodeError::UnknownTag(tag)),
+        };
+        reader.finish()?;
+        Ok(message)
+    }
+}
+
+/// The chat server: it keeps the names of the registered clients and forwards their messages.
+///
+/// It drives a `Client` node declared as `NodeType::Server`, through the node's command and
+/// event channels.
+pub struct ChatServer {
+    id: NodeId,
+    node_commands: Sender<ClientCommand>,
+    node_events: Receiver<ClientEvent>,
+    clients: BTreeMap<NodeId, String>,
+    debug: bool,
+}
+
+impl ChatServer {
+    /// Creates a new `ChatServer`.
+    ///
+    /// # Parameters
+    /// - `id`: The ID of the node carrying the server.
+    /// - `node_commands`: A channel sender for the commands of the node.
+    /// - `node_events`: A channel receiver for the events of the node.
+    #[must_use]
+    pub fn new(id: NodeId, node_commands: Sender<ClientCommand>, node_events: Receiver<ClientEvent>) -> Self {
+        Self {
+            id,
+            node_commands,
+            node_events,
+            clients: BTreeMap::new(),
+            debug: env::var("BCD_DEBUG").is_ok(),
+        }
+    }
+
+    /// Serves requests until the node stops.
+    pub fn run(&mut self) {
+        while let Ok(event) = self.node_events.recv() {
+            if let ClientEvent::MessageReceived { source, data, .. } = event {
+                match ChatMessage::decode(&data) {
+                    Ok(request) => self.handle_request(source, request),
+                    Err(e) => self.reply(source, &ChatMessage::Error(e.to_string())),
+                }
+            }
+        }
+    }
+
+    /// Answers a request of a client.
+    ///
+    /// # Parameters
+    /// - `source`: The client that sent the request.
+    /// - `request`: The request.
+    pub fn handle_request(&mut self, source: NodeId, request: ChatMessage) {
+        self.log(&format!("{} {request:?} from {source}", "Request".green()));
+        match request {
+            ChatMessage::Register { name } => {
+                self.clients.insert(source, name);
+                self.reply(source, &ChatMessage::Registered);
+            }
+            ChatMessage::ListClients => {
+                let clients = self.clients.iter().map(|(id, name)| (*id, name.clone())).collect();
+                self.reply(source, &ChatMessage::ClientList(clients));
+            }
+            ChatMessage::SendTo { peer, text } => {
+                if !self.clients.contains_key(&source) {
+                    self.reply(source, &ChatMessage::Error("not registered".to_string()));
+                } else if !self.clients.contains_key(&peer) {
+                    self.reply(source, &ChatMessage::Error(format!("client {peer} is not registered")));
+                } else {
+                    self.reply(peer, &ChatMessage::Message { from: source, text });
+                }
+            }
+            _ => self.reply(source, &ChatMessage::Error("not a request".to_string())),
+        }
+    }
+
+    fn reply(&self, destination: NodeId, message: &ChatMessage) {
+        let command = ClientCommand::SendMessage { destination, data: message.encode() };
+        if let Err(e) = self.node_commands.send(command) {
+            self.log(&format!("{} {}", "Error in Sending command: ", e));
+        }
+    }
+
+    /// Prints to console if debug mode is enabled.
+    fn log(&self, message: &str) {
+        if self.debug {
+            println!("{} {message}", format!("[CHAT SERVER #{}]", self.id).blue());
+        }
+    }
+}
+
+/// A chat client, driving a `Client` node through its command and event channels.
+pub struct ChatClient {
+    server: NodeId,
+    node_commands: Sender<ClientCommand>,
+    node_events: Receiver<ClientEvent>,
+}
+
+impl ChatClient {
+    /// Creates a new `ChatClient`.
+    ///
+    /// # Parameters
+    /// - `server`: The ID of the chat server.
+    /// - `node_commands`: A channel sender for the commands of the node.
+    /// - `node_events`: A channel receiver for the events of the node.
+    #[must_use]
+    pub fn new(server: NodeId, node_commands: Sender<ClientCommand>, node_events: Receiver<ClientEvent>) -> Self {
+        Self { server, node_commands, node_events }
+    }
+
+    /// Registers the client under `name`.
+    pub fn register(&self, name: &str) {
+        self.send(&ChatMessage::Register { name: name.to_string() });
+    }
+
+    /// Asks the server for the registered clients.
+    pub fn list_clients(&self) {
+        self.send(&ChatMessage::ListClients);
+    }
+
+    /// Sends `text` to `peer` through the server.
+    pub fn send_to(&self, peer: NodeId, text: &str) {
+        self.send(&ChatMessage::SendTo { peer, text: text.to_string() });
+    }
+
+    /// Waits for the next message of the server.
+    ///
+    /// # Returns
+    /// `None` if no valid message arrives before `deadline`.
+    pub fn recv_deadline(&self, deadline: Instant) -> Option<ChatMessage> {
+        while let Ok(event) = self.node_events.recv_deadline(deadline) {
+            if let ClientEvent::MessageReceived { source, data, .. } = event {
+                if source == self.server {
+                    if let Ok(message) = ChatMessage::decode(&data) {
+                        return Some(message);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn send(&self, message: &ChatMessage) {
+        let _ = self.node_commands.send(ClientCommand::SendMessage { destination: self.server, data: message.encode() });
+    }
+}
+
+/// Outcome of [`run_scenario`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScenarioReport {
+    /// Chat messages sent between clients.
+    pub sent: usize,
+    /// Chat messages received by their peer.
+    pub received: usize,
+    /// Time between the first registration and the last message received.
+    pub elapsed: Duration,
+}
+
+/// Errors returned by [`run_scenario`].
+#[derive(Debug)]
+pub enum ScenarioError {
+    /// The network could not be started.
+    Simulation(SimulationError),
+    /// The configuration needs one server and at least two clients.
+    MissingNodes,
+    /// Some messages did not arrive in time.
+    Timeout { pending: Vec<String> },
+}
+
+impl std::fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScenarioError::Simulation(e) => write!(f, "{e}"),
+            ScenarioError::MissingNodes => write!(f, "the scenario needs one server and at least two clients"),
+            ScenarioError::Timeout { pending } => write!(f, "timeout, still waiting for: {}", pending.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+/// Runs a chat over a network of `BetterCallDrone`s: the first server of `config` runs the
+/// chat server, every client registers, checks it is listed, then sends `messages` messages to
+/// every other client. Succeeds only if every message reaches its peer.
+///
+/// # Parameters
+/// - `config`: The network, usually with a non-zero PDR.
+/// - `messages`: How many messages each client sends to each peer.
+/// - `timeout`: How long to wait for each step.
+///
+/// # Errors
+/// Returns a `ScenarioError` if the network cannot be started or a message is lost.
+pub fn run_scenario(config: Config, messages: usize, timeout: Duration) -> Result<ScenarioReport, ScenarioError> {
+    let server_id = config.server.first().map(|s| s.id).ok_or(ScenarioError::MissingNodes)?;
+    let client_ids: Vec<NodeId> = config.client.iter().map(|c| c.id).collect();
+    if client_ids.len() < 2 {
+        return Err(ScenarioError::MissingNodes);
+    }
+    let mut simulation = Simulation::spawn(config).map_err(ScenarioError::Simulation)?;
+
+    let server_node = simulation.spawn_client(server_id, NodeType::Server).expect("the server is in the configuration");
+    let mut server = ChatServer::new(server_id, server_node.commands.clone(), server_node.events.clone());
+    thread::spawn(move || server.run());
+    let nodes: Vec<ClientHandle> = client_ids.iter()
+        .map(|&id| simulation.spawn_client(id, NodeType::Client).expect("the client is in the configuration"))
+        .collect();
+    let clients: HashMap<NodeId, ChatClient> = client_ids.iter().zip(&nodes)
+        .map(|(&id, node)| (id, ChatClient::new(server_id, node.commands.clone(), node.events.clone())))
+        .collect();
+
+    let start = Instant::now();
+    let result = converse(&clients, &client_ids, messages, timeout).map(|(sent, received)| ScenarioReport {
+        sent,
+        received,
+        elapsed: start.elapsed(),
+    });
+
+    for node in nodes {
+        let _ = node.stop();
+    }
+    let _ = server_node.stop();
+    result
+}
+
+/// Registers every client, then exchanges the messages.
+///
+/// # Returns
+/// The number of messages sent and received.
+fn converse(clients: &HashMap<NodeId, ChatClient>, ids: &[NodeId], messages: usize, timeout: Duration) -> Result<(usize, usize), ScenarioError> {
+    let name = |id: NodeId| format!("client-{id}");
+    let timeout_error = |pending: Vec<String>| Err(ScenarioError::Timeout { pending });
+
+    for &id in ids {
+        clients[&id].register(&name(id));
+    }
+    for &id in ids {
+        let deadline = Instant::now() + timeout;
+        if clients[&id].recv_deadline(deadline) != Some(ChatMessage::Registered) {
+            return timeout_error(vec![format!("registration of {id}")]);
+        }
+    }
+    let listed: Vec<(NodeId, String)> = {
+        let mut sorted = ids.to_vec();
+        sorted.sort_unstable();
+        sorted.into_iter().map(|id| (id, name(id))).collect()
+    };
+    for &id in ids {
+        clients[&id].list_clients();
+        let deadline = Instant::now() + timeout;
+        if clients[&id].recv_deadline(deadline) != Some(ChatMessage::ClientList(listed.clone())) {
+            return timeout_error(vec![format!("client list of {id}")]);
+        }
+    }
+
+    let mut expected: HashMap<NodeId, HashSet<(NodeId, String)>> = HashMap::new();
+    for &from in ids {
+        for &to in ids.iter().filter(|&&to| to != from) {
+            for n in 0..messages {
+                let text = format!("message {n} from {from} to {to}");
+                clients[&from].send_to(to, &text);
+                expected.entry(to).or_default().insert((from, text));
+            }
+        }
+    }
+    let sent = expected.values().map(HashSet::len).sum();
+
+    let mut received = 0;
+    for &id in ids {
+        let deadline = Instant::now() + timeout;
+        let pending = expected.entry(id).or_default();
+        while !pending.is_empty() {
+            match clients[&id].recv_deadline(deadline) {
+                Some(ChatMessage::Message { from, text }) => {
+                    if pending.remove(&(from, text)) {
+                        received += 1;
+                    }
+                }
+                Some(_) => {}
+                None => return timeout_error(pending.iter().map(|(from, text)| format!("{text:?} ({from} -> {id})")).collect()),
+            }
+        }
+    }
+    Ok((sent, received))
+}
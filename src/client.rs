@@ -0,0 +1,336 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::time::{Duration, Instant};
+use colored::Colorize;
+use crossbeam_channel::{select_biased, Receiver, Sender};
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{Fragment, Nack, NackType, NodeType, Packet, PacketType};
+use crate::discovery::{TopologyChange, TopologyDiscovery};
+use crate::node::{answer_flood, reply_route};
+use crate::routing::{most_reliable_route, LinkQuality};
+use crate::transport::{OutgoingFragment, Transport, TransportConfig, TransportStats};
+
+/// How long the client waits before flooding again while some fragments have no route.
+pub const FLOOD_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Commands accepted by a `Client`.
+#[derive(Debug, Clone)]
+pub enum ClientCommand {
+    /// Splits `data` into fragments and sends them to `destination`.
+    SendMessage { destination: NodeId, data: Vec<u8> },
+    /// Starts a new flood to refresh the known topology.
+    DiscoverTopology,
+    /// Adds a neighboring drone.
+    AddSender(NodeId, Sender<Packet>),
+    /// Removes a neighboring drone.
+    RemoveSender(NodeId),
+    /// Stops the client.
+    Crash,
+}
+
+/// Events reported by a `Client`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    /// A message has been split and its session started.
+    MessageSent { session_id: u64, destination: NodeId },
+    /// Every fragment of a session has been acknowledged.
+    MessageDelivered { session_id: u64 },
+    /// A full message has been reassembled.
+    MessageReceived { source: NodeId, session_id: u64, data: Vec<u8> },
+    /// A flood has been closed and the known topology differs from the previous flood.
+    TopologyChanged(TopologyChange),
+    /// A packet has been sent to a neighbor.
+    PacketSent(Packet),
+}
+
+/// A reference client node: it sends messages through a [`Transport`], source-routes the fragments
+/// through the most reliable known drones and recovers from `Nack`s.
+///
+/// - `Dropped`: the fragment is sent again.
+/// - `ErrorInRouting`, `DestinationIsDrone`: the faulty link or node is removed from the known
+///   topology and the fragment is sent again once a route is known.
+/// - `UnexpectedRecipient`: the reporting drone is reachable, so nothing is removed and the
+///   fragment is sent again.
+///
+/// Every nack but `Dropped` also asks for a new flood, started at most once per [`FLOOD_INTERVAL`].
+pub struct Client {
+    id: NodeId,
+    controller_send: Sender<ClientEvent>,
+    controller_recv: Receiver<ClientCommand>,
+    packet_recv: Receiver<Packet>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+
+    node_type: NodeType,
+    discovery: TopologyDiscovery,
+    quality: LinkQuality,
+    transport: Transport,
+    /// Fragments waiting for a route.
+    waiting: VecDeque<OutgoingFragment>,
+    /// Whether a routing error asked for a new flood.
+    flood_needed: bool,
+    debug: bool,
+}
+
+impl Client {
+    /// Creates a new `Client` with the default [`TransportConfig`].
+    ///
+    /// # Parameters
+    /// - `id`: The ID of the client.
+    /// - `controller_send`: A channel sender for the events of the client.
+    /// - `controller_recv`: A channel receiver for the commands of the client.
+    /// - `packet_recv`: A channel receiver for incoming packets.
+    /// - `packet_send`: The neighboring drones and their packet senders.
+    #[must_use]
+    pub fn new(
+        id: NodeId,
+        controller_send: Sender<ClientEvent>,
+        controller_recv: Receiver<ClientCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+    ) -> Self {
+        Self {
+            id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            node_type: NodeType::Client,
+            discovery: TopologyDiscovery::new(id, NodeType::Client, FLOOD_INTERVAL),
+            quality: LinkQuality::new(),
+            transport: Transport::new(TransportConfig::default()),
+            waiting: VecDeque::new(),
+            flood_needed: false,
+            debug: env::var("BCD_DEBUG").is_ok(),
+        }
+    }
+
+    /// Replaces the parameters of the transport. Must be called before any message is sent.
+    pub fn set_transport_config(&mut self, config: TransportConfig) {
+        self.transport = Transport::new(config);
+    }
+
+    /// Sets the type the client declares in the topology, e.g. `NodeType::Server` when it carries
+    /// the messages of a server application. Must be called before the client runs.
+    pub fn set_node_type(&mut self, node_type: NodeType) {
+        self.node_type = node_type;
+        self.discovery = TopologyDiscovery::new(self.id, node_type, FLOOD_INTERVAL);
+    }
+
+    /// Starts the main loop of the client, until it receives `ClientCommand::Crash`
+    /// or its command channel is closed.
+    pub fn run(&mut self) {
+        loop {
+            let now = Instant::now();
+            let wake_up = self.transport.next_timeout()
+                .map_or(FLOOD_INTERVAL, |t| t.saturating_duration_since(now).min(FLOOD_INTERVAL));
+            select_biased! {
+                recv(self.controller_recv) -> command => {
+                    match command {
+                        Ok(ClientCommand::Crash) | Err(_) => break,
+                        Ok(command) => self.handle_command(command),
+                    }
+                }
+                recv(self.packet_recv) -> packet => {
+                    if let Ok(packet) = packet {
+                        self.handle_packet(packet);
+                    }
+                }
+                default(wake_up) => {}
+            }
+            self.tick(Instant::now());
+        }
+    }
+
+    /// Closes floods, sends the fragments the transport is ready to send and reports its events.
+    ///
+    /// # Parameters
+    /// - `now`: The current time.
+    pub fn tick(&mut self, now: Instant) {
+        if let Some(change) = self.discovery.poll(now) {
+            if !change.is_empty() {
+                self.emit(ClientEvent::TopologyChanged(change));
+            }
+        }
+        for outgoing in self.transport.poll_transmit(now) {
+            if !self.waiting.contains(&outgoing) {
+                self.waiting.push_back(outgoing);
+            }
+        }
+        self.retry_waiting();
+        if (self.flood_needed || !self.waiting.is_empty()) && self.discovery.last_flood().is_none_or(|t| now.duration_since(t) >= FLOOD_INTERVAL) {
+            self.flood();
+        }
+        while let Some(session_id) = self.transport.delivered_message() {
+            self.emit(ClientEvent::MessageDelivered { session_id });
+        }
+        while let Some(message) = self.transport.receive_message() {
+            self.emit(ClientEvent::MessageReceived { source: message.source, session_id: message.session_id, data: message.data });
+        }
+    }
+
+    /// Processes a command.
+    ///
+    /// # Parameters
+    /// - `command`: The command to handle.
+    pub fn handle_command(&mut self, command: ClientCommand) {
+        match command {
+            ClientCommand::SendMessage { destination, data } => self.send_message(destination, &data),
+            ClientCommand::DiscoverTopology => self.flood(),
+            ClientCommand::AddSender(node_id, sender) => {
+                self.packet_send.insert(node_id, sender);
+            }
+            ClientCommand::RemoveSender(node_id) => {
+                self.packet_send.remove(&node_id);
+                self.discovery.remove_link(self.id, node_id);
+            }
+            ClientCommand::Crash => {}
+        }
+    }
+
+    /// Handles an incoming packet based on its type.
+    ///
+    /// # Parameters
+    /// - `packet`: The incoming packet.
+    pub fn handle_packet(&mut self, packet: Packet) {
+        match packet.pack_type {
+            PacketType::MsgFragment(fragment) => self.handle_fragment(&packet.routing_header, packet.session_id, &fragment),
+            PacketType::Ack(ack) => {
+                self.quality.record_forwarded(&packet.routing_header.hops);
+                self.learn_ack_route(&packet.routing_header.hops);
+                self.transport.handle_ack(packet.session_id, ack.fragment_index);
+            }
+            PacketType::Nack(nack) => self.handle_nack(&packet.routing_header, packet.session_id, &nack),
+            PacketType::FloodRequest(request) => self.send_packet(answer_flood(request, self.id, self.node_type, packet.session_id)),
+            PacketType::FloodResponse(response) => {
+                if self.discovery.handle_response(&response) {
+                    self.retry_waiting();
+                }
+            }
+        }
+    }
+
+    /// Starts sending a message.
+    ///
+    /// # Parameters
+    /// - `destination`: The node the message is for.
+    /// - `data`: The message.
+    pub fn send_message(&mut self, destination: NodeId, data: &[u8]) {
+        let session_id = self.transport.send_message(destination, data);
+        self.emit(ClientEvent::MessageSent { session_id, destination });
+    }
+
+    /// Returns the number of messages whose fragments are not all acknowledged.
+    #[must_use]
+    pub fn pending_messages(&self) -> usize {
+        self.transport.pending_messages()
+    }
+
+    /// Returns the counters of the transport.
+    #[must_use]
+    pub fn transport_stats(&self) -> TransportStats {
+        self.transport.stats()
+    }
+
+    /// Acknowledges a fragment and hands it to the transport.
+    fn handle_fragment(&mut self, routing_header: &SourceRoutingHeader, session_id: u64, fragment: &Fragment) {
+        let Some(route) = reply_route(routing_header) else {
+            return;
+        };
+        let source = route.hops[route.hops.len() - 1];
+        self.send_packet(Packet::new_ack(route, session_id, fragment.fragment_index));
+        self.transport.handle_fragment(source, session_id, fragment);
+    }
+
+    /// Adds the links of the route an ack came back on to the graph. The ack follows the route
+    /// the fragment took, so after a drone repaired that route it shows the detour; the detour is
+    /// kept when the next flood closes.
+    fn learn_ack_route(&mut self, hops: &[NodeId]) {
+        self.discovery.add_route(hops);
+    }
+
+    /// Reacts to a nack: the transport resends the fragment, routing errors also update the topology
+    /// and ask for a new flood, which `tick` starts once [`FLOOD_INTERVAL`] has elapsed.
+    fn handle_nack(&mut self, routing_header: &SourceRoutingHeader, session_id: u64, nack: &Nack) {
+        self.log(&format!("({session_id}:{}) | {} -> {:?}", nack.fragment_index, "Received Nack".red(), nack.nack_type));
+        self.quality.record_nack(routing_header, nack);
+        match nack.nack_type {
+            NackType::Dropped => {}
+            NackType::ErrorInRouting(unreachable) => {
+                if let Some(&reporter) = routing_header.hops.first() {
+                    self.discovery.remove_link(reporter, unreachable);
+                }
+                self.flood_needed = true;
+            }
+            // The recipient is the drone reporting the nack, so it is reachable: the route the
+            // fragment took may simply be stale.
+            NackType::UnexpectedRecipient(_) => self.flood_needed = true,
+            NackType::DestinationIsDrone => {
+                if let Some(destination) = self.transport.destination(session_id) {
+                    self.discovery.remove_node(destination);
+                }
+                self.flood_needed = true;
+            }
+        }
+        self.transport.handle_nack(session_id, nack.fragment_index);
+        self.tick(Instant::now());
+    }
+
+    /// Sends every waiting fragment that now has a route.
+    fn retry_waiting(&mut self) {
+        for _ in 0..self.waiting.len() {
+            let Some(outgoing) = self.waiting.pop_front() else {
+                break;
+            };
+            match most_reliable_route(self.discovery.graph(), &self.quality, self.id, outgoing.destination) {
+                Some(routing_header) => {
+                    let packet = Packet::new_fragment(routing_header, outgoing.session_id, outgoing.fragment);
+                    self.send_packet(packet);
+                }
+                None => self.waiting.push_back(outgoing),
+            }
+        }
+    }
+
+    /// Sends a new `FloodRequest` to every neighbor.
+    fn flood(&mut self) {
+        self.flood_needed = false;
+        let request = self.discovery.start_flood(Instant::now());
+        for sender in self.packet_send.values() {
+            let packet = Packet::new_flood_request(SourceRoutingHeader::empty_route(), request.flood_id, request.clone());
+            if let Err(e) = sender.send(packet) {
+                self.log(&format!("{} {}", "Error in Sending FloodRequest: ", e));
+            }
+        }
+    }
+
+    /// Sends a packet to the node at `hops[hop_index]`.
+    ///
+    /// If that neighbor is gone or has crashed the link is forgotten, and a fragment is resent by
+    /// the transport on a new route once its `Ack` times out.
+    fn send_packet(&mut self, packet: Packet) {
+        let Some(&next_hop) = packet.routing_header.hops.get(packet.routing_header.hop_index) else {
+            return;
+        };
+        let sent = self.packet_send.get(&next_hop).is_some_and(|sender| sender.send(packet.clone()).is_ok());
+        if sent {
+            self.emit(ClientEvent::PacketSent(packet));
+        } else {
+            self.packet_send.remove(&next_hop);
+            self.discovery.remove_link(self.id, next_hop);
+        }
+    }
+
+    /// Sends an event to the controller.
+    fn emit(&self, event: ClientEvent) {
+        if let Err(e) = self.controller_send.send(event) {
+            self.log(&format!("{} {}", "Error in Sending event: ", e));
+        }
+    }
+
+    /// Prints to console if debug mode is enabled.
+    fn log(&self, message: &str) {
+        if self.debug {
+            println!("{} {message}", format!("[CLIENT #{}]", self.id).blue());
+        }
+    }
+}
@@ -0,0 +1,89 @@
+/// Errors returned when decoding the messages of the applications.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The message ends before the end of a field.
+    Truncated,
+    /// The first byte does not identify a message.
+    UnknownTag(u8),
+    /// A text field is not valid UTF-8.
+    InvalidText,
+    /// The message has bytes after its last field.
+    TrailingBytes,
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "message is truncated"),
+            DecodeError::UnknownTag(tag) => write!(f, "unknown message tag {tag}"),
+            DecodeError::InvalidText => write!(f, "text is not valid UTF-8"),
+            DecodeError::TrailingBytes => write!(f, "unexpected bytes after the message"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Writes a length as a little-endian `u32`.
+pub(crate) fn put_len(bytes: &mut Vec<u8>, len: usize) {
+    let len = u32::try_from(len).expect("message fields are shorter than 4 GiB");
+    bytes.extend(len.to_le_bytes());
+}
+
+/// Writes a byte string prefixed by its length.
+pub(crate) fn put_bytes(bytes: &mut Vec<u8>, data: &[u8]) {
+    put_len(bytes, data.len());
+    bytes.extend(data);
+}
+
+/// Writes a text prefixed by its length.
+pub(crate) fn put_text(bytes: &mut Vec<u8>, text: &str) {
+    put_bytes(bytes, text.as_bytes());
+}
+
+/// Reads the fields of an encoded message.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < n {
+            return Err(DecodeError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn len(&mut self) -> Result<usize, DecodeError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.len()?;
+        self.take(len)
+    }
+
+    pub(crate) fn text(&mut self) -> Result<String, DecodeError> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| DecodeError::InvalidText)
+    }
+
+    /// Checks that the whole message has been read.
+    pub(crate) fn finish(&self) -> Result<(), DecodeError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(DecodeError::TrailingBytes)
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! A conformance suite for any implementation of the WG `Drone` trait.
+//!
+//! Every function spawns a drone with ID 11 in its own thread, talks to it through channels and
+//! panics if the drone does not follow the protocol. Run them against another drone with e.g.
+//! `conformance::run_all::<OtherDrone>()`. Needs the `testing` feature.
+
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::drone::Drone;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{FloodResponse, NackType, NodeType, Packet, PacketType};
+use crate::testing::{ack, flood_request, forwarded, fragment, nack, route, DroneFixture};
+
+/// The ID of the drone under test.
+const DRONE: NodeId = 11;
+
+/// Checks that a flood response carries `path_trace` and is routed back along it.
+fn assert_flood_response(packet: &Packet, flood_id: u64, path_trace: &[(NodeId, NodeType)]) {
+    let PacketType::FloodResponse(response) = &packet.pack_type else {
+        panic!("expected a FloodResponse, got {packet:?}");
+    };
+    assert_eq!(response, &FloodResponse { flood_id, path_trace: path_trace.to_vec() });
+    let hops: Vec<NodeId> = path_trace.iter().rev().map(|(id, _)| *id).collect();
+    assert_eq!(packet.routing_header.hops, hops);
+    assert_eq!(packet.routing_header.hop_index, 1);
+}
+
+/// A fragment is forwarded to the next hop, with `hop_index` incremented and a `PacketSent` event.
+pub fn fragment_forward<D: Drone + Send + 'static>() {
+    let drone = DroneFixture::spawn_with::<D>(DRONE, &[1, 12], 0.0);
+    let packet = fragment(1, &[1, DRONE, 12, 21]);
+    drone.send(&packet);
+
+    drone.assert_packet(12, &forwarded(&packet));
+    drone.wait_for_event(|event| *event == DroneEvent::PacketSent(forwarded(&packet)));
+}
+
+/// A fragment whose next hop is not a neighbor is answered with `ErrorInRouting`.
+pub fn nack_error_in_routing<D: Drone + Send + 'static>() {
+    let drone = DroneFixture::spawn_with::<D>(DRONE, &[1], 0.0);
+    drone.send(&fragment(1, &[1, DRONE, 12]));
+    drone.assert_packet(1, &nack(1, &[DRONE, 1], NackType::ErrorInRouting(12)));
+}
+
+/// A fragment whose route ends at the drone is answered with `DestinationIsDrone`.
+pub fn nack_destination_is_drone<D: Drone + Send + 'static>() {
+    let drone = DroneFixture::spawn_with::<D>(DRONE, &[1], 0.0);
+    drone.send(&fragment(1, &[1, DRONE]));
+    drone.assert_packet(1, &nack(1, &[DRONE, 1], NackType::DestinationIsDrone));
+}
+
+/// A fragment whose current hop is another node is answered with `UnexpectedRecipient`.
+pub fn nack_unexpected_recipient<D: Drone + Send + 'static>() {
+    let drone = DroneFixture::spawn_with::<D>(DRONE, &[1], 0.0);
+    drone.send(&fragment(1, &[1, 16, 21]));
+    drone.assert_packet(1, &nack(1, &[DRONE, 1], NackType::UnexpectedRecipient(DRONE)));
+}
+
+/// A dropped fragment is answered with `Dropped` and reported with `PacketDropped`.
+pub fn nack_dropped<D: Drone + Send + 'static>() {
+    let drone = DroneFixture::spawn_with::<D>(DRONE, &[1, 12], 1.0);
+    let packet = fragment(1, &[1, DRONE, 12]);
+    drone.send(&packet);
+
+    drone.assert_packet(1, &nack(1, &[DRONE, 1], NackType::Dropped));
+    drone.wait_for_event(|event| *event == DroneEvent::PacketDropped(packet.clone()));
+    drone.assert_no_packet(12);
+}
+
+/// A nack is forwarded along its route and never dropped.
+pub fn nack_forward<D: Drone + Send + 'static>() {
+    let drone = DroneFixture::spawn_with::<D>(DRONE, &[1, 12], 1.0);
+    let packet = nack(1, &[12, DRONE, 1], NackType::Dropped);
+    drone.send(&packet);
+    drone.assert_packet(1, &forwarded(&packet));
+}
+
+/// A new flood request is forwarded to every neighbor except the sender, and the same flood
+/// coming back from another neighbor is answered with a `FloodResponse`.
+pub fn flood_deduplication<D: Drone + Send + 'static>() {
+    let drone = DroneFixture::spawn_with::<D>(DRONE, &[1, 12, 13], 0.0);
+    drone.send(&flood_request(42, &[(1, NodeType::Client)]));
+
+    let forwarded = [(1, NodeType::Client), (DRONE, NodeType::Drone)];
+    for neighbor in [12, 13] {
+        let packet = drone.recv(neighbor);
+        let PacketType::FloodRequest(request) = packet.pack_type else {
+            panic!("expected a FloodRequest, got {packet:?}");
+        };
+        assert_eq!((request.flood_id, request.initiator_id), (42, 1));
+        assert_eq!(request.path_trace, forwarded);
+    }
+    drone.assert_no_packet(1);
+
+    drone.send(&flood_request(42, &[(1, NodeType::Client), (12, NodeType::Drone)]));
+    let response = drone.recv(12);
+    assert_flood_response(&response, 42, &[(1, NodeType::Client), (12, NodeType::Drone), (DRONE, NodeType::Drone)]);
+    drone.assert_no_packet(13);
+}
+
+/// A flood request reaching a drone with no other neighbor is answered with a `FloodResponse`.
+pub fn flood_dead_end<D: Drone + Send + 'static>() {
+    let drone = DroneFixture::spawn_with::<D>(DRONE, &[12], 0.0);
+    drone.send(&flood_request(42, &[(1, NodeType::Client), (12, NodeType::Drone)]));
+    let response = drone.recv(12);
+    assert_flood_response(&response, 42, &[(1, NodeType::Client), (12, NodeType::Drone), (DRONE, NodeType::Drone)]);
+}
+
+/// An `Ack`, a `Nack` or a `FloodResponse` that cannot be forwarded goes through the controller.
+/// The `hop_index` of the packet given to the controller is not checked.
+pub fn controller_shortcut<D: Drone + Send + 'static>() {
+    let drone = DroneFixture::spawn_with::<D>(DRONE, &[1, 12], 0.0);
+    let packets = [
+        ack(1, &[1, DRONE, 13]),
+        nack(1, &[1, DRONE, 13], NackType::Dropped),
+        Packet::new_flood_response(route(1, &[1, DRONE, 13]), 1, FloodResponse {
+            flood_id: 42,
+            path_trace: vec![(13, NodeType::Client), (DRONE, NodeType::Drone), (1, NodeType::Drone)],
+        }),
+    ];
+    for packet in packets {
+        drone.send(&packet);
+        drone.wait_for_event(|event| matches!(event, DroneEvent::ControllerShortcut(p)
+            if p.pack_type == packet.pack_type && p.routing_header.hops == packet.routing_header.hops));
+    }
+}
+
+/// On `Crash` the drone handles the packets still queued and stops: fragments are answered with
+/// `ErrorInRouting`, other packets are forwarded and flood requests are discarded.
+pub fn crash_draining<D: Drone + Send + 'static>() {
+    let (mut fixture, drone) = DroneFixture::build::<D>(DRONE, &[1, 12], 0.0);
+    let ack = ack(1, &[12, DRONE, 1]);
+    fixture.send(&fragment(1, &[1, DRONE, 12]));
+    fixture.send(&ack);
+    fixture.send(&flood_request(42, &[(1, NodeType::Client)]));
+    fixture.command(DroneCommand::Crash);
+    fixture.start(drone);
+
+    fixture.assert_packet(1, &nack(1, &[DRONE, 1], NackType::ErrorInRouting(DRONE)));
+    fixture.assert_packet(1, &forwarded(&ack));
+    fixture.assert_no_packet(12);
+    fixture.assert_stopped();
+}
+
+/// `AddSender`, `RemoveSender` and `SetPacketDropRate` change how the next fragments are handled.
+pub fn commands<D: Drone + Send + 'static>() {
+    let mut drone = DroneFixture::spawn_with::<D>(DRONE, &[1], 0.0);
+    let sender = drone.add_neighbor(12);
+    drone.command(DroneCommand::AddSender(12, sender));
+    let packet = fragment(1, &[1, DRONE, 12]);
+    drone.send(&packet);
+    drone.assert_packet(12, &forwarded(&packet));
+
+    drone.command(DroneCommand::SetPacketDropRate(1.0));
+    drone.send(&packet);
+    drone.assert_packet(1, &nack(1, &[DRONE, 1], NackType::Dropped));
+
+    drone.command(DroneCommand::SetPacketDropRate(0.0));
+    drone.command(DroneCommand::RemoveSender(12));
+    drone.send(&packet);
+    drone.assert_packet(1, &nack(1, &[DRONE, 1], NackType::ErrorInRouting(12)));
+    drone.assert_no_packet(12);
+}
+
+/// Runs the whole suite.
+pub fn run_all<D: Drone + Send + 'static>() {
+    fragment_forward::<D>();
+    nack_error_in_routing::<D>();
+    nack_destination_is_drone::<D>();
+    nack_unexpected_recipient::<D>();
+    nack_dropped::<D>();
+    nack_forward::<D>();
+    flood_deduplication::<D>();
+    flood_dead_end::<D>();
+    controller_shortcut::<D>();
+    crash_draining::<D>();
+    commands::<D>();
+}
@@ -0,0 +1,530 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use rand::random;
+use wg_2024::config::Config;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{FloodRequest, FloodResponse, NodeType};
+
+/// A view of the network: the known nodes with their type, and the links between them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkGraph {
+    nodes: BTreeMap<NodeId, NodeType>,
+    links: BTreeMap<NodeId, BTreeSet<NodeId>>,
+}
+
+impl NetworkGraph {
+    /// Creates an empty graph.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the graph of a configuration: every node with its type and every link.
+    #[must_use]
+    pub fn from_config(config: &Config) -> Self {
+        let mut graph = Self::new();
+        for d in &config.drone {
+            graph.add_node(d.id, NodeType::Drone);
+        }
+        for c in &config.client {
+            graph.add_node(c.id, NodeType::Client);
+        }
+        for s in &config.server {
+            graph.add_node(s.id, NodeType::Server);
+        }
+        let links = config.drone.iter().map(|d| (d.id, &d.connected_node_ids))
+            .chain(config.client.iter().map(|c| (c.id, &c.connected_drone_ids)))
+            .chain(config.server.iter().map(|s| (s.id, &s.connected_drone_ids)));
+        for (node_id, neighbors) in links {
+            for &neighbor in neighbors {
+                graph.add_link(node_id, neighbor);
+            }
+        }
+        graph
+    }
+
+    /// Adds the nodes of a `path_trace` and the links between consecutive entries.
+    ///
+    /// # Returns
+    /// `true` if the graph changed.
+    pub fn add_path(&mut self, path_trace: &[(NodeId, NodeType)]) -> bool {
+        let mut changed = false;
+        for &(node_id, node_type) in path_trace {
+            changed |= self.nodes.insert(node_id, node_type) != Some(node_type);
+        }
+        for pair in path_trace.windows(2) {
+            changed |= self.add_link(pair[0].0, pair[1].0);
+        }
+        changed
+    }
+
+    /// Adds a bidirectional link between two nodes.
+    ///
+    /// # Returns
+    /// `true` if the link was not known.
+    pub fn add_link(&mut self, a: NodeId, b: NodeId) -> bool {
+        if a == b {
+            return false;
+        }
+        let added = self.links.entry(a).or_default().insert(b);
+        self.links.entry(b).or_default().insert(a);
+        added
+    }
+
+    /// Removes the link between two nodes, if known.
+    pub fn remove_link(&mut self, a: NodeId, b: NodeId) {
+        if let Some(links) = self.links.get_mut(&a) {
+            links.remove(&b);
+        }
+        if let Some(links) = self.links.get_mut(&b) {
+            links.remove(&a);
+        }
+    }
+
+    /// Removes a node and all its links.
+    pub fn remove_node(&mut self, node_id: NodeId) {
+        self.nodes.remove(&node_id);
+        for neighbor in self.links.remove(&node_id).unwrap_or_default() {
+            if let Some(links) = self.links.get_mut(&neighbor) {
+                links.remove(&node_id);
+            }
+        }
+    }
+
+    /// Inserts a node with its type without adding any link.
+    pub fn add_node(&mut self, node_id: NodeId, node_type: NodeType) {
+        self.nodes.insert(node_id, node_type);
+    }
+
+    /// Returns the type of a node, if known.
+    #[must_use]
+    pub fn node_type(&self, node_id: NodeId) -> Option<NodeType> {
+        self.nodes.get(&node_id).copied()
+    }
+
+    /// Returns the known nodes with their type, sorted by id.
+    #[must_use]
+    pub fn nodes(&self) -> &BTreeMap<NodeId, NodeType> {
+        &self.nodes
+    }
+
+    /// Returns the known neighbors of a node, sorted by id.
+    pub fn neighbors(&self, node_id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.links.get(&node_id).into_iter().flatten().copied()
+    }
+
+    /// Returns every known link once, as `(smaller id, bigger id)`.
+    #[must_use]
+    pub fn links(&self) -> BTreeSet<(NodeId, NodeId)> {
+        self.links.iter()
+            .flat_map(|(&a, neighbors)| neighbors.iter().map(move |&b| (a.min(b), a.max(b))))
+            .collect()
+    }
+
+    /// Finds a route with the fewest hops between two nodes.
+    ///
+    /// Only drones are used as intermediate hops, since clients and servers do not forward packets.
+    ///
+    /// # Returns
+    /// The hops from `from` to `to`, both included, or `None` if `to` cannot be reached.
+    #[must_use]
+    pub fn shortest_route(&self, from: NodeId, to: NodeId) -> Option<Vec<NodeId>> {
+        self.shortest_route_via(from, self.neighbors(from), to)
+    }
+
+    /// Finds a route with the fewest hops between two nodes, leaving `from` through `neighbors`
+    /// instead of the links of `from` in the graph, e.g. the live neighbors of a drone.
+    ///
+    /// # Returns
+    /// The hops from `from` to `to`, both included, or `None` if `to` cannot be reached.
+    #[must_use]
+    pub fn shortest_route_via(&self, from: NodeId, neighbors: impl IntoIterator<Item = NodeId>, to: NodeId) -> Option<Vec<NodeId>> {
+        if from == to {
+            return Some(vec![from]);
+        }
+        let mut previous: HashMap<NodeId, NodeId> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for next in neighbors {
+            if next != from && !previous.contains_key(&next) {
+                previous.insert(next, from);
+                queue.push_back(next);
+            }
+        }
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut hops = vec![to];
+                while let Some(&prev) = previous.get(hops.last()?) {
+                    hops.push(prev);
+                }
+                hops.reverse();
+                return Some(hops);
+            }
+            if self.node_type(node) != Some(NodeType::Drone) {
+                continue;
+            }
+            for next in self.neighbors(node) {
+                if next != from && !previous.contains_key(&next) {
+                    previous.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Adds the links of a route a packet took. The hops between the two ends are drones; the
+    /// type of the ends is kept if known.
+    pub fn add_route(&mut self, hops: &[NodeId]) {
+        if hops.len() > 2 {
+            for &drone in &hops[1..hops.len() - 1] {
+                self.nodes.entry(drone).or_insert(NodeType::Drone);
+            }
+        }
+        for pair in hops.windows(2) {
+            self.add_link(pair[0], pair[1]);
+        }
+    }
+
+    /// Adds every node and link of another graph. The type of a node already known is kept.
+    pub fn merge(&mut self, other: &NetworkGraph) {
+        for (&node_id, &node_type) in &other.nodes {
+            self.nodes.entry(node_id).or_insert(node_type);
+        }
+        for (a, b) in other.links() {
+            self.add_link(a, b);
+        }
+    }
+}
+
+/// How many route repairs a [`TopologyHandle`] remembers.
+pub const REPAIR_LOG_CAPACITY: usize = 100;
+
+/// A route rewritten by a drone because its next hop was not a neighbor, see
+/// [`TopologyHandle::set_route_repair`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteRepair {
+    /// The session of the repaired packet.
+    pub session_id: u64,
+    /// The next hop that was not a neighbor.
+    pub missing: NodeId,
+    /// The hops before the repair.
+    pub original: Vec<NodeId>,
+    /// The hops after the repair.
+    pub repaired: Vec<NodeId>,
+}
+
+/// What a drone shares with its [`TopologyHandle`]s.
+#[derive(Debug)]
+struct Learned {
+    graph: NetworkGraph,
+    learning: bool,
+    route_repair: bool,
+    repairs: VecDeque<RouteRepair>,
+}
+
+impl Default for Learned {
+    fn default() -> Self {
+        Self { graph: NetworkGraph::new(), learning: true, route_repair: false, repairs: VecDeque::new() }
+    }
+}
+
+/// A [`NetworkGraph`] shared between a drone learning it from the flood responses it relays and
+/// the controller reading it, see [`crate::BetterCallDrone::learn_topology`]. Through the same
+/// handle the controller can let the drone repair routes with the graph, and read the repairs.
+#[derive(Debug, Clone, Default)]
+pub struct TopologyHandle(Arc<Mutex<Learned>>);
+
+impl TopologyHandle {
+    /// Creates a handle to an empty graph, with learning on and route repair off.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Finds a route with the fewest hops in the learned graph without copying it, see
+    /// [`NetworkGraph::shortest_route_via`].
+    ///
+    /// # Returns
+    /// The hops from `from` to `to`, both included, or `None` if `to` cannot be reached.
+    #[must_use]
+    pub fn shortest_route_via(&self, from: NodeId, neighbors: impl IntoIterator<Item = NodeId>, to: NodeId) -> Option<Vec<NodeId>> {
+        self.learned().graph.shortest_route_via(from, neighbors, to)
+    }
+
+    /// Returns a copy of the graph learned so far.
+    #[must_use]
+    pub fn snapshot(&self) -> NetworkGraph {
+        self.learned().graph.clone()
+    }
+
+    /// Adds the nodes and links of a `path_trace` to the graph, if learning is on.
+    ///
+    /// # Returns
+    /// `true` if the graph changed.
+    pub fn add_path(&self, path_trace: &[(NodeId, NodeType)]) -> bool {
+        let mut learned = self.learned();
+        learned.learning && learned.graph.add_path(path_trace)
+    }
+
+    /// Turns learning on or off. While it is off the drone ignores the flood responses it relays,
+    /// and keeps the graph learned so far.
+    pub fn set_learning(&self, enabled: bool) {
+        self.learned().learning = enabled;
+    }
+
+    /// Returns whether learning is on.
+    #[must_use]
+    pub fn learning(&self) -> bool {
+        self.learned().learning
+    }
+
+    /// Forgets the graph learned so far.
+    pub fn clear(&self) {
+        self.learned().graph = NetworkGraph::new();
+    }
+
+    /// Turns route repair on or off: when the next hop of a packet is not a neighbor, the drone
+    /// looks for a detour in the learned graph instead of sending `ErrorInRouting`.
+    pub fn set_route_repair(&self, enabled: bool) {
+        self.learned().route_repair = enabled;
+    }
+
+    /// Returns whether route repair is on.
+    #[must_use]
+    pub fn route_repair(&self) -> bool {
+        self.learned().route_repair
+    }
+
+    /// Records a route repair, forgetting the oldest one beyond [`REPAIR_LOG_CAPACITY`].
+    pub fn record_repair(&self, repair: RouteRepair) {
+        let mut learned = self.learned();
+        if learned.repairs.len() == REPAIR_LOG_CAPACITY {
+            learned.repairs.pop_front();
+        }
+        learned.repairs.push_back(repair);
+    }
+
+    /// Returns the last route repairs, oldest first.
+    #[must_use]
+    pub fn repairs(&self) -> Vec<RouteRepair> {
+        self.learned().repairs.iter().cloned().collect()
+    }
+
+    fn learned(&self) -> MutexGuard<'_, Learned> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The difference between two views of the network.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopologyChange {
+    pub added_nodes: BTreeSet<NodeId>,
+    pub removed_nodes: BTreeSet<NodeId>,
+    pub added_links: BTreeSet<(NodeId, NodeId)>,
+    pub removed_links: BTreeSet<(NodeId, NodeId)>,
+}
+
+impl TopologyChange {
+    /// Computes what changed from `old` to `new`.
+    #[must_use]
+    pub fn between(old: &NetworkGraph, new: &NetworkGraph) -> Self {
+        let old_nodes: BTreeSet<NodeId> = old.nodes.keys().copied().collect();
+        let new_nodes: BTreeSet<NodeId> = new.nodes.keys().copied().collect();
+        let (old_links, new_links) = (old.links(), new.links());
+        Self {
+            added_nodes: new_nodes.difference(&old_nodes).copied().collect(),
+            removed_nodes: old_nodes.difference(&new_nodes).copied().collect(),
+            added_links: new_links.difference(&old_links).copied().collect(),
+            removed_links: old_links.difference(&new_links).copied().collect(),
+        }
+    }
+
+    /// Returns `true` if nothing changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty() && self.removed_nodes.is_empty() && self.added_links.is_empty() && self.removed_links.is_empty()
+    }
+}
+
+/// A flood whose responses are still being collected.
+#[derive(Debug, Clone)]
+struct Flood {
+    flood_id: u64,
+    started: Instant,
+    graph: NetworkGraph,
+}
+
+/// Builds the view of the network of a client or server from the `FloodResponse`s it receives.
+///
+/// Each flood collects responses for a time window. Responses are merged into the known graph as
+/// soon as they arrive, so routes can be computed during a flood; when the window closes the known
+/// graph is replaced by what the flood observed and the change since the previous flood is reported.
+/// Routes learned outside of floods, see [`TopologyDiscovery::add_route`], are kept across floods
+/// until one of their links or nodes is removed.
+#[derive(Debug, Clone)]
+pub struct TopologyDiscovery {
+    node_id: NodeId,
+    node_type: NodeType,
+    window: Duration,
+    first_flood_id: u64,
+    next_flood_id: u64,
+    current: Option<Flood>,
+    last_flood: Option<Instant>,
+    graph: NetworkGraph,
+    /// The routes learned outside of floods, merged into the graph when a flood closes.
+    routes: NetworkGraph,
+    /// The graph at the end of the previous flood, to compute changes.
+    previous: NetworkGraph,
+}
+
+impl TopologyDiscovery {
+    /// Creates a discovery for a node that knows nothing of the network yet.
+    ///
+    /// # Parameters
+    /// - `node_id`: The ID of the node running the discovery.
+    /// - `node_type`: The type of the node, `Client` or `Server`.
+    /// - `window`: How long responses are collected after starting a flood.
+    #[must_use]
+    pub fn new(node_id: NodeId, node_type: NodeType, window: Duration) -> Self {
+        let mut graph = NetworkGraph::new();
+        graph.add_node(node_id, node_type);
+        // Drones remember every (flood_id, initiator) pair, so ids must stay fresh across restarts.
+        let first_flood_id = random::<u64>() >> 1;
+        Self {
+            node_id,
+            node_type,
+            window,
+            first_flood_id,
+            next_flood_id: first_flood_id,
+            current: None,
+            last_flood: None,
+            previous: graph.clone(),
+            graph,
+            routes: NetworkGraph::new(),
+        }
+    }
+
+    /// Starts a new flood with a fresh `flood_id`, closing the current one if any.
+    ///
+    /// # Parameters
+    /// - `now`: The current time.
+    ///
+    /// # Returns
+    /// The `FloodRequest` to send to every neighbor.
+    pub fn start_flood(&mut self, now: Instant) -> FloodRequest {
+        if self.current.is_some() {
+            self.finish_flood();
+        }
+        let flood_id = self.next_flood_id;
+        self.next_flood_id += 1;
+        let mut graph = NetworkGraph::new();
+        graph.add_node(self.node_id, self.node_type);
+        self.current = Some(Flood { flood_id, started: now, graph });
+        self.last_flood = Some(now);
+        FloodRequest {
+            flood_id,
+            initiator_id: self.node_id,
+            path_trace: vec![(self.node_id, self.node_type)],
+        }
+    }
+
+    /// Merges a `FloodResponse` into the known graph.
+    ///
+    /// Responses to older floods of this node still update the known graph, responses to
+    /// floods of other nodes are ignored.
+    ///
+    /// # Returns
+    /// `true` if the response belongs to a flood of this node.
+    pub fn handle_response(&mut self, response: &FloodResponse) -> bool {
+        if !(self.first_flood_id..self.next_flood_id).contains(&response.flood_id)
+            || response.path_trace.first().map(|(id, _)| *id) != Some(self.node_id) {
+            return false;
+        }
+        if let Some(flood) = self.current.as_mut().filter(|f| f.flood_id == response.flood_id) {
+            flood.graph.add_path(&response.path_trace);
+        }
+        self.graph.add_path(&response.path_trace);
+        true
+    }
+
+    /// Closes the current flood if its window has elapsed.
+    ///
+    /// # Parameters
+    /// - `now`: The current time.
+    ///
+    /// # Returns
+    /// The change since the previous flood, if a flood has just been closed.
+    pub fn poll(&mut self, now: Instant) -> Option<TopologyChange> {
+        let flood = self.current.as_ref()?;
+        if now.duration_since(flood.started) < self.window {
+            return None;
+        }
+        Some(self.finish_flood())
+    }
+
+    /// Closes the current flood and reports the change since the previous one.
+    fn finish_flood(&mut self) -> TopologyChange {
+        if let Some(flood) = self.current.take() {
+            self.graph = flood.graph;
+            self.graph.merge(&self.routes);
+        }
+        let change = TopologyChange::between(&self.previous, &self.graph);
+        self.previous = self.graph.clone();
+        change
+    }
+
+    /// Returns `true` if a flood is collecting responses.
+    #[must_use]
+    pub fn is_flooding(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Returns when the last flood was started.
+    #[must_use]
+    pub fn last_flood(&self) -> Option<Instant> {
+        self.last_flood
+    }
+
+    /// Returns the known graph.
+    #[must_use]
+    pub fn graph(&self) -> &NetworkGraph {
+        &self.graph
+    }
+
+    /// Returns the known graph, to add what the node learned outside of floods.
+    ///
+    /// Edits made here are lost when the current flood closes, use [`Self::add_route`],
+    /// [`Self::remove_link`] and [`Self::remove_node`] to keep them.
+    pub fn graph_mut(&mut self) -> &mut NetworkGraph {
+        &mut self.graph
+    }
+
+    /// Adds a route a packet took, e.g. the route of an ack, to the known graph, see
+    /// [`NetworkGraph::add_route`]. The route is kept when floods close, until one of its links or
+    /// nodes is removed.
+    pub fn add_route(&mut self, hops: &[NodeId]) {
+        self.graph.add_route(hops);
+        self.routes.add_route(hops);
+    }
+
+    /// Removes a broken link from the known graph, from what the current flood observed and
+    /// from the learned routes, so closing the flood does not bring it back.
+    pub fn remove_link(&mut self, a: NodeId, b: NodeId) {
+        self.graph.remove_link(a, b);
+        self.routes.remove_link(a, b);
+        if let Some(flood) = self.current.as_mut() {
+            flood.graph.remove_link(a, b);
+        }
+    }
+
+    /// Removes a node that cannot be used from the known graph, from what the current flood
+    /// observed and from the learned routes, so closing the flood does not bring it back.
+    pub fn remove_node(&mut self, node_id: NodeId) {
+        self.graph.remove_node(node_id);
+        self.routes.remove_node(node_id);
+        if let Some(flood) = self.current.as_mut() {
+            flood.graph.remove_node(node_id);
+        }
+    }
+}
@@ -1,14 +1,22 @@
 #[allow(unused)]
 use crossbeam_channel::{select_biased, Receiver, Sender};
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::env;
+use std::time::Instant;
 use colored::Colorize;
-use rand::random;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use wg_2024::controller::{DroneCommand, DroneEvent};
 use wg_2024::controller::DroneEvent::{PacketDropped, PacketSent};
 use wg_2024::drone::Drone;
 use wg_2024::network::{NodeId, SourceRoutingHeader};
-use wg_2024::packet::{FloodRequest, Fragment, Nack, NackType, NodeType, Packet, PacketType};
+use wg_2024::packet::{FloodRequest, Nack, NackType, NodeType, Packet, PacketType};
+use crate::discovery::{RouteRepair, TopologyHandle};
+use crate::flood_limit::{FloodLimitHandle, ThrottleAction};
+
+/// The most drones a route repair puts in place of a missing next hop.
+pub const MAX_DETOUR: usize = 3;
 
 pub struct BetterCallDrone {
     id: NodeId,
@@ -22,8 +30,17 @@ pub struct BetterCallDrone {
     ///
     /// - `received_flood_ids`: A `HashSet` that contains pairs of `flood_id` and `initiator_id`
     ///   to track flood requests that have already been processed.
+    /// - `dropped_flood_ids`: The pairs of the floods dropped by the flood limit, whose duplicates
+    ///   are dropped too.
+    /// - `rng`: The generator deciding which fragments are dropped, see [`BetterCallDrone::set_seed`].
+    /// - `topology`: The graph learned from the relayed flood responses, see [`BetterCallDrone::learn_topology`].
+    /// - `flood_limit`: The rate limit of new floods per initiator, see [`BetterCallDrone::limit_floods`].
     /// - `debug`: A flag indicating whether debug mode is enabled.
     received_flood_ids: HashSet<(u64, NodeId)>,
+    dropped_flood_ids: HashSet<(u64, NodeId)>,
+    rng: StdRng,
+    topology: Option<TopologyHandle>,
+    flood_limit: Option<FloodLimitHandle>,
     debug: bool,
 }
 
@@ -44,6 +61,9 @@ impl Drone for BetterCallDrone {
     /// # Notes
     /// - **Debug Mode**: The `debug` field is automatically enabled if the environment variable `BCD_DEBUG` is set.
     /// - **Flood Tracking**: The `received_flood_ids` field is initialized as an empty `HashSet`.
+    /// - **Drops**: The `rng` field is seeded from the operating system.
+    /// - **Topology**: The drone learns nothing until [`BetterCallDrone::learn_topology`] is called.
+    /// - **Flood Limit**: Every new flood is forwarded until [`BetterCallDrone::limit_floods`] is called.
     fn new(
         id: NodeId,
         controller_send: Sender<DroneEvent>,
@@ -62,51 +82,96 @@ impl Drone for BetterCallDrone {
             pdr,
 
             received_flood_ids: HashSet::new(),
+            dropped_flood_ids: HashSet::new(),
+            rng: StdRng::from_entropy(),
+            topology: None,
+            flood_limit: None,
             debug: debug_check,
         }
     }
 
     /// Starts the main run loop for the drone. It listens for commands and packets, and processes them accordingly.
+    ///
+    /// If the SC disconnects, the drone keeps forwarding packets until its neighbors disconnect too.
+    /// If every neighbor disconnects, it keeps handling commands until `Crash` or until the SC disconnects.
     fn run(&mut self) {
-        self.log(&format!("{}","Successfully spawned and started".green()));
+        self.log(format_args!("{}","Successfully spawned and started".green()));
         loop {
             select_biased! {
                 recv(self.controller_recv) -> command => {
-                    if let Ok(command) = command {
-                        if let DroneCommand::Crash = command {
-                            self.log(&format!("{}","Received Crash Command from SC".cyan()));
-                            self.crash_drone();
-                            break;
+                    let Ok(command) = command else {
+                        self.log(format_args!("{}","SC disconnected, forwarding the remaining packets".red()));
+                        while let Ok(packet) = self.packet_recv.recv() {
+                            self.handle_packet(packet);
                         }
-                        self.handle_command(command);
+                        break;
+                    };
+                    if self.execute_command(command) {
+                        break;
                     }
                 }
                 recv(self.packet_recv) -> packet => {
-                    if let Ok(packet) = packet {
-                        self.handle_packet(packet);
-                    }
+                    let Ok(packet) = packet else {
+                        self.log(format_args!("{}","Packet channel disconnected, waiting for commands".red()));
+                        while let Ok(command) = self.controller_recv.recv() {
+                            if self.execute_command(command) {
+                                break;
+                            }
+                        }
+                        break;
+                    };
+                    self.handle_packet(packet);
                 },
             }
         }
-        self.log(&format!("{}","Successfully stopped".green()));
+        self.log(format_args!("{}","Successfully stopped".green()));
     }
 }
 
 impl BetterCallDrone {
-    /// Handles incoming packets based on their type.
+    /// Handles incoming packets based on their type. The packet is moved along the forwarding
+    /// path without being rebuilt.
     ///
     /// # Parameters
     /// - `packet`: The incoming packet to process.
     pub fn handle_packet(&mut self, packet: Packet) {
         self.log_received(&packet);
         match packet.pack_type {
-            PacketType::Nack(_) | PacketType::Ack(_) | PacketType::FloodResponse(_) => self.forward_packet(packet, 0),
-            PacketType::MsgFragment(fragment) => self.handle_fragment(&packet.routing_header, packet.session_id, &fragment),
+            PacketType::MsgFragment(_) => self.handle_fragment(packet),
             PacketType::FloodRequest(flood_request) => self.handle_ndp(flood_request, packet.session_id),
+            PacketType::FloodResponse(ref flood_response) => {
+                if let Some(topology) = &self.topology {
+                    topology.add_path(&flood_response.path_trace);
+                }
+                self.forward_packet(packet);
+            }
+            PacketType::Nack(_) | PacketType::Ack(_) => self.forward_packet(packet),
         }
     }
 
-    /// Processes commands sent by the simulation controller.
+    /// Makes the drone learn the network from the `path_trace` of every flood response it
+    /// receives. Learning is off by default.
+    ///
+    /// # Returns
+    /// A handle to the learned graph, to read it from another thread after the drone has been
+    /// moved to its own, and to turn route repair on. Calling this again returns a handle to
+    /// the same graph.
+    pub fn learn_topology(&mut self) -> TopologyHandle {
+        self.topology.get_or_insert_with(TopologyHandle::new).clone()
+    }
+
+    /// Makes the drone check every new flood request against a per-initiator token bucket,
+    /// once a [`crate::flood_limit::FloodPolicy`] is set through the returned handle.
+    ///
+    /// # Returns
+    /// A handle to set the policy and read how many floods were throttled from another thread.
+    /// Calling this again returns a handle to the same limit.
+    pub fn limit_floods(&mut self) -> FloodLimitHandle {
+        self.flood_limit.get_or_insert_with(FloodLimitHandle::new).clone()
+    }
+
+    /// Processes commands sent by the simulation controller. `Crash` only drains the queued
+    /// packets: stopping is up to the caller, like [`Drone::run`] does.
     ///
     /// # Parameters
     /// - `command`: The command to handle.
@@ -114,18 +179,31 @@ impl BetterCallDrone {
         match command {
             DroneCommand::AddSender(node_id, sender) => self.add_sender(node_id, sender),
             DroneCommand::SetPacketDropRate(pdr) => self.set_pdr(pdr),
-            DroneCommand::Crash => unreachable!(),
+            DroneCommand::Crash => self.crash_drone(),
             DroneCommand::RemoveSender(node_id) => self.remove_sender(node_id),
         }
     }
 
+    /// Handles a command received by the run loop.
+    ///
+    /// # Returns
+    /// `true` if the command was `Crash` and the drone must stop.
+    fn execute_command(&mut self, command: DroneCommand) -> bool {
+        let crash = matches!(command, DroneCommand::Crash);
+        if crash {
+            self.log(format_args!("{}","Received Crash Command from SC".cyan()));
+        }
+        self.handle_command(command);
+        crash
+    }
+
     /// The following functions handle the debug functionality -------------------------------------
     ///
     /// Prints to console if debug mode is enabled.
     ///
     /// # Parameters
     /// - `message`: The message to print.
-    fn log(&self, message: &str) {
+    fn log(&self, message: impl std::fmt::Display) {
         if self.debug {
             let m = format!("{} {message}",
                             format!("[BCDRONE #{}]", self.id).purple(),);
@@ -138,6 +216,9 @@ impl BetterCallDrone {
     /// # Parameters
     /// - `packet`: The packet received.
     fn log_received(&self, packet: &Packet) {
+        if !self.debug {
+            return;
+        }
         let message = format!("({}:{}) | {} -> {} | {}",
                               packet.session_id,
                               packet.get_fragment_index(),
@@ -150,7 +231,7 @@ impl BetterCallDrone {
                                   PacketType::MsgFragment(_) => "Message".cyan(),
                               }, packet,
         );
-        self.log(&message);
+        self.log(message);
     }
 
     /// Generates the message to print in case of nack.
@@ -161,6 +242,9 @@ impl BetterCallDrone {
     /// - `fragment_index`: fragment index of the packet.
     /// - `through_sc`: check if the nack is sent through SC or not.
     fn log_nack(&self, nack_type: NackType, session_id: u64, fragment_index: u64, through_sc: bool) {
+        if !self.debug {
+            return;
+        }
         let mut sent = "SentNack";
         if through_sc {sent = "SentNack through SC";};
         let message = format!("({}:{}) | {} -> {}",
@@ -169,7 +253,7 @@ impl BetterCallDrone {
                               sent.red(),
                               format!("{nack_type:?}").red(),
         );
-        self.log(&message);
+        self.log(message);
     }
 
     /// Generates the message to print in case of forwarder packet
@@ -177,6 +261,9 @@ impl BetterCallDrone {
     /// # Parameters
     /// - `packet`: The packet received.
     fn log_forwarded(&self, packet: &Packet) {
+        if !self.debug {
+            return;
+        }
         let message = format!("({}:{}) | {} -> {} | {}",
                               packet.session_id,
                               packet.get_fragment_index(),
@@ -189,67 +276,137 @@ impl BetterCallDrone {
                                   PacketType::MsgFragment(_) => "Message".cyan(),
                               }, packet,
         );
-        self.log(&message);
+        self.log(message);
     }
 
 
     /// The following functions handle the processing of packets -----------------------------------
     ///
-    /// Forwards a packet to the next node in its routing path.
+    /// Forwards a packet to the next node in its routing path, or answers with a nack if the
+    /// drone is not the current hop, is the last hop or cannot reach the next hop.
+    ///
+    /// The packet is moved to the neighbor: the only copy is the one in the `PacketSent` event.
     ///
     /// # Parameters
     /// - `packet`: The packet to forward.
-    /// - `fragment_index`: The index of the fragment being forwarded.
-    pub fn forward_packet(&mut self, mut packet: Packet, fragment_index: u64) {
-        if self.id == packet.routing_header.hops[packet.routing_header.hop_index] {
-            if let Some(next_hop) = packet.routing_header.hops.get(packet.routing_header.hop_index + 1) {
-                packet.routing_header.hop_index += 1;
-                if let Some(sender) = self.packet_send.get(next_hop) {
-                    if let Err(e) = sender.send(packet.clone()) {
-                        self.log(&format!("{} {}","Error in Forwarding Packet", e));
-                    } else {
-                        self.log_forwarded(&packet);
-                    }
+    pub fn forward_packet(&mut self, packet: Packet) {
+        match self.next_hop(&packet) {
+            Ok(next_hop) => self.send_to_next_hop(packet, next_hop),
+            Err(NackType::ErrorInRouting(missing)) => self.repair_route(packet, missing),
+            Err(nack_type) => self.send_nack(packet, nack_type),
+        }
+    }
 
-                    if let Err(e) = self.controller_send.send(PacketSent(packet.clone())) {
-                        self.log(&format!("{} {}","Error in Sending `PacketSent` to SC: ", e));
-                    } else {
-                        self.log(&format!("{}","Event PacketSent sent to SC".green()));
-                    }
+    /// Handles a fragment packet, deciding whether to forward or drop it.
+    ///
+    /// The drop is drawn once per fragment but only applies to fragments the drone could
+    /// forward: as in the protocol, `UnexpectedRecipient`, `DestinationIsDrone` and
+    /// `ErrorInRouting` take precedence over `Dropped`. A dropped fragment whose next hop is not
+    /// a neighbor is answered with `ErrorInRouting` and its route is never repaired.
+    ///
+    /// # Parameters
+    /// - `packet`: The fragment packet to handle.
+    pub fn handle_fragment(&mut self, packet: Packet) {
+        let drop = self.should_drop_packet();
+        match self.next_hop(&packet) {
+            Err(NackType::ErrorInRouting(missing)) if !drop => self.repair_route(packet, missing),
+            Err(nack_type) => self.send_nack(packet, nack_type),
+            Ok(_) if drop => {
+                let nack = self.nack(&packet, NackType::Dropped);
+                self.send_back(nack, NackType::Dropped);
+                if let Err(e) = self.controller_send.send(PacketDropped(packet)) {
+                    self.log(format_args!("{} {}","Error in Sending `PacketDropped` to SC: ", e));
                 } else {
-                    self.send_nack(packet.clone(), fragment_index, NackType::ErrorInRouting(*next_hop));
+                    self.log(format_args!("{}","Event PacketDropped sent to SC".green()));
                 }
-            } else {
-                self.send_nack(packet, fragment_index, NackType::DestinationIsDrone);
             }
+            Ok(next_hop) => self.send_to_next_hop(packet, next_hop),
+        }
+    }
+
+    /// Checks that the drone is the current hop of a packet and finds the neighbor to send it to.
+    ///
+    /// # Returns
+    /// The neighbor, or the type of the nack to send.
+    fn next_hop(&self, packet: &Packet) -> Result<NodeId, NackType> {
+        let hop_index = packet.routing_header.hop_index;
+        if packet.routing_header.hops.get(hop_index) != Some(&self.id) {
+            return Err(NackType::UnexpectedRecipient(self.id));
+        }
+        let Some(&next_hop) = packet.routing_header.hops.get(hop_index + 1) else {
+            return Err(NackType::DestinationIsDrone);
+        };
+        if self.packet_send.contains_key(&next_hop) {
+            Ok(next_hop)
         } else {
-            self.send_nack(packet, fragment_index, NackType::UnexpectedRecipient(self.id));
+            Err(NackType::ErrorInRouting(next_hop))
         }
     }
 
-    /// Handles a fragment packet, deciding whether to forward or drop it.
+    /// Rewrites the route of a packet whose next hop is not a neighbor, with the shortest detour
+    /// of at most [`MAX_DETOUR`] drones in the learned graph to the hop after the missing one,
+    /// or to the missing hop if it is the destination, and sends it. The repair is recorded in
+    /// the [`TopologyHandle`] and the SC sees the new route in the `PacketSent` event; the
+    /// destination acks along the new route, so the source learns the detour from the ack.
+    ///
+    /// Without route repair or a detour, the packet is answered with `ErrorInRouting`.
+    ///
+    /// # Parameters
+    /// - `packet`: The packet, whose current hop is the drone.
+    /// - `missing`: The next hop, which is not a neighbor.
+    fn repair_route(&mut self, mut packet: Packet, missing: NodeId) {
+        let hop_index = packet.routing_header.hop_index;
+        let hops = &packet.routing_header.hops;
+        let (target, resume) = match hops.get(hop_index + 2) {
+            Some(&after_missing) => (after_missing, hop_index + 3),
+            None => (missing, hop_index + 2),
+        };
+        let detour = self.topology.as_ref()
+            .filter(|topology| topology.route_repair())
+            .and_then(|topology| topology.shortest_route_via(self.id, self.packet_send.keys().copied(), target))
+            .filter(|detour| detour.len() >= 2 && detour.len() - 2 <= MAX_DETOUR);
+        let (Some(detour), Some(topology)) = (detour, &self.topology) else {
+            self.send_nack(packet, NackType::ErrorInRouting(missing));
+            return;
+        };
+
+        let mut repaired = hops[..hop_index].to_vec();
+        repaired.extend(&detour);
+        repaired.extend(&hops[resume..]);
+        let original = std::mem::replace(&mut packet.routing_header.hops, repaired.clone());
+        self.log(format_args!("{} {:?} -> {:?}","Repaired route".yellow(), original, repaired));
+        topology.record_repair(RouteRepair { session_id: packet.session_id, missing, original, repaired });
+        self.send_to_next_hop(packet, detour[1]);
+    }
+
+    /// Sends a packet to its next hop, or answers with `ErrorInRouting` if the send fails.
     ///
     /// # Parameters
-    /// - `routing_header`: The routing header of the packet.
-    /// - `session_id`: The session ID of the packet.
-    /// - `fragment`: The fragment to handle.
-    pub fn handle_fragment(&mut self, routing_header : &SourceRoutingHeader, session_id : u64, fragment: &Fragment) {
-        let rh = routing_header.clone();
-        let packet = Packet {
-            routing_header: rh,
-            session_id,
-            pack_type: PacketType::MsgFragment(fragment.clone()),
+    /// - `packet`: The packet, whose current hop is the drone.
+    /// - `next_hop`: The neighbor returned by [`BetterCallDrone::next_hop`].
+    fn send_to_next_hop(&mut self, mut packet: Packet, next_hop: NodeId) {
+        let Some(sender) = self.packet_send.get(&next_hop) else {
+            self.send_nack(packet, NackType::ErrorInRouting(next_hop));
+            return;
         };
-        if self.should_drop_packet() {
-            self.send_nack(packet.clone(), fragment.fragment_index, NackType::Dropped);
-            if let Err(e) = self.controller_send.send(PacketDropped(packet.clone())) {
-                self.log(&format!("{} {}","Error in Sending `PacketDropped` to SC: ", e));
-            } else {
-                self.log(&format!("{}","Event PacketDropped sent to SC".green()));
+        let hop_index = packet.routing_header.hop_index;
+        packet.routing_header.hop_index += 1;
+        let event = packet.clone();
+        match sender.send(packet) {
+            Ok(()) => {
+                self.log_forwarded(&event);
+                if let Err(e) = self.controller_send.send(PacketSent(event)) {
+                    self.log(format_args!("{} {}","Error in Sending `PacketSent` to SC: ", e));
+                } else {
+                    self.log(format_args!("{}","Event PacketSent sent to SC".green()));
+                }
+            }
+            Err(e) => {
+                self.log(format_args!("{} {}","Error in Forwarding Packet", e));
+                let mut packet = e.into_inner();
+                packet.routing_header.hop_index = hop_index;
+                self.send_nack(packet, NackType::ErrorInRouting(next_hop));
             }
-        } else {
-            let index = fragment.fragment_index;
-            self.forward_packet(packet, index);
         }
     }
 
@@ -258,39 +415,78 @@ impl BetterCallDrone {
     /// # Returns
     /// `true` if the packet should be dropped, otherwise `false`.
     #[must_use]
-    pub fn should_drop_packet(&self) -> bool {
-        random::<f32>() <= self.pdr
+    pub fn should_drop_packet(&mut self) -> bool {
+        Self::is_drop(self.rng.gen::<f32>(), self.pdr)
+    }
+
+    /// Decides a drop from a uniform draw in `[0, 1)`: a PDR of 0 never drops and a PDR of 1
+    /// always drops.
+    ///
+    /// # Parameters
+    /// - `draw`: The random draw.
+    /// - `pdr`: The Packet Drop Rate.
+    #[must_use]
+    pub fn is_drop(draw: f32, pdr: f32) -> bool {
+        draw < pdr
+    }
+
+    /// Seeds the generator deciding which fragments are dropped, so that a run can be repeated.
+    ///
+    /// # Parameters
+    /// - `seed`: The seed.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
     }
 
     /// Handles a flood request packet (Network Discovery Protocol).
     ///
+    /// The request is cloned for every neighbor but the last one, which gets the original.
+    /// New floods over the limit of their initiator are dropped or answered right away, see
+    /// [`BetterCallDrone::limit_floods`].
+    ///
     /// # Parameters
     /// - `flood_request`: The flood request to process.
     /// - `session_id`: The session ID of the request.
     pub fn handle_ndp(&mut self, mut flood_request: FloodRequest, session_id: u64) {
-        let prev_node = flood_request.path_trace.last().unwrap().0;
+        let prev_node = flood_request.path_trace.last().map(|(id, _)| *id);
         flood_request.increment(self.id, NodeType::Drone);
-        if self.received_flood_ids.contains(&(flood_request.flood_id, flood_request.initiator_id)) {
-            self.forward_flood_response(&mut flood_request, session_id);
-        } else {
-            self.received_flood_ids.insert((flood_request.flood_id, flood_request.initiator_id));
-            let neighbors: Vec<(NodeId, Sender<Packet>)> = self.packet_send
-                .iter()
-                .filter(|(&neighbor_id, _)| neighbor_id != prev_node)
-                .map(|(&neighbor_id, sender)| (neighbor_id, sender.clone()))
-                .collect();
-            if neighbors.is_empty() {
-                self.forward_flood_response(&mut flood_request, session_id);
-            } else {
-                for (_n_id, n_send) in neighbors {
-                    let packet = Packet::new_flood_request(SourceRoutingHeader::empty_route(), session_id, flood_request.clone());
-                    if let Err(e) = n_send.send(packet.clone()) {
-                        self.log(&format!("{} {}","Error in Sending FloodRequest: ", e));
-                    } else {
-                        self.log_forwarded(&packet);
-                    }
+        let flood = (flood_request.flood_id, flood_request.initiator_id);
+        if !self.received_flood_ids.insert(flood) {
+            if !self.dropped_flood_ids.contains(&flood) {
+                self.forward_flood_response(&flood_request, session_id);
+            }
+            return;
+        }
+        let initiator_id = flood_request.initiator_id;
+        if let Some(action) = self.flood_limit.as_ref().and_then(|limit| limit.throttle(initiator_id, Instant::now())) {
+            self.log(format_args!("{} {} from {} ({:?})","Throttled flood".yellow(), flood_request.flood_id, initiator_id, action));
+            match action {
+                ThrottleAction::Respond => self.forward_flood_response(&flood_request, session_id),
+                ThrottleAction::Drop => {
+                    self.dropped_flood_ids.insert(flood);
                 }
             }
+            return;
+        }
+        let neighbors: Vec<&Sender<Packet>> = self.packet_send
+            .iter()
+            .filter(|(&neighbor_id, _)| Some(neighbor_id) != prev_node)
+            .map(|(_, sender)| sender)
+            .collect();
+        let Some((last, others)) = neighbors.split_last() else {
+            self.forward_flood_response(&flood_request, session_id);
+            return;
+        };
+
+        let packet = Packet::new_flood_request(SourceRoutingHeader::empty_route(), session_id, flood_request);
+        self.log_forwarded(&packet);
+        for sender in others {
+            if let Err(e) = sender.send(packet.clone()) {
+                self.log(format_args!("{} {}","Error in Sending FloodRequest: ", e));
+            }
+        }
+        if let Err(e) = last.send(packet) {
+            self.log(format_args!("{} {}","Error in Sending FloodRequest: ", e));
         }
     }
 
@@ -299,58 +495,91 @@ impl BetterCallDrone {
     /// # Parameters
     /// - `flood_request`: The original `FloodRequest`.
     /// - `session_id`: The session ID of the request.
-    pub fn forward_flood_response(&mut self, flood_request: &mut FloodRequest, session_id: u64) {
+    pub fn forward_flood_response(&mut self, flood_request: &FloodRequest, session_id: u64) {
         let packet = flood_request.generate_response(session_id);
-        self.forward_packet(packet, 0);
+        self.forward_packet(packet);
     }
 
     /// Generates and Sends a NACK through the reversed path or through SC.
     ///
+    /// Acks, nacks and flood responses are given to the SC; when their next hop cannot be reached
+    /// their `hop_index` points to that hop, as if the drone had forwarded them. For other packets
+    /// a nack is routed back along the hops before the drone; it goes through the SC if the
+    /// previous hop cannot be reached.
+    ///
     /// # Parameters
     /// - `packet`: The original packet causing the NACK.
-    /// - `fragment_index`: The index of the fragment.
     /// - `nack_type`: The type of NACK being sent.
-    pub fn send_nack(&mut self, mut packet: Packet, fragment_index: u64, nack_type: NackType) {
+    pub fn send_nack(&mut self, mut packet: Packet, nack_type: NackType) {
         match packet.pack_type {
             PacketType::Nack(_) | PacketType::Ack(_) | PacketType::FloodResponse(_) => {
-                if let Err(e) = self.controller_send.send(DroneEvent::ControllerShortcut(packet.clone())) {
-                    self.log(&format!("{} {}","Error in Sending Nack through SC: ", e));
-                } else {
-                    self.log_nack(nack_type, packet.session_id, fragment_index, true);
+                if let NackType::ErrorInRouting(_) = nack_type {
+                    packet.routing_header.hop_index += 1;
                 }
+                self.send_to_controller(packet, nack_type);
             }
-            _ => {
-                packet.routing_header.hops[packet.routing_header.hop_index] = self.id;
-                let nack = Nack { fragment_index, nack_type };
-                let self_index = packet.routing_header
-                    .hops
-                    .iter()
-                    .position(|&hop| hop == self.id)
-                    .unwrap_or(0);
-                let reversed_hops: Vec<NodeId> = packet.routing_header.hops[..=self_index]
-                    .iter()
-                    .copied()
-                    .rev()
-                    .collect();
-
-                if let Some(sender) = self.packet_send.get(&reversed_hops[1]) {
-                    if let Err(e) = sender.send(Packet {
-                        pack_type: PacketType::Nack(nack.clone()),
-                        routing_header: SourceRoutingHeader {
-                            hop_index: 1,
-                            hops: reversed_hops,
-                        },
-                        session_id: packet.session_id,
-                    }) {
-                        self.log(&format!("{} {}","Error in Sending Nack: ", e));
-                    } else {
-                        self.log_nack(nack_type, packet.session_id, fragment_index, false);
-                    }
-                }
+            PacketType::MsgFragment(_) | PacketType::FloodRequest(_) => {
+                let nack = self.nack(&packet, nack_type);
+                self.send_back(nack, nack_type);
+            }
+        }
+    }
+
+    /// Builds the nack of a packet, routed back along the hops before the drone.
+    ///
+    /// # Parameters
+    /// - `packet`: The original packet causing the NACK.
+    /// - `nack_type`: The type of NACK.
+    fn nack(&self, packet: &Packet, nack_type: NackType) -> Packet {
+        let hops = &packet.routing_header.hops;
+        let previous_hops = &hops[..packet.routing_header.hop_index.min(hops.len())];
+        let reversed_hops: Vec<NodeId> = std::iter::once(self.id)
+            .chain(previous_hops.iter().rev().copied())
+            .collect();
+        Packet {
+            pack_type: PacketType::Nack(Nack { fragment_index: packet.get_fragment_index(), nack_type }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: reversed_hops,
+            },
+            session_id: packet.session_id,
+        }
+    }
+
+    /// Sends a nack to the previous hop, or through the SC if the previous hop cannot be reached.
+    ///
+    /// # Parameters
+    /// - `nack`: The nack built by [`BetterCallDrone::nack`].
+    /// - `nack_type`: The type of the nack.
+    fn send_back(&self, nack: Packet, nack_type: NackType) {
+        let Some(sender) = nack.routing_header.hops.get(1).and_then(|hop| self.packet_send.get(hop)) else {
+            self.send_to_controller(nack, nack_type);
+            return;
+        };
+        let (session_id, fragment_index) = (nack.session_id, nack.get_fragment_index());
+        match sender.send(nack) {
+            Ok(()) => self.log_nack(nack_type, session_id, fragment_index, false),
+            Err(e) => {
+                self.log(format_args!("{} {}","Error in Sending Nack: ", e));
+                self.send_to_controller(e.into_inner(), nack_type);
             }
         }
     }
 
+    /// Sends a packet that cannot be delivered through the network to the SC.
+    ///
+    /// # Parameters
+    /// - `packet`: The packet.
+    /// - `nack_type`: The reason why the packet cannot be delivered.
+    fn send_to_controller(&self, packet: Packet, nack_type: NackType) {
+        let (session_id, fragment_index) = (packet.session_id, packet.get_fragment_index());
+        if let Err(e) = self.controller_send.send(DroneEvent::ControllerShortcut(packet)) {
+            self.log(format_args!("{} {}","Error in Sending Nack through SC: ", e));
+        } else {
+            self.log_nack(nack_type, session_id, fragment_index, true);
+        }
+    }
+
     /// The following functions handle the commands sent to the drone by the SC --------------------
     ///
     /// Adds a sender to the list of neighbors.
@@ -359,12 +588,12 @@ impl BetterCallDrone {
     /// - `node_id`: The ID of the node to add.
     /// - `sender`: The sender channel associated with the node.
     pub fn add_sender(&mut self, node_id: NodeId, sender: Sender<Packet>) {
-        self.log(&format!("{}","Received AddSender Command from SC".cyan()));
-        if self.packet_send.contains_key(&node_id) {
-            self.log(&format!("{} -> {} {}","AddSender".cyan(),"Error while trying to remove sender id:".red(), node_id));
+        self.log(format_args!("{}","Received AddSender Command from SC".cyan()));
+        if let Entry::Vacant(entry) = self.packet_send.entry(node_id) {
+            entry.insert(sender);
+            self.log(format_args!("{} -> {} {}","AddSender".cyan(),"Successfully added sender id:".green(), node_id));
         } else {
-            self.packet_send.insert(node_id, sender);
-            self.log(&format!("{} -> {} {}","AddSender".cyan(),"Successfully added sender id:".green(), node_id));
+            self.log(format_args!("{} -> {} {}","AddSender".cyan(),"Error while trying to remove sender id:".red(), node_id));
         }
     }
 
@@ -373,12 +602,12 @@ impl BetterCallDrone {
     /// # Parameters
     /// - `pdr`: The new packet drop rate.
     pub fn set_pdr(&mut self, pdr: f32) {
-        self.log(&format!("{}","Received SetPacketDropRate Command from SC".cyan()));
+        self.log(format_args!("{}","Received SetPacketDropRate Command from SC".cyan()));
         if (0.0..=1.0).contains(&pdr) {
             self.pdr = pdr;
-            self.log(&format!("{} -> {} {}","SetPacketDropRate".cyan(),"Updated PDR to".green(), pdr));
+            self.log(format_args!("{} -> {} {}","SetPacketDropRate".cyan(),"Updated PDR to".green(), pdr));
         } else {
-            self.log(&format!("{} -> {}{}{}","SetPacketDropRate".cyan(),"Invalid PDR (".red(), pdr, ")".red()));
+            self.log(format_args!("{} -> {}{}{}","SetPacketDropRate".cyan(),"Invalid PDR (".red(), pdr, ")".red()));
         }
     }
 
@@ -387,26 +616,24 @@ impl BetterCallDrone {
     /// # Parameters
     /// - `node_id`: The ID of the node to remove.
     pub fn remove_sender(&mut self, node_id: NodeId) {
-        self.log(&format!("{}","Received RemoveSender Command from SC".cyan()));
+        self.log(format_args!("{}","Received RemoveSender Command from SC".cyan()));
         if self.packet_send.contains_key(&node_id) {
             self.packet_send.remove(&node_id);
-            self.log(&format!("{} -> {} {}","RemoveSender".cyan(),"Successfully removed sender id:".green(), node_id));
+            self.log(format_args!("{} -> {} {}","RemoveSender".cyan(),"Successfully removed sender id:".green(), node_id));
         } else {
-            self.log(&format!("{} -> {} {}","RemoveSender".cyan(),"Error while trying to remove sender id:".red(), node_id));
+            self.log(format_args!("{} -> {} {}","RemoveSender".cyan(),"Error while trying to remove sender id:".red(), node_id));
         }
     }
 
     /// Crashes the drone, clearing queued packets and stopping operation.
     pub fn crash_drone(&mut self){
         while let Ok(packet) = self.packet_recv.try_recv() {
-            match &packet.pack_type {
-                PacketType::MsgFragment(frag) => {
-                    self.send_nack(packet.clone(), frag.fragment_index, NackType::ErrorInRouting(self.id));
-                }
+            match packet.pack_type {
+                PacketType::MsgFragment(_) => self.send_nack(packet, NackType::ErrorInRouting(self.id)),
                 PacketType::FloodRequest(_) => {}
-                _ => self.forward_packet(packet, 0),
+                _ => self.forward_packet(packet),
             }
         }
-        self.log(&format!("{}, {}","Finished handling packets".green(),"Drone Crashed successfully".red()));
+        self.log(format_args!("{}, {}","Finished handling packets".green(),"Drone Crashed successfully".red()));
     }
-}
\ No newline at end of file
+}
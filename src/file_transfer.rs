@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::env;
+use std::thread;
+use std::time::{Duration, Instant};
+use colored::Colorize;
+use crossbeam_channel::{Receiver, Sender};
+use sha2::{Digest, Sha256};
+use wg_2024::config::Config;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{NodeType, PacketType, FRAGMENT_DSIZE};
+use crate::client::{ClientCommand, ClientEvent};
+use crate::codec::{put_bytes, put_text, Reader};
+use crate::simulation::{Simulation, SimulationError};
+use crate::transport::TransportStats;
+pub use crate::codec::DecodeError;
+
+/// Messages exchanged between file-transfer clients and the file server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileMessage {
+    /// Client to server: a file and the SHA-256 digest of its content.
+    Upload { name: String, digest: [u8; 32], data: Vec<u8> },
+    /// Server to client: the file matched its digest and was stored.
+    Stored { name: String },
+    /// Server to client: the file did not match its digest and was discarded.
+    Corrupted { name: String },
+}
+
+impl FileMessage {
+    /// Creates an `Upload` with the digest of `data`.
+    #[must_use]
+    pub fn upload(name: &str, data: Vec<u8>) -> Self {
+        FileMessage::Upload { name: name.to_string(), digest: digest(&data), data }
+    }
+
+    /// Serializes the message: a tag byte followed by its fields. Texts and contents are
+    /// prefixed by their length as a little-endian `u32`.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            FileMessage::Upload { name, digest, data } => {
+                bytes.push(0);
+                put_text(&mut bytes, name);
+                bytes.extend(digest);
+                put_bytes(&mut bytes, data);
+            }
+            FileMessage::Stored { name } => {
+                bytes.push(1);
+                put_text(&mut bytes, name);
+            }
+            FileMessage::Corrupted { name } => {
+                bytes.push(2);
+                put_text(&mut bytes, name);
+            }
+        }
+        bytes
+    }
+
+    /// Parses a message serialized by [`FileMessage::encode`].
+    ///
+    /// # Errors
+    /// Returns a `DecodeError` if `bytes` is not a valid message.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let message = match reader.byte()? {
+            0 => {
+                let name = reader.text()?;
+                let mut digest = [0; 32];
+                digest.copy_from_slice(reader.take(32)?);
+                FileMessage::Upload { name, digest, data: reader.bytes()?.to_vec() }
+            }
+            1 => FileMessage::Stored { name: reader.text()? },
+            2 => FileMessage::Corrupted { name: reader.text()? },
+            tag => return Err(DecodeError::UnknownTag(tag)),
+        };
+        reader.finish()?;
+        Ok(message)
+    }
+}
+
+/// Returns the SHA-256 digest of `data`.
+#[must_use]
+pub fn digest(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// The file server: it checks the digest of every uploaded file and keeps the valid ones.
+///
+/// It drives a `Client` node declared as `NodeType::Server`, through the node's command and
+/// event channels.
+pub struct FileServer {
+    id: NodeId,
+    node_commands: Sender<ClientCommand>,
+    node_events: Receiver<ClientEvent>,
+    files: HashMap<String, Vec<u8>>,
+    debug: bool,
+}
+
+impl FileServer {
+    /// Creates a new `FileServer`.
+    ///
+    /// # Parameters
+    /// - `id`: The ID of the node carrying the server.
+    /// - `node_commands`: A channel sender for the commands of the node.
+    /// - `node_events`: A channel receiver for the events of the node.
+    #[must_use]
+    pub fn new(id: NodeId, node_commands: Sender<ClientCommand>, node_events: Receiver<ClientEvent>) -> Self {
+        Self {
+            id,
+            node_commands,
+            node_events,
+            files: HashMap::new(),
+            debug: env::var("BCD_DEBUG").is_ok(),
+        }
+    }
+
+    /// Serves uploads until the node stops.
+    pub fn run(&mut self) {
+        while let Ok(event) = self.node_events.recv() {
+            if let ClientEvent::MessageReceived { source, data, .. } = event {
+                if let Ok(message) = FileMessage::decode(&data) {
+                    self.handle_message(source, message);
+                }
+            }
+        }
+    }
+
+    /// Stores an uploaded file if its digest matches and tells the client the outcome.
+    ///
+    /// # Parameters
+    /// - `source`: The client that sent the message.
+    /// - `message`: The message.
+    pub fn handle_message(&mut self, source: NodeId, message: FileMessage) {
+        let FileMessage::Upload { name, digest: expected, data } = message else {
+            return;
+        };
+        let reply = if digest(&data) == expected {
+            self.log(&format!("{} {name} ({} bytes) from {source}", "Stored".green(), data.len()));
+            self.files.insert(name.clone(), data);
+            FileMessage::Stored { name }
+        } else {
+            self.log(&format!("{} {name} from {source}", "Corrupted".red()));
+            FileMessage::Corrupted { name }
+        };
+        let command = ClientCommand::SendMessage { destination: source, data: reply.encode() };
+        if let Err(e) = self.node_commands.send(command) {
+            self.log(&format!("{} {}", "Error in Sending command: ", e));
+        }
+    }
+
+    /// Returns the stored files.
+    #[must_use]
+    pub fn files(&self) -> &HashMap<String, Vec<u8>> {
+        &self.files
+    }
+
+    /// Prints to console if debug mode is enabled.
+    fn log(&self, message: &str) {
+        if self.debug {
+            println!("{} {message}", format!("[FILE SERVER #{}]", self.id).blue());
+        }
+    }
+}
+
+/// Outcome of [`run_transfer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferReport {
+    /// Size of the file.
+    pub bytes: usize,
+    /// Time between the start of the upload and the answer of the server.
+    pub elapsed: Duration,
+    /// Whether the server found the digest valid.
+    pub verified: bool,
+    /// Counters of the transport of the uploading client.
+    pub stats: TransportStats,
+}
+
+impl TransferReport {
+    /// Returns the goodput of the transfer, in bytes per second.
+    #[must_use]
+    pub fn throughput(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Errors returned by [`run_transfer`].
+#[derive(Debug)]
+pub enum TransferError {
+    /// The network could not be started or a drone could not be crashed.
+    Simulation(SimulationError),
+    /// The configuration needs at least one client and one server.
+    MissingNodes,
+    /// The server did not answer in time.
+    Timeout,
+}
+
+impl std::fmt::Display for TransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferError::Simulation(e) => write!(f, "{e}"),
+            TransferError::MissingNodes => write!(f, "the transfer needs one client and one server"),
+            TransferError::Timeout => write!(f, "timeout, the server did not confirm the file"),
+        }
+    }
+}
+
+impl std::error::Error for TransferError {}
+
+/// Uploads a file from the first client of `config` to a file server on its first server, over
+/// a network of `BetterCallDrone`s.
+///
+/// # Parameters
+/// - `config`: The network.
+/// - `data`: The content of the file.
+/// - `crashes`: Drones crashed once half of the fragments of the file have been sent.
+/// - `timeout`: How long to wait for the answer of the server.
+///
+/// # Errors
+/// Returns a `TransferError` if the network cannot be started, a drone cannot be crashed
+/// or the server does not answer in time.
+pub fn run_transfer(config: Config, data: Vec<u8>, crashes: &[NodeId], timeout: Duration) -> Result<TransferReport, TransferError> {
+    let client_id = config.client.first().map(|c| c.id).ok_or(TransferError::MissingNodes)?;
+    let server_id = config.server.first().map(|s| s.id).ok_or(TransferError::MissingNodes)?;
+    let mut simulation = Simulation::spawn(config).map_err(TransferError::Simulation)?;
+
+    let server_node = simulation.spawn_client(server_id, NodeType::Server).expect("the server is in the configuration");
+    let mut server = FileServer::new(server_id, server_node.commands.clone(), server_node.events.clone());
+    thread::spawn(move || server.run());
+    let client_node = simulation.spawn_client(client_id, NodeType::Client).expect("the client is in the configuration");
+
+    let bytes = data.len();
+    let encoded = FileMessage::upload("file", data).encode();
+    let crash_after = encoded.len().div_ceil(FRAGMENT_DSIZE) / 2;
+    let start = Instant::now();
+    let deadline = start + timeout;
+    let _ = client_node.commands.send(ClientCommand::SendMessage { destination: server_id, data: encoded });
+
+    let mut fragments_sent = 0;
+    let mut crashed = crashes.is_empty();
+    let mut result = Err(TransferError::Timeout);
+    while let Ok(event) = client_node.events.recv_deadline(deadline) {
+        match event {
+            ClientEvent::PacketSent(packet) if matches!(packet.pack_type, PacketType::MsgFragment(_)) => fragments_sent += 1,
+            ClientEvent::MessageReceived { source, data, .. } if source == server_id => {
+                let verified = matches!(FileMessage::decode(&data), Ok(FileMessage::Stored { .. }));
+                result = Ok((start.elapsed(), verified));
+                break;
+            }
+            _ => {}
+        }
+        if !crashed && fragments_sent >= crash_after {
+            crashed = true;
+            if let Some(e) = crashes.iter().find_map(|&drone| simulation.crash(drone).err()) {
+                result = Err(TransferError::Simulation(e));
+                break;
+            }
+        }
+    }
+
+    let stats = client_node.stop();
+    let _ = server_node.stop();
+    result.map(|(elapsed, verified)| TransferReport { bytes, elapsed, verified, stats })
+}
@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+use wg_2024::network::NodeId;
+
+/// What a drone does with a new flood request once its initiator is over the limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleAction {
+    /// Discards the request.
+    Drop,
+    /// Answers with a `FloodResponse` ending at the drone, without forwarding the request.
+    Respond,
+}
+
+/// A token bucket per initiator: each new flood takes a token, a full bucket holds `burst`
+/// tokens and refills at `per_second` tokens per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloodPolicy {
+    burst: u32,
+    per_second: f64,
+    action: ThrottleAction,
+}
+
+/// Why a [`FloodPolicy`] cannot be created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloodPolicyError {
+    /// The bucket would never hold a token.
+    ZeroBurst,
+    /// The refill rate is negative or not finite.
+    InvalidRate(f64),
+}
+
+impl fmt::Display for FloodPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FloodPolicyError::ZeroBurst => write!(f, "the burst must allow at least one flood"),
+            FloodPolicyError::InvalidRate(rate) => write!(f, "{rate} is not a valid number of floods per second"),
+        }
+    }
+}
+
+impl std::error::Error for FloodPolicyError {}
+
+impl FloodPolicy {
+    /// Creates a policy.
+    ///
+    /// # Parameters
+    /// - `burst`: How many floods an initiator can start at once.
+    /// - `per_second`: How many floods per second an initiator can keep starting; `0` never refills.
+    /// - `action`: What to do with the floods over the limit.
+    ///
+    /// # Errors
+    /// Returns an error if `burst` is `0` or `per_second` is negative or not finite.
+    pub fn new(burst: u32, per_second: f64, action: ThrottleAction) -> Result<Self, FloodPolicyError> {
+        if burst == 0 {
+            return Err(FloodPolicyError::ZeroBurst);
+        }
+        if !per_second.is_finite() || per_second < 0.0 {
+            return Err(FloodPolicyError::InvalidRate(per_second));
+        }
+        Ok(Self { burst, per_second, action })
+    }
+
+    /// Returns how many floods an initiator can start at once.
+    #[must_use]
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// Returns how many floods per second an initiator can keep starting.
+    #[must_use]
+    pub fn per_second(&self) -> f64 {
+        self.per_second
+    }
+
+    /// Returns what happens to the floods over the limit.
+    #[must_use]
+    pub fn action(&self) -> ThrottleAction {
+        self.action
+    }
+}
+
+/// The floods a drone checked against its [`FloodPolicy`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FloodStats {
+    /// New floods within the limit, handled as usual.
+    pub admitted: u64,
+    /// New floods over the limit, per initiator.
+    pub throttled: BTreeMap<NodeId, u64>,
+}
+
+impl FloodStats {
+    /// Returns how many floods were over the limit, whatever their initiator.
+    #[must_use]
+    pub fn total_throttled(&self) -> u64 {
+        self.throttled.values().sum()
+    }
+}
+
+/// The tokens left to an initiator.
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// What a drone shares with its [`FloodLimitHandle`]s. There is at most one bucket per
+/// `NodeId`, so a storm of forged initiators cannot make the map grow without bound.
+#[derive(Debug, Default)]
+struct Limiter {
+    policy: Option<FloodPolicy>,
+    buckets: HashMap<NodeId, TokenBucket>,
+    stats: FloodStats,
+}
+
+/// The flood rate limit of a drone, shared with the controller setting the policy and reading
+/// the counters, see [`crate::BetterCallDrone::limit_floods`].
+#[derive(Debug, Clone, Default)]
+pub struct FloodLimitHandle(Arc<Mutex<Limiter>>);
+
+impl FloodLimitHandle {
+    /// Creates a handle without a policy: every flood is forwarded.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the policy, or removes it with `None`. Every initiator starts with a full bucket.
+    pub fn set_policy(&self, policy: Option<FloodPolicy>) {
+        let mut limiter = self.limiter();
+        limiter.policy = policy;
+        limiter.buckets.clear();
+    }
+
+    /// Returns the current policy.
+    #[must_use]
+    pub fn policy(&self) -> Option<FloodPolicy> {
+        self.limiter().policy
+    }
+
+    /// Takes a token for a new flood of `initiator`, and counts the flood.
+    ///
+    /// # Parameters
+    /// - `initiator`: The `initiator_id` of the flood request.
+    /// - `now`: The current time.
+    ///
+    /// # Returns
+    /// `None` if the flood can be forwarded, or what to do with it if the bucket is empty.
+    pub fn throttle(&self, initiator: NodeId, now: Instant) -> Option<ThrottleAction> {
+        let mut limiter = self.limiter();
+        let Some(policy) = limiter.policy else {
+            limiter.stats.admitted += 1;
+            return None;
+        };
+        let burst = f64::from(policy.burst);
+        let bucket = limiter.buckets.entry(initiator).or_insert(TokenBucket { tokens: burst, updated: now });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * policy.per_second).min(burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            limiter.stats.admitted += 1;
+            None
+        } else {
+            *limiter.stats.throttled.entry(initiator).or_default() += 1;
+            Some(policy.action)
+        }
+    }
+
+    /// Returns the counters since the drone started.
+    #[must_use]
+    pub fn stats(&self) -> FloodStats {
+        self.limiter().stats.clone()
+    }
+
+    fn limiter(&self) -> MutexGuard<'_, Limiter> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
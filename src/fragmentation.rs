@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, HashMap};
+use wg_2024::network::NodeId;
+use wg_2024::packet::{Fragment, FRAGMENT_DSIZE};
+
+/// Splits a message into `Fragment`s of at most `FRAGMENT_DSIZE` bytes.
+///
+/// An empty message is sent as a single empty fragment.
+///
+/// # Parameters
+/// - `message`: The bytes to split.
+///
+/// # Returns
+/// The fragments, ordered by `fragment_index`.
+#[must_use]
+pub fn fragment_message(message: &[u8]) -> Vec<Fragment> {
+    let total_n_fragments = message.len().div_ceil(FRAGMENT_DSIZE).max(1) as u64;
+    if message.is_empty() {
+        return vec![Fragment { fragment_index: 0, total_n_fragments, length: 0, data: [0; FRAGMENT_DSIZE] }];
+    }
+    message.chunks(FRAGMENT_DSIZE)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let mut data = [0; FRAGMENT_DSIZE];
+            data[..chunk.len()].copy_from_slice(chunk);
+            Fragment {
+                fragment_index: index as u64,
+                total_n_fragments,
+                length: chunk.len() as u8,
+                data,
+            }
+        })
+        .collect()
+}
+
+/// A message whose fragments are still arriving.
+///
+/// The fragments are kept in a map that grows as they arrive: `total` comes from the network,
+/// so nothing is allocated from it.
+#[derive(Debug, Clone)]
+struct PartialMessage {
+    fragments: BTreeMap<usize, Vec<u8>>,
+    total: usize,
+}
+
+/// Rebuilds messages from their fragments, grouped by source and `session_id`.
+#[derive(Debug, Clone, Default)]
+pub struct Reassembler {
+    partial: HashMap<(NodeId, u64), PartialMessage>,
+}
+
+impl Reassembler {
+    /// Creates an empty `Reassembler`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a fragment and returns the full message once every fragment has arrived.
+    ///
+    /// Duplicated fragments, and fragments that disagree with the first one on the number of
+    /// fragments of the message, are ignored.
+    ///
+    /// # Parameters
+    /// - `source`: The node that sent the message.
+    /// - `session_id`: The session of the message.
+    /// - `fragment`: The received fragment.
+    pub fn insert(&mut self, source: NodeId, session_id: u64, fragment: &Fragment) -> Option<Vec<u8>> {
+        let total = usize::try_from(fragment.total_n_fragments).ok()?;
+        let index = usize::try_from(fragment.fragment_index).ok()?;
+        if index >= total {
+            return None;
+        }
+        let partial = self.partial.entry((source, session_id)).or_insert_with(|| PartialMessage {
+            fragments: BTreeMap::new(),
+            total,
+        });
+        if partial.total != total || partial.fragments.contains_key(&index) {
+            return None;
+        }
+        let length = usize::from(fragment.length).min(FRAGMENT_DSIZE);
+        partial.fragments.insert(index, fragment.data[..length].to_vec());
+        if partial.fragments.len() < total {
+            return None;
+        }
+        let partial = self.partial.remove(&(source, session_id))?;
+        Some(partial.fragments.into_values().flatten().collect())
+    }
+
+    /// Returns the number of messages still waiting for fragments.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+}
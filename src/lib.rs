@@ -1,2 +1,21 @@
+pub mod chat;
+pub mod client;
+pub mod codec;
+#[cfg(feature = "testing")]
+pub mod conformance;
+pub mod discovery;
 pub mod drone;
+pub mod file_transfer;
+pub mod flood_limit;
+pub mod fragmentation;
+pub mod monitor;
+pub mod node;
+pub mod repl;
+pub mod routing;
+pub mod server;
+pub mod simulation;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod topology;
+pub mod transport;
 pub use drone::BetterCallDrone;
\ No newline at end of file
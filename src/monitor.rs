@@ -0,0 +1,130 @@
+use std::collections::{BTreeMap, VecDeque};
+use wg_2024::config::Config;
+use wg_2024::controller::DroneEvent;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{Packet, PacketType};
+use crate::repl::describe_event;
+
+/// How many events the monitor keeps in its log.
+pub const LOG_CAPACITY: usize = 500;
+
+/// Counters of a single drone, computed from the events it sent to the controller.
+///
+/// Only what a drone reports can be counted: the nacks it generates and sends straight to the
+/// previous hop, for `ErrorInRouting`, `UnexpectedRecipient` or `DestinationIsDrone`, produce no
+/// event and cannot be observed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DroneStats {
+    /// The current neighbors of the drone.
+    pub neighbors: Vec<NodeId>,
+    /// The current PDR of the drone.
+    pub pdr: f32,
+    /// Packets forwarded to a neighbor (`PacketSent`).
+    pub sent: u64,
+    /// Fragments dropped because of the PDR (`PacketDropped`).
+    pub dropped: u64,
+    /// Nacks seen for this drone: one per dropped fragment, plus every forwarded nack.
+    pub nacks: u64,
+}
+
+/// Aggregates the `DroneEvent`s of a network into per-drone counters and a scrolling log.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkMonitor {
+    drones: BTreeMap<NodeId, DroneStats>,
+    shortcuts: u64,
+    log: VecDeque<String>,
+}
+
+impl NetworkMonitor {
+    /// Creates a monitor for the drones of a configuration.
+    #[must_use]
+    pub fn new(config: &Config) -> Self {
+        let mut monitor = Self::default();
+        monitor.update_topology(config);
+        monitor
+    }
+
+    /// Updates neighbors and PDRs from the current topology, forgetting drones that no longer exist.
+    ///
+    /// # Parameters
+    /// - `config`: The current topology.
+    pub fn update_topology(&mut self, config: &Config) {
+        self.drones.retain(|id, _| config.drone.iter().any(|d| d.id == *id));
+        for d in &config.drone {
+            let stats = self.drones.entry(d.id).or_default();
+            stats.neighbors.clone_from(&d.connected_node_ids);
+            stats.pdr = d.pdr;
+        }
+    }
+
+    /// Updates the counters of the drone that sent an event and appends it to the log.
+    ///
+    /// A `ControllerShortcut` does not say which drone sent it: its `hop_index` depends on why
+    /// the packet could not be forwarded, and after `UnexpectedRecipient` the drone is not even
+    /// in the route. Shortcuts are only counted for the whole network, see
+    /// [`NetworkMonitor::shortcuts`].
+    ///
+    /// # Parameters
+    /// - `event`: The event received from the drone.
+    pub fn record(&mut self, event: &DroneEvent) {
+        let sender = match event {
+            DroneEvent::PacketSent(packet) => previous_hop(packet),
+            DroneEvent::PacketDropped(packet) => packet.routing_header.hops.get(packet.routing_header.hop_index).copied(),
+            DroneEvent::ControllerShortcut(_) => {
+                self.shortcuts += 1;
+                None
+            }
+        };
+        if let Some(stats) = sender.and_then(|id| self.drones.get_mut(&id)) {
+            match event {
+                DroneEvent::PacketSent(packet) => {
+                    stats.sent += 1;
+                    if matches!(packet.pack_type, PacketType::Nack(_)) {
+                        stats.nacks += 1;
+                    }
+                }
+                DroneEvent::PacketDropped(_) => {
+                    stats.dropped += 1;
+                    stats.nacks += 1;
+                }
+                DroneEvent::ControllerShortcut(_) => {}
+            }
+        }
+        let origin = sender.map_or_else(|| "?".to_string(), |id| id.to_string());
+        self.push_log(format!("[#{origin}] {}", describe_event(event)));
+    }
+
+    /// Appends a line to the log, discarding the oldest one when the log is full.
+    pub fn push_log(&mut self, line: String) {
+        if self.log.len() == LOG_CAPACITY {
+            self.log.pop_front();
+        }
+        self.log.push_back(line);
+    }
+
+    /// Returns the counters of every drone, sorted by id.
+    #[must_use]
+    pub fn drones(&self) -> &BTreeMap<NodeId, DroneStats> {
+        &self.drones
+    }
+
+    /// Returns how many packets the drones sent through the controller (`ControllerShortcut`).
+    #[must_use]
+    pub fn shortcuts(&self) -> u64 {
+        self.shortcuts
+    }
+
+    /// Returns the logged events, oldest first.
+    #[must_use]
+    pub fn log(&self) -> &VecDeque<String> {
+        &self.log
+    }
+}
+
+/// Returns the node that sent a packet, which is the hop before the current one.
+///
+/// Drones increase the hop index before forwarding, so for `PacketSent` this is the drone itself.
+fn previous_hop(packet: &Packet) -> Option<NodeId> {
+    let index = packet.routing_header.hop_index.checked_sub(1)?;
+    packet.routing_header.hops.get(index).copied()
+}
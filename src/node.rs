@@ -0,0 +1,37 @@
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{FloodRequest, NodeType, Packet};
+
+/// Builds the route back to the source of a packet received by a client or server.
+///
+/// # Parameters
+/// - `routing_header`: The routing header of the received packet, whose current hop is this node.
+///
+/// # Returns
+/// The reversed route, ready to be sent to `hops[1]`, or `None` if the packet came from nowhere.
+#[must_use]
+pub fn reply_route(routing_header: &SourceRoutingHeader) -> Option<SourceRoutingHeader> {
+    let last = routing_header.hop_index.min(routing_header.hops.len().checked_sub(1)?);
+    let hops: Vec<NodeId> = routing_header.hops[..=last].iter().rev().copied().collect();
+    if hops.len() < 2 {
+        return None;
+    }
+    Some(SourceRoutingHeader { hop_index: 1, hops })
+}
+
+/// Answers a flood request at a client or server: these nodes never forward floods.
+///
+/// # Parameters
+/// - `request`: The received flood request.
+/// - `node_id`: The ID of the answering node.
+/// - `node_type`: The type of the answering node.
+/// - `session_id`: The session ID of the request.
+///
+/// # Returns
+/// The `FloodResponse` packet, ready to be sent to `hops[1]`.
+#[must_use]
+pub fn answer_flood(mut request: FloodRequest, node_id: NodeId, node_type: NodeType, session_id: u64) -> Packet {
+    request.increment(node_id, node_type);
+    let mut response = request.generate_response(session_id);
+    response.routing_header.hop_index = 1;
+    response
+}
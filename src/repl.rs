@@ -0,0 +1,291 @@
+use std::fmt::Write;
+use wg_2024::controller::DroneEvent;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{FloodRequest, Fragment, NodeType, Packet, PacketType, FRAGMENT_DSIZE};
+use crate::discovery::{NetworkGraph, TopologyChange};
+use crate::flood_limit::{FloodPolicy, FloodStats, ThrottleAction};
+use crate::simulation::Simulation;
+
+/// Help text listing every command understood by the controller.
+pub const HELP: &str = "\
+Commands:
+  add <a> <b>                         connect two nodes (AddSender)
+  remove <a> <b>                      disconnect two nodes (RemoveSender)
+  pdr <drone> <pdr>                   change the PDR of a drone (SetPacketDropRate)
+  crash <drone>                       crash a drone (Crash)
+  flood <node> <flood_id>             start a flood from a client or server
+  send <hops> <session> <index> <msg> inject a fragment from hops[0], e.g. `send 1,11,12,21 7 0 hello`
+  topology                            print the current topology
+  learn <drone> on|off                let a drone learn the network from the flood responses it relays
+  learned <drone>                     compare what a drone learned from flood responses with the topology
+  repair <drone> on|off               let a drone repair routes with what it learned
+  floodlimit <drone> <burst> <per_s> drop|respond
+                                      limit the new floods a drone forwards per initiator
+  floodlimit <drone> off              forward every new flood again
+  floods <drone>                      print how many floods a drone forwarded and throttled
+  help                                print this message
+  quit                                crash every drone and exit";
+
+/// How many of the last route repairs of a drone `learned` prints.
+const REPAIRS_SHOWN: usize = 5;
+
+/// A command typed in the controller REPL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplCommand {
+    AddLink(NodeId, NodeId),
+    RemoveLink(NodeId, NodeId),
+    SetPdr(NodeId, f32),
+    Crash(NodeId),
+    Flood { initiator: NodeId, flood_id: u64 },
+    Send { hops: Vec<NodeId>, session_id: u64, fragment_index: u64, message: String },
+    Topology,
+    Learn(NodeId, bool),
+    Learned(NodeId),
+    Repair(NodeId, bool),
+    FloodLimit(NodeId, Option<FloodPolicy>),
+    Floods(NodeId),
+    Help,
+    Quit,
+}
+
+impl ReplCommand {
+    /// Parses a line typed in the REPL.
+    ///
+    /// # Errors
+    /// Returns a message describing why the line is not a valid command.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Err("empty command".to_string());
+        };
+        let mut next = |what: &str| words.next().ok_or(format!("missing {what}, type `help` for usage"));
+        let command = match name {
+            "add" => ReplCommand::AddLink(parse_number(next("node")?)?, parse_number(next("node")?)?),
+            "remove" => ReplCommand::RemoveLink(parse_number(next("node")?)?, parse_number(next("node")?)?),
+            "pdr" => ReplCommand::SetPdr(parse_number(next("drone")?)?, parse_number(next("pdr")?)?),
+            "crash" => ReplCommand::Crash(parse_number(next("drone")?)?),
+            "flood" => ReplCommand::Flood {
+                initiator: parse_number(next("node")?)?,
+                flood_id: parse_number(next("flood id")?)?,
+            },
+            "send" => {
+                let hops = next("hops")?.split(',').map(parse_number).collect::<Result<Vec<NodeId>, _>>()?;
+                let session_id = parse_number(next("session id")?)?;
+                let fragment_index = parse_number(next("fragment index")?)?;
+                let message = words.collect::<Vec<_>>().join(" ");
+                if hops.len() < 2 {
+                    return Err("a route needs at least two hops".to_string());
+                }
+                if message.len() > FRAGMENT_DSIZE {
+                    return Err(format!("a fragment carries at most {FRAGMENT_DSIZE} bytes"));
+                }
+                ReplCommand::Send { hops, session_id, fragment_index, message }
+            }
+            "topology" => ReplCommand::Topology,
+            "learn" => ReplCommand::Learn(parse_number(next("drone")?)?, parse_switch(next("on or off")?)?),
+            "learned" => ReplCommand::Learned(parse_number(next("drone")?)?),
+            "repair" => ReplCommand::Repair(parse_number(next("drone")?)?, parse_switch(next("on or off")?)?),
+            "floodlimit" => {
+                let drone = parse_number(next("drone")?)?;
+                let burst = next("burst or off")?;
+                if burst == "off" {
+                    ReplCommand::FloodLimit(drone, None)
+                } else {
+                    let burst = parse_number(burst)?;
+                    let per_second = parse_number(next("floods per second")?)?;
+                    let action = match next("drop or respond")? {
+                        "drop" => ThrottleAction::Drop,
+                        "respond" => ThrottleAction::Respond,
+                        other => return Err(format!("`{other}` is not drop or respond")),
+                    };
+                    ReplCommand::FloodLimit(drone, Some(FloodPolicy::new(burst, per_second, action).map_err(|e| e.to_string())?))
+                }
+            }
+            "floods" => ReplCommand::Floods(parse_number(next("drone")?)?),
+            "help" => ReplCommand::Help,
+            "quit" | "exit" => ReplCommand::Quit,
+            _ => return Err(format!("unknown command `{name}`, type `help` for usage")),
+        };
+        Ok(command)
+    }
+
+    /// Executes the command on a running simulation.
+    ///
+    /// # Returns
+    /// The text to print in the REPL.
+    ///
+    /// # Errors
+    /// Returns a message if the simulation refused the command.
+    pub fn execute(&self, simulation: &mut Simulation) -> Result<String, String> {
+        let result = match self {
+            ReplCommand::AddLink(a, b) => simulation.add_link(*a, *b)
+                .map(|()| format!("connected {a} and {b}")),
+            ReplCommand::RemoveLink(a, b) => simulation.remove_link(*a, *b)
+                .map(|()| format!("disconnected {a} and {b}")),
+            ReplCommand::SetPdr(id, pdr) => simulation.set_pdr(*id, *pdr)
+                .map(|()| format!("set PDR of {id} to {pdr}")),
+            ReplCommand::Crash(id) => simulation.crash(*id)
+                .map(|()| format!("crashed {id}")),
+            ReplCommand::Flood { initiator, flood_id } => return flood(simulation, *initiator, *flood_id),
+            ReplCommand::Send { hops, session_id, fragment_index, message } => {
+                let packet = Packet::new_fragment(
+                    SourceRoutingHeader { hop_index: 1, hops: hops.clone() },
+                    *session_id,
+                    Fragment {
+                        fragment_index: *fragment_index,
+                        total_n_fragments: fragment_index + 1,
+                        length: message.len() as u8,
+                        data: message_data(message),
+                    },
+                );
+                simulation.inject(hops[1], packet)
+                    .map(|()| format!("fragment ({session_id}:{fragment_index}) injected into {}", hops[1]))
+            }
+            ReplCommand::Topology => Ok(describe_topology(simulation)),
+            ReplCommand::Learn(id, enabled) => simulation.set_learning(*id, *enabled)
+                .map(|()| format!("topology learning {} for {id}", if *enabled { "on" } else { "off" })),
+            ReplCommand::Learned(id) => simulation.learned_topology(*id)
+                .and_then(|learned| Ok((learned, simulation.route_repairs(*id)?, simulation.is_learning(*id)?)))
+                .map(|(learned, repairs, learning)| {
+                    let mut out = describe_learned(*id, &learned, &NetworkGraph::from_config(simulation.config()));
+                    if !learning {
+                        let _ = write!(out, "\n  learning is off, turn it on with `learn {id} on`");
+                    }
+                    for repair in repairs.iter().rev().take(REPAIRS_SHOWN) {
+                        let _ = write!(out, "\n  repaired session {} around {}: {:?} -> {:?}",
+                            repair.session_id, repair.missing, repair.original, repair.repaired);
+                    }
+                    out
+                }),
+            ReplCommand::Repair(id, enabled) => simulation.set_route_repair(*id, *enabled)
+                .map(|()| format!("route repair {} for {id}", if *enabled { "on" } else { "off" })),
+            ReplCommand::FloodLimit(id, policy) => simulation.set_flood_policy(*id, *policy)
+                .map(|()| match policy {
+                    Some(policy) => format!("flood limit for {id}: burst {}, {} per second, then {:?}",
+                        policy.burst(), policy.per_second(), policy.action()),
+                    None => format!("flood limit off for {id}"),
+                }),
+            ReplCommand::Floods(id) => simulation.flood_stats(*id)
+                .map(|stats| describe_flood_stats(*id, &stats)),
+            ReplCommand::Help => Ok(HELP.to_string()),
+            ReplCommand::Quit => {
+                simulation.shutdown();
+                Ok("bye".to_string())
+            }
+        };
+        result.map_err(|e| e.to_string())
+    }
+}
+
+/// Sends a new `FloodRequest` from a client or server to each of its drones.
+fn flood(simulation: &Simulation, initiator: NodeId, flood_id: u64) -> Result<String, String> {
+    let config = simulation.config();
+    let (node_type, drones) = if let Some(c) = config.client.iter().find(|c| c.id == initiator) {
+        (NodeType::Client, c.connected_drone_ids.clone())
+    } else if let Some(s) = config.server.iter().find(|s| s.id == initiator) {
+        (NodeType::Server, s.connected_drone_ids.clone())
+    } else {
+        return Err(format!("node {initiator} is not a client or a server"));
+    };
+    let request = FloodRequest {
+        flood_id,
+        initiator_id: initiator,
+        path_trace: vec![(initiator, node_type)],
+    };
+    for drone in &drones {
+        let packet = Packet::new_flood_request(SourceRoutingHeader::empty_route(), flood_id, request.clone());
+        simulation.inject(*drone, packet).map_err(|e| e.to_string())?;
+    }
+    Ok(format!("flood {flood_id} sent from {initiator} to {drones:?}"))
+}
+
+/// Copies a message into the payload of a fragment, padding it with zeros.
+fn message_data(message: &str) -> [u8; FRAGMENT_DSIZE] {
+    let mut data = [0; FRAGMENT_DSIZE];
+    data[..message.len()].copy_from_slice(message.as_bytes());
+    data
+}
+
+/// Parses a number, reporting the offending word on failure.
+fn parse_number<T: std::str::FromStr>(word: &str) -> Result<T, String> {
+    word.parse().map_err(|_| format!("`{word}` is not a valid number"))
+}
+
+/// Parses `on` or `off`.
+fn parse_switch(word: &str) -> Result<bool, String> {
+    match word {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        other => Err(format!("`{other}` is not on or off")),
+    }
+}
+
+/// Formats the current topology of the simulation, one node per line.
+#[must_use]
+pub fn describe_topology(simulation: &Simulation) -> String {
+    let config = simulation.config();
+    let mut out = String::new();
+    for d in &config.drone {
+        let _ = writeln!(out, "drone  {:>3} pdr {:.2} -> {:?}", d.id, d.pdr, d.connected_node_ids);
+    }
+    for c in &config.client {
+        let _ = writeln!(out, "client {:>3}          -> {:?}", c.id, c.connected_drone_ids);
+    }
+    for s in &config.server {
+        let _ = writeln!(out, "server {:>3}          -> {:?}", s.id, s.connected_drone_ids);
+    }
+    out.trim_end().to_string()
+}
+
+/// Formats what a drone learned compared with the real topology: the nodes and links it has not
+/// observed, and the ones it observed that no longer exist.
+#[must_use]
+pub fn describe_learned(drone_id: NodeId, learned: &NetworkGraph, topology: &NetworkGraph) -> String {
+    let mut out = format!("drone {drone_id} learned {} nodes and {} links", learned.nodes().len(), learned.links().len());
+    let change = TopologyChange::between(topology, learned);
+    if !change.removed_nodes.is_empty() || !change.removed_links.is_empty() {
+        let _ = write!(out, "\n  not observed: nodes {:?}, links {:?}", change.removed_nodes, change.removed_links);
+    }
+    if !change.added_nodes.is_empty() || !change.added_links.is_empty() {
+        let _ = write!(out, "\n  not in the topology: nodes {:?}, links {:?}", change.added_nodes, change.added_links);
+    }
+    out
+}
+
+/// Formats the floods a drone forwarded and, per initiator, the ones it throttled.
+#[must_use]
+pub fn describe_flood_stats(drone_id: NodeId, stats: &FloodStats) -> String {
+    let mut out = format!("drone {drone_id} forwarded {} new floods and throttled {}", stats.admitted, stats.total_throttled());
+    for (initiator, throttled) in &stats.throttled {
+        let _ = write!(out, "\n  from {initiator}: {throttled} throttled");
+    }
+    out
+}
+
+/// Formats a short description of a packet: its type, session, fragment and route.
+#[must_use]
+pub fn describe_packet(packet: &Packet) -> String {
+    let kind = match &packet.pack_type {
+        PacketType::MsgFragment(_) => "Message".to_string(),
+        PacketType::Ack(_) => "Ack".to_string(),
+        PacketType::Nack(nack) => format!("Nack({:?})", nack.nack_type),
+        PacketType::FloodRequest(request) => format!("FloodRequest(flood {})", request.flood_id),
+        PacketType::FloodResponse(response) => format!("FloodResponse(flood {}, trace {:?})", response.flood_id,
+            response.path_trace.iter().map(|(id, _)| *id).collect::<Vec<_>>()),
+    };
+    format!("({}:{}) {kind} route {:?}@{}",
+            packet.session_id,
+            packet.get_fragment_index(),
+            packet.routing_header.hops,
+            packet.routing_header.hop_index)
+}
+
+/// Formats a short description of a drone event.
+#[must_use]
+pub fn describe_event(event: &DroneEvent) -> String {
+    match event {
+        DroneEvent::PacketSent(packet) => format!("PacketSent {}", describe_packet(packet)),
+        DroneEvent::PacketDropped(packet) => format!("PacketDropped {}", describe_packet(packet)),
+        DroneEvent::ControllerShortcut(packet) => format!("ControllerShortcut {}", describe_packet(packet)),
+    }
+}
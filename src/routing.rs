@@ -0,0 +1,256 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{Nack, NackType, NodeType};
+use crate::discovery::NetworkGraph;
+
+/// Prior observations given to every drone, so that a drone never seen is assumed to have
+/// a PDR of 10% and a single drop does not make a drone unusable.
+const PRIOR_DROPPED: f64 = 1.0;
+const PRIOR_FORWARDED: f64 = 9.0;
+
+/// Highest PDR used for routing, so that every drone keeps a finite cost.
+const MAX_ESTIMATED_PDR: f64 = 0.999;
+
+/// Per-drone estimate of the packet drop rate, learned from the `Ack`s and `Nack`s a node receives.
+#[derive(Debug, Clone, Default)]
+pub struct LinkQuality {
+    /// `(forwarded, dropped)` fragments observed for each drone.
+    observed: HashMap<NodeId, (u64, u64)>,
+    crashed: HashSet<NodeId>,
+}
+
+impl LinkQuality {
+    /// Creates an estimator with no observations.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that every drone of a route forwarded a fragment.
+    ///
+    /// # Parameters
+    /// - `hops`: The route, source and destination included; only the intermediate hops are counted.
+    pub fn record_forwarded(&mut self, hops: &[NodeId]) {
+        if hops.len() > 2 {
+            for drone in &hops[1..hops.len() - 1] {
+                self.observed.entry(*drone).or_default().0 += 1;
+            }
+        }
+    }
+
+    /// Records that a drone dropped a fragment.
+    pub fn record_dropped(&mut self, drone: NodeId) {
+        self.observed.entry(drone).or_default().1 += 1;
+    }
+
+    /// Records a received `Nack`: for `Dropped` the first hop of its route dropped the fragment,
+    /// and the drones between it and this node had forwarded it.
+    ///
+    /// # Parameters
+    /// - `routing_header`: The routing header of the received nack, from the reporting drone to this node.
+    /// - `nack`: The nack.
+    pub fn record_nack(&mut self, routing_header: &SourceRoutingHeader, nack: &Nack) {
+        if nack.nack_type != NackType::Dropped {
+            return;
+        }
+        if let Some((&dropper, rest)) = routing_header.hops.split_first() {
+            self.record_dropped(dropper);
+            if let Some((_, forwarded)) = rest.split_last() {
+                for drone in forwarded {
+                    self.observed.entry(*drone).or_default().0 += 1;
+                }
+            }
+        }
+    }
+
+    /// Marks a drone as crashed: it is excluded from every route until [`LinkQuality::mark_alive`].
+    pub fn mark_crashed(&mut self, drone: NodeId) {
+        self.crashed.insert(drone);
+    }
+
+    /// Makes a crashed drone usable again.
+    pub fn mark_alive(&mut self, drone: NodeId) {
+        self.crashed.remove(&drone);
+    }
+
+    /// Returns `true` if the drone is marked as crashed.
+    #[must_use]
+    pub fn is_crashed(&self, drone: NodeId) -> bool {
+        self.crashed.contains(&drone)
+    }
+
+    /// Returns the estimated PDR of a drone.
+    #[must_use]
+    pub fn estimated_pdr(&self, drone: NodeId) -> f64 {
+        let (forwarded, dropped) = self.observed.get(&drone).copied().unwrap_or_default();
+        let dropped = dropped as f64 + PRIOR_DROPPED;
+        let total = forwarded as f64 + dropped + PRIOR_FORWARDED;
+        (dropped / total).min(MAX_ESTIMATED_PDR)
+    }
+
+    /// Returns the estimated probability that a fragment crosses every intermediate hop of a route.
+    #[must_use]
+    pub fn reliability(&self, hops: &[NodeId]) -> f64 {
+        if hops.len() <= 2 {
+            return 1.0;
+        }
+        hops[1..hops.len() - 1].iter().map(|drone| 1.0 - self.estimated_pdr(*drone)).product()
+    }
+
+    /// The cost of crossing a drone: `-ln(1 - pdr)`, so that the cheapest route is the most reliable.
+    fn cost(&self, drone: NodeId) -> f64 {
+        -(1.0 - self.estimated_pdr(drone)).ln()
+    }
+}
+
+/// A node waiting in the Dijkstra queue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct State {
+    cost: f64,
+    node: NodeId,
+}
+
+impl Eq for State {}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, so that the `BinaryHeap` pops the cheapest state first.
+        other.cost.total_cmp(&self.cost).then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Finds the route with the highest estimated delivery probability.
+///
+/// Only drones that are not marked as crashed are used as intermediate hops.
+///
+/// # Parameters
+/// - `graph`: The known topology.
+/// - `quality`: The PDR estimates.
+/// - `from`: The source of the route.
+/// - `to`: The destination of the route.
+///
+/// # Returns
+/// A `SourceRoutingHeader` with `hop_index` 1, ready to be sent to its first drone,
+/// or `None` if the destination cannot be reached.
+#[must_use]
+pub fn most_reliable_route(graph: &NetworkGraph, quality: &LinkQuality, from: NodeId, to: NodeId) -> Option<SourceRoutingHeader> {
+    dijkstra(graph, quality, from, to, &HashSet::new(), &HashSet::new())
+        .map(|(hops, _)| SourceRoutingHeader { hop_index: 1, hops })
+}
+
+/// Finds up to `k` loopless routes, from the most to the least reliable (Yen's algorithm).
+///
+/// # Parameters
+/// - `graph`: The known topology.
+/// - `quality`: The PDR estimates.
+/// - `from`: The source of the routes.
+/// - `to`: The destination of the routes.
+/// - `k`: The maximum number of routes.
+///
+/// # Returns
+/// The routes as `SourceRoutingHeader`s with `hop_index` 1.
+#[must_use]
+pub fn k_most_reliable_routes(graph: &NetworkGraph, quality: &LinkQuality, from: NodeId, to: NodeId, k: usize) -> Vec<SourceRoutingHeader> {
+    if k == 0 {
+        return Vec::new();
+    }
+    let Some(best) = dijkstra(graph, quality, from, to, &HashSet::new(), &HashSet::new()) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(Vec<NodeId>, f64)> = vec![best];
+    let mut candidates: Vec<(Vec<NodeId>, f64)> = Vec::new();
+
+    while found.len() < k {
+        let last = found[found.len() - 1].0.clone();
+        for i in 0..last.len() - 1 {
+            let spur = last[i];
+            let root = &last[..=i];
+            let excluded_links: HashSet<(NodeId, NodeId)> = found.iter()
+                .filter(|(hops, _)| hops.len() > i + 1 && hops[..=i] == *root)
+                .map(|(hops, _)| (hops[i], hops[i + 1]))
+                .collect();
+            let excluded_nodes: HashSet<NodeId> = root[..i].iter().copied().collect();
+            let Some((spur_hops, _)) = dijkstra(graph, quality, spur, to, &excluded_nodes, &excluded_links) else {
+                continue;
+            };
+            let mut hops = root[..i].to_vec();
+            hops.extend(spur_hops);
+            if !found.iter().chain(candidates.iter()).any(|(h, _)| *h == hops) {
+                let cost = route_cost(quality, &hops);
+                candidates.push((hops, cost));
+            }
+        }
+        let Some(position) = candidates.iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.1.total_cmp(&b.1).then_with(|| a.0.len().cmp(&b.0.len())))
+            .map(|(position, _)| position) else {
+            break;
+        };
+        found.push(candidates.swap_remove(position));
+    }
+
+    found.into_iter().map(|(hops, _)| SourceRoutingHeader { hop_index: 1, hops }).collect()
+}
+
+/// Returns the cost of the intermediate hops of a route.
+fn route_cost(quality: &LinkQuality, hops: &[NodeId]) -> f64 {
+    if hops.len() <= 2 {
+        return 0.0;
+    }
+    hops[1..hops.len() - 1].iter().map(|drone| quality.cost(*drone)).sum()
+}
+
+/// Dijkstra over the drones of the graph, skipping excluded nodes and directed links.
+///
+/// # Returns
+/// The cheapest route with its cost.
+fn dijkstra(
+    graph: &NetworkGraph,
+    quality: &LinkQuality,
+    from: NodeId,
+    to: NodeId,
+    excluded_nodes: &HashSet<NodeId>,
+    excluded_links: &HashSet<(NodeId, NodeId)>,
+) -> Option<(Vec<NodeId>, f64)> {
+    let mut best: HashMap<NodeId, f64> = HashMap::from([(from, 0.0)]);
+    let mut previous: HashMap<NodeId, NodeId> = HashMap::new();
+    let mut queue = BinaryHeap::from([State { cost: 0.0, node: from }]);
+
+    while let Some(State { cost, node }) = queue.pop() {
+        if node == to {
+            let mut hops = vec![to];
+            while let Some(&prev) = previous.get(hops.last()?) {
+                hops.push(prev);
+            }
+            hops.reverse();
+            return Some((hops, cost));
+        }
+        if best.get(&node).is_some_and(|&b| cost > b) {
+            continue;
+        }
+        // Packets can only go through drones.
+        if node != from && (graph.node_type(node) != Some(NodeType::Drone) || quality.is_crashed(node)) {
+            continue;
+        }
+        let crossing = if node == from { 0.0 } else { quality.cost(node) };
+        for next in graph.neighbors(node) {
+            if next == from || excluded_nodes.contains(&next) || excluded_links.contains(&(node, next)) {
+                continue;
+            }
+            let next_cost = cost + crossing;
+            if best.get(&next).is_none_or(|&b| next_cost < b) {
+                best.insert(next, next_cost);
+                previous.insert(next, node);
+                queue.push(State { cost: next_cost, node: next });
+            }
+        }
+    }
+    None
+}
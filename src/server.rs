@@ -0,0 +1,165 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use colored::Colorize;
+use crossbeam_channel::{select_biased, Receiver, Sender};
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{Fragment, NodeType, Packet, PacketType};
+use crate::fragmentation::Reassembler;
+use crate::node::{answer_flood, reply_route};
+
+/// Commands accepted by a `Server`.
+#[derive(Debug, Clone)]
+pub enum ServerCommand {
+    /// Adds a neighboring drone.
+    AddSender(NodeId, Sender<Packet>),
+    /// Removes a neighboring drone.
+    RemoveSender(NodeId),
+    /// Stops the server.
+    Crash,
+}
+
+/// Events reported by a `Server`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerEvent {
+    /// A full message has been reassembled.
+    MessageReceived { source: NodeId, session_id: u64, data: Vec<u8> },
+    /// A packet has been sent to a neighbor.
+    PacketSent(Packet),
+}
+
+/// A reference server node: it reassembles the fragments routed to it, acknowledges each of them
+/// along the reversed route and answers `FloodRequest`s as a `NodeType::Server`.
+///
+/// Fragments of a message already reassembled are acknowledged again but not stored, so
+/// retransmissions caused by lost `Ack`s do not report a message twice.
+pub struct Server {
+    id: NodeId,
+    controller_send: Sender<ServerEvent>,
+    controller_recv: Receiver<ServerCommand>,
+    packet_recv: Receiver<Packet>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+
+    reassembler: Reassembler,
+    /// The `(source, session_id)` pairs of the messages already reassembled.
+    completed: HashSet<(NodeId, u64)>,
+    debug: bool,
+}
+
+impl Server {
+    /// Creates a new `Server`.
+    ///
+    /// # Parameters
+    /// - `id`: The ID of the server.
+    /// - `controller_send`: A channel sender for the events of the server.
+    /// - `controller_recv`: A channel receiver for the commands of the server.
+    /// - `packet_recv`: A channel receiver for incoming packets.
+    /// - `packet_send`: The neighboring drones and their packet senders.
+    #[must_use]
+    pub fn new(
+        id: NodeId,
+        controller_send: Sender<ServerEvent>,
+        controller_recv: Receiver<ServerCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+    ) -> Self {
+        Self {
+            id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            reassembler: Reassembler::new(),
+            completed: HashSet::new(),
+            debug: env::var("BCD_DEBUG").is_ok(),
+        }
+    }
+
+    /// Starts the main loop of the server, until it receives `ServerCommand::Crash`
+    /// or its command channel is closed.
+    pub fn run(&mut self) {
+        loop {
+            select_biased! {
+                recv(self.controller_recv) -> command => {
+                    match command {
+                        Ok(ServerCommand::Crash) | Err(_) => break,
+                        Ok(command) => self.handle_command(command),
+                    }
+                }
+                recv(self.packet_recv) -> packet => {
+                    if let Ok(packet) = packet {
+                        self.handle_packet(packet);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Processes a command.
+    ///
+    /// # Parameters
+    /// - `command`: The command to handle.
+    pub fn handle_command(&mut self, command: ServerCommand) {
+        match command {
+            ServerCommand::AddSender(node_id, sender) => {
+                self.packet_send.insert(node_id, sender);
+            }
+            ServerCommand::RemoveSender(node_id) => {
+                self.packet_send.remove(&node_id);
+            }
+            ServerCommand::Crash => {}
+        }
+    }
+
+    /// Handles an incoming packet based on its type.
+    ///
+    /// # Parameters
+    /// - `packet`: The incoming packet.
+    pub fn handle_packet(&mut self, packet: Packet) {
+        match packet.pack_type {
+            PacketType::MsgFragment(fragment) => self.handle_fragment(&packet.routing_header, packet.session_id, &fragment),
+            PacketType::FloodRequest(request) => {
+                self.send_packet(answer_flood(request, self.id, NodeType::Server, packet.session_id));
+            }
+            PacketType::Ack(_) | PacketType::Nack(_) | PacketType::FloodResponse(_) => {
+                self.log(&format!("({}:{}) | {}", packet.session_id, packet.get_fragment_index(), "Ignored packet".yellow()));
+            }
+        }
+    }
+
+    /// Acknowledges a fragment and reports the message once complete.
+    fn handle_fragment(&mut self, routing_header: &SourceRoutingHeader, session_id: u64, fragment: &Fragment) {
+        let Some(route) = reply_route(routing_header) else {
+            return;
+        };
+        let source = route.hops[route.hops.len() - 1];
+        self.send_packet(Packet::new_ack(route, session_id, fragment.fragment_index));
+        if self.completed.contains(&(source, session_id)) {
+            return;
+        }
+        if let Some(data) = self.reassembler.insert(source, session_id, fragment) {
+            self.completed.insert((source, session_id));
+            self.log(&format!("({session_id}) | {} {} bytes from {source}", "Received message".green(), data.len()));
+            if let Err(e) = self.controller_send.send(ServerEvent::MessageReceived { source, session_id, data }) {
+                self.log(&format!("{} {}", "Error in Sending event: ", e));
+            }
+        }
+    }
+
+    /// Sends a packet to the node at `hops[hop_index]`.
+    fn send_packet(&self, packet: Packet) {
+        let Some(sender) = packet.routing_header.hops.get(packet.routing_header.hop_index).and_then(|id| self.packet_send.get(id)) else {
+            self.log(&format!("({}) | {}", packet.session_id, "No neighbor for the next hop".red()));
+            return;
+        };
+        if sender.send(packet.clone()).is_ok() {
+            let _ = self.controller_send.send(ServerEvent::PacketSent(packet));
+        }
+    }
+
+    /// Prints to console if debug mode is enabled.
+    fn log(&self, message: &str) {
+        if self.debug {
+            println!("{} {message}", format!("[SERVER #{}]", self.id).blue());
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::thread::{self, JoinHandle};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use wg_2024::config::Config;
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::drone::Drone;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{NodeType, Packet};
+use crate::client::{Client, ClientCommand, ClientEvent};
+use crate::discovery::{NetworkGraph, RouteRepair, TopologyHandle};
+use crate::flood_limit::{FloodLimitHandle, FloodPolicy, FloodStats};
+use crate::topology::{validate_config, TopologyError};
+use crate::transport::TransportStats;
+use crate::BetterCallDrone;
+
+/// A running network of `BetterCallDrone`s built from a validated configuration.
+///
+/// Drones run in their own threads. Clients and servers are not spawned: the packets
+/// the drones send them are collected and can be read with [`Simulation::endpoint_packets`].
+/// Every command is checked against the WG rules before it reaches the drones.
+/// A drone learns the network from the flood responses it relays once learning is turned on, see
+/// [`Simulation::set_learning`], and can have its floods rate limited, see
+/// [`Simulation::set_flood_policy`].
+pub struct Simulation {
+    config: Config,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    endpoint_recv: HashMap<NodeId, Receiver<Packet>>,
+    command_send: HashMap<NodeId, Sender<DroneCommand>>,
+    event_recv: Receiver<DroneEvent>,
+    handles: HashMap<NodeId, JoinHandle<()>>,
+    topologies: HashMap<NodeId, TopologyHandle>,
+    flood_limits: HashMap<NodeId, FloodLimitHandle>,
+}
+
+/// The channels of a client or server taken out of a [`Simulation`].
+pub struct Endpoint {
+    /// The receiver of the packets sent to the node.
+    pub packet_recv: Receiver<Packet>,
+    /// The senders of the drones connected to the node.
+    pub packet_send: HashMap<NodeId, Sender<Packet>>,
+}
+
+/// A [`Client`] node running on the channels of an endpoint, see [`Simulation::spawn_client`].
+pub struct ClientHandle {
+    /// The sender of the commands of the node.
+    pub commands: Sender<ClientCommand>,
+    /// The receiver of the events of the node.
+    pub events: Receiver<ClientEvent>,
+    handle: JoinHandle<TransportStats>,
+}
+
+impl ClientHandle {
+    /// Crashes the node and waits for its thread to stop.
+    ///
+    /// # Returns
+    /// The counters of the transport of the node.
+    #[must_use]
+    pub fn stop(self) -> TransportStats {
+        let _ = self.commands.send(ClientCommand::Crash);
+        self.handle.join().unwrap_or_default()
+    }
+}
+
+/// A reason why the simulation refused a request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationError {
+    /// The requested node does not exist or has crashed.
+    UnknownNode(NodeId),
+    /// The requested node exists but is not a drone.
+    NotADrone(NodeId),
+    /// The request would leave the network in an invalid state.
+    InvalidTopology(Vec<TopologyError>),
+}
+
+impl std::fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::UnknownNode(id) => write!(f, "node {id} does not exist"),
+            SimulationError::NotADrone(id) => write!(f, "node {id} is not a drone"),
+            SimulationError::InvalidTopology(errors) => {
+                write!(f, "the resulting topology would be invalid:")?;
+                for error in errors {
+                    write!(f, "\n  - {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+impl Simulation {
+    /// Validates the configuration and spawns a `BetterCallDrone` for each of its drones.
+    ///
+    /// # Parameters
+    /// - `config`: The network configuration.
+    ///
+    /// # Errors
+    /// Returns `SimulationError::InvalidTopology` if the configuration breaks the WG rules.
+    pub fn spawn(config: Config) -> Result<Self, SimulationError> {
+        validate_config(&config).map_err(SimulationError::InvalidTopology)?;
+
+        let mut packet_send = HashMap::new();
+        let mut packet_recv = HashMap::new();
+        let ids = config.drone.iter().map(|d| d.id)
+            .chain(config.client.iter().map(|c| c.id))
+            .chain(config.server.iter().map(|s| s.id));
+        for id in ids {
+            let (send, recv) = unbounded();
+            packet_send.insert(id, send);
+            packet_recv.insert(id, recv);
+        }
+
+        let (event_send, event_recv) = unbounded();
+        let mut command_send = HashMap::new();
+        let mut handles = HashMap::new();
+        let mut topologies = HashMap::new();
+        let mut flood_limits = HashMap::new();
+        for d in &config.drone {
+            let (c_send, c_recv) = unbounded();
+            let neighbors = d.connected_node_ids.iter().map(|id| (*id, packet_send[id].clone())).collect();
+            let mut drone = BetterCallDrone::new(
+                d.id,
+                event_send.clone(),
+                c_recv,
+                packet_recv.remove(&d.id).expect("every node has a channel"),
+                neighbors,
+                d.pdr,
+            );
+            let topology = drone.learn_topology();
+            topology.set_learning(false);
+            topologies.insert(d.id, topology);
+            flood_limits.insert(d.id, drone.limit_floods());
+            command_send.insert(d.id, c_send);
+            handles.insert(d.id, thread::spawn(move || drone.run()));
+        }
+
+        Ok(Self {
+            config,
+            packet_send,
+            endpoint_recv: packet_recv,
+            command_send,
+            event_recv,
+            handles,
+            topologies,
+            flood_limits,
+        })
+    }
+
+    /// Returns the current topology, updated by every accepted command.
+    #[must_use]
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Turns learning on or off for a drone, see [`TopologyHandle::set_learning`]. Learning is off
+    /// when the simulation is spawned.
+    ///
+    /// # Errors
+    /// Returns an error if the node is not a running drone.
+    pub fn set_learning(&self, drone_id: NodeId, enabled: bool) -> Result<(), SimulationError> {
+        let topology = self.topologies.get(&drone_id).ok_or_else(|| self.not_a_drone(drone_id))?;
+        topology.set_learning(enabled);
+        Ok(())
+    }
+
+    /// Returns whether a drone is learning from the flood responses it relays.
+    ///
+    /// # Errors
+    /// Returns an error if the node is not a running drone.
+    pub fn is_learning(&self, drone_id: NodeId) -> Result<bool, SimulationError> {
+        self.topologies.get(&drone_id).map(TopologyHandle::learning).ok_or_else(|| self.not_a_drone(drone_id))
+    }
+
+    /// Returns what a drone has learned from the flood responses it relayed.
+    ///
+    /// # Errors
+    /// Returns an error if the node is not a running drone.
+    pub fn learned_topology(&self, drone_id: NodeId) -> Result<NetworkGraph, SimulationError> {
+        self.topologies.get(&drone_id).map(TopologyHandle::snapshot).ok_or_else(|| self.not_a_drone(drone_id))
+    }
+
+    /// Turns route repair on or off for a drone, see [`TopologyHandle::set_route_repair`].
+    ///
+    /// # Errors
+    /// Returns an error if the node is not a running drone.
+    pub fn set_route_repair(&self, drone_id: NodeId, enabled: bool) -> Result<(), SimulationError> {
+        let topology = self.topologies.get(&drone_id).ok_or_else(|| self.not_a_drone(drone_id))?;
+        topology.set_route_repair(enabled);
+        Ok(())
+    }
+
+    /// Returns the last routes a drone repaired, oldest first.
+    ///
+    /// # Errors
+    /// Returns an error if the node is not a running drone.
+    pub fn route_repairs(&self, drone_id: NodeId) -> Result<Vec<RouteRepair>, SimulationError> {
+        self.topologies.get(&drone_id).map(TopologyHandle::repairs).ok_or_else(|| self.not_a_drone(drone_id))
+    }
+
+    /// Sets or removes the flood rate limit of a drone, see [`FloodLimitHandle::set_policy`].
+    ///
+    /// # Errors
+    /// Returns an error if the node is not a running drone.
+    pub fn set_flood_policy(&self, drone_id: NodeId, policy: Option<FloodPolicy>) -> Result<(), SimulationError> {
+        let limit = self.flood_limits.get(&drone_id).ok_or_else(|| self.not_a_drone(drone_id))?;
+        limit.set_policy(policy);
+        Ok(())
+    }
+
+    /// Returns how many new floods a drone forwarded and throttled.
+    ///
+    /// # Errors
+    /// Returns an error if the node is not a running drone.
+    pub fn flood_stats(&self, drone_id: NodeId) -> Result<FloodStats, SimulationError> {
+        self.flood_limits.get(&drone_id).map(FloodLimitHandle::stats).ok_or_else(|| self.not_a_drone(drone_id))
+    }
+
+    /// Returns the receiver of the events sent by the drones.
+    #[must_use]
+    pub fn events(&self) -> &Receiver<DroneEvent> {
+        &self.event_recv
+    }
+
+    /// Returns the receivers of the packets delivered to clients and servers, by node id.
+    #[must_use]
+    pub fn endpoint_packets(&self) -> &HashMap<NodeId, Receiver<Packet>> {
+        &self.endpoint_recv
+    }
+
+    /// Takes the channels of a client or server, to run a real node on them.
+    ///
+    /// # Parameters
+    /// - `node_id`: The ID of the client or server.
+    ///
+    /// # Returns
+    /// The channels of the node, or `None` if the node is not a client or server or its channels
+    /// were already taken. Links added later with [`Simulation::add_link`] are not reported to the node.
+    pub fn take_endpoint(&mut self, node_id: NodeId) -> Option<Endpoint> {
+        let neighbors = self.config.client.iter().find(|c| c.id == node_id).map(|c| &c.connected_drone_ids)
+            .or_else(|| self.config.server.iter().find(|s| s.id == node_id).map(|s| &s.connected_drone_ids))?;
+        let packet_send = neighbors.iter()
+            .filter_map(|id| Some((*id, self.packet_send.get(id)?.clone())))
+            .collect();
+        let packet_recv = self.endpoint_recv.remove(&node_id)?;
+        Some(Endpoint { packet_recv, packet_send })
+    }
+
+    /// Runs a [`Client`] node in its own thread on the channels of a client or server.
+    ///
+    /// # Parameters
+    /// - `node_id`: The ID of the client or server.
+    /// - `node_type`: The type the node declares in flood responses.
+    ///
+    /// # Returns
+    /// The channels of the node, or `None` if [`Simulation::take_endpoint`] fails.
+    pub fn spawn_client(&mut self, node_id: NodeId, node_type: NodeType) -> Option<ClientHandle> {
+        let endpoint = self.take_endpoint(node_id)?;
+        let (command_send, command_recv) = unbounded();
+        let (event_send, event_recv) = unbounded();
+        let mut client = Client::new(node_id, event_send, command_recv, endpoint.packet_recv, endpoint.packet_send);
+        client.set_node_type(node_type);
+        let handle = thread::spawn(move || {
+            client.run();
+            client.transport_stats()
+        });
+        Some(ClientHandle { commands: command_send, events: event_recv, handle })
+    }
+
+    /// Injects a packet into the channel of a node, as if a neighbor had sent it.
+    ///
+    /// # Errors
+    /// Returns `SimulationError::UnknownNode` if the node does not exist.
+    pub fn inject(&self, node_id: NodeId, packet: Packet) -> Result<(), SimulationError> {
+        let sender = self.packet_send.get(&node_id).ok_or(SimulationError::UnknownNode(node_id))?;
+        sender.send(packet).map_err(|_| SimulationError::UnknownNode(node_id))
+    }
+
+    /// Connects two nodes, sending `AddSender` to the drones among them.
+    ///
+    /// # Errors
+    /// Returns an error if a node does not exist or the link breaks the WG rules.
+    pub fn add_link(&mut self, a: NodeId, b: NodeId) -> Result<(), SimulationError> {
+        let mut config = self.config.clone();
+        for (from, to) in [(a, b), (b, a)] {
+            neighbors_mut(&mut config, from).ok_or(SimulationError::UnknownNode(from))?.push(to);
+        }
+        self.apply(config)?;
+        self.send_command(a, DroneCommand::AddSender(b, self.packet_send[&b].clone()));
+        self.send_command(b, DroneCommand::AddSender(a, self.packet_send[&a].clone()));
+        Ok(())
+    }
+
+    /// Disconnects two nodes, sending `RemoveSender` to the drones among them.
+    ///
+    /// # Errors
+    /// Returns an error if a node does not exist or the network would become invalid.
+    pub fn remove_link(&mut self, a: NodeId, b: NodeId) -> Result<(), SimulationError> {
+        let mut config = self.config.clone();
+        for (from, to) in [(a, b), (b, a)] {
+            neighbors_mut(&mut config, from).ok_or(SimulationError::UnknownNode(from))?.retain(|&id| id != to);
+        }
+        self.apply(config)?;
+        self.send_command(a, DroneCommand::RemoveSender(b));
+        self.send_command(b, DroneCommand::RemoveSender(a));
+        Ok(())
+    }
+
+    /// Changes the PDR of a drone.
+    ///
+    /// # Errors
+    /// Returns an error if the node is not a drone or the PDR is outside `[0, 1]`.
+    pub fn set_pdr(&mut self, drone_id: NodeId, pdr: f32) -> Result<(), SimulationError> {
+        let mut config = self.config.clone();
+        config.drone.iter_mut()
+            .find(|d| d.id == drone_id)
+            .ok_or_else(|| self.not_a_drone(drone_id))?
+            .pdr = pdr;
+        self.apply(config)?;
+        self.send_command(drone_id, DroneCommand::SetPacketDropRate(pdr));
+        Ok(())
+    }
+
+    /// Crashes a drone: its neighbors remove it and the drone drains its queue and stops.
+    ///
+    /// # Errors
+    /// Returns an error if the node is not a drone or the network would become invalid without it.
+    pub fn crash(&mut self, drone_id: NodeId) -> Result<(), SimulationError> {
+        let mut config = self.config.clone();
+        let position = config.drone.iter()
+            .position(|d| d.id == drone_id)
+            .ok_or_else(|| self.not_a_drone(drone_id))?;
+        let neighbors = config.drone.remove(position).connected_node_ids;
+        for &neighbor in &neighbors {
+            if let Some(links) = neighbors_mut(&mut config, neighbor) {
+                links.retain(|&id| id != drone_id);
+            }
+        }
+        self.apply(config)?;
+
+        for neighbor in neighbors {
+            self.send_command(neighbor, DroneCommand::RemoveSender(drone_id));
+        }
+        self.send_command(drone_id, DroneCommand::Crash);
+        self.command_send.remove(&drone_id);
+        self.packet_send.remove(&drone_id);
+        self.topologies.remove(&drone_id);
+        self.flood_limits.remove(&drone_id);
+        if let Some(handle) = self.handles.remove(&drone_id) {
+            let _ = handle.join();
+        }
+        Ok(())
+    }
+
+    /// Crashes every drone still running and waits for their threads to stop.
+    pub fn shutdown(&mut self) {
+        for (_, sender) in self.command_send.drain() {
+            let _ = sender.send(DroneCommand::Crash);
+        }
+        for (_, handle) in self.handles.drain() {
+            let _ = handle.join();
+        }
+    }
+
+    /// Replaces the current topology if the new one is valid.
+    fn apply(&mut self, config: Config) -> Result<(), SimulationError> {
+        validate_config(&config).map_err(SimulationError::InvalidTopology)?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Sends a command to a node, if it is a running drone.
+    fn send_command(&self, node_id: NodeId, command: DroneCommand) {
+        if let Some(sender) = self.command_send.get(&node_id) {
+            let _ = sender.send(command);
+        }
+    }
+
+    /// Returns the error for a node that was expected to be a drone.
+    fn not_a_drone(&self, node_id: NodeId) -> SimulationError {
+        if self.packet_send.contains_key(&node_id) {
+            SimulationError::NotADrone(node_id)
+        } else {
+            SimulationError::UnknownNode(node_id)
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Returns the neighbor list of any node of the configuration.
+fn neighbors_mut(config: &mut Config, node_id: NodeId) -> Option<&mut Vec<NodeId>> {
+    if let Some(d) = config.drone.iter_mut().find(|d| d.id == node_id) {
+        return Some(&mut d.connected_node_ids);
+    }
+    if let Some(c) = config.client.iter_mut().find(|c| c.id == node_id) {
+        return Some(&mut c.connected_drone_ids);
+    }
+    config.server.iter_mut().find(|s| s.id == node_id).map(|s| &mut s.connected_drone_ids)
+}
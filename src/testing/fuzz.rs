@@ -0,0 +1,214 @@
+//! Inputs of the fuzz targets in `fuzz/`, decoded from raw bytes with `arbitrary`, and the
+//! harnesses feeding them to a `BetterCallDrone`. Needs the `fuzz` feature.
+//!
+//! Node IDs are taken modulo [`NODES`] so that the routes often go through the drone and its
+//! neighbors. The drone has ID 1 and neighbors 0, 2 and 3, and a neighbor 4 whose channel is
+//! closed, so that its sends fail.
+
+use std::thread;
+use arbitrary::Arbitrary;
+use crossbeam_channel::unbounded;
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::drone::Drone;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType};
+use super::DirectDrone;
+
+/// The number of node IDs used in the inputs.
+pub const NODES: u8 = 8;
+/// The ID of the drone under test.
+pub const DRONE: NodeId = 1;
+/// The neighbors of the drone; the last one has a closed channel.
+const NEIGHBORS: [(NodeId, bool); 4] = [(0, true), (2, true), (3, true), (4, false)];
+
+/// A node ID, taken modulo [`NODES`].
+#[derive(Debug, Clone, Copy, Arbitrary)]
+pub struct Node(u8);
+
+impl From<Node> for NodeId {
+    fn from(node: Node) -> Self {
+        node.0 % NODES
+    }
+}
+
+/// One input of the drone.
+#[derive(Debug, Clone, Arbitrary)]
+pub enum FuzzOp {
+    /// A packet received from a neighbor.
+    Packet(FuzzPacket),
+    /// `AddSender`, with a live or a closed channel.
+    AddSender { node: Node, live: bool },
+    /// `RemoveSender`.
+    RemoveSender(Node),
+    /// `SetPacketDropRate`, with any value.
+    SetPacketDropRate(f32),
+    /// `Crash`.
+    Crash,
+}
+
+/// A `Packet` with any route, including routes that do not go through the drone.
+#[derive(Debug, Clone, Arbitrary)]
+pub struct FuzzPacket {
+    pub pack_type: FuzzPacketType,
+    pub hops: Vec<Node>,
+    pub hop_index: u8,
+    pub session_id: u64,
+}
+
+/// A `PacketType`.
+#[derive(Debug, Clone, Arbitrary)]
+pub enum FuzzPacketType {
+    MsgFragment { fragment_index: u64, total_n_fragments: u64, data: Vec<u8> },
+    Ack { fragment_index: u64 },
+    Nack { fragment_index: u64, nack_type: FuzzNackType },
+    FloodRequest { flood_id: u64, initiator_id: Node, path_trace: Vec<(Node, FuzzNodeType)> },
+    FloodResponse { flood_id: u64, path_trace: Vec<(Node, FuzzNodeType)> },
+}
+
+/// A `NackType`.
+#[derive(Debug, Clone, Copy, Arbitrary)]
+pub enum FuzzNackType {
+    ErrorInRouting(Node),
+    DestinationIsDrone,
+    Dropped,
+    UnexpectedRecipient(Node),
+}
+
+/// A `NodeType`.
+#[derive(Debug, Clone, Copy, Arbitrary)]
+pub enum FuzzNodeType {
+    Client,
+    Drone,
+    Server,
+}
+
+impl From<FuzzPacket> for Packet {
+    fn from(packet: FuzzPacket) -> Self {
+        let trace = |path_trace: Vec<(Node, FuzzNodeType)>| -> Vec<(NodeId, NodeType)> {
+            path_trace.into_iter().map(|(node, node_type)| (node.into(), node_type.into())).collect()
+        };
+        let pack_type = match packet.pack_type {
+            FuzzPacketType::MsgFragment { fragment_index, total_n_fragments, data } => {
+                let length = data.len().min(128);
+                let mut bytes = [0; 128];
+                bytes[..length].copy_from_slice(&data[..length]);
+                PacketType::MsgFragment(Fragment {
+                    fragment_index,
+                    total_n_fragments,
+                    length: u8::try_from(length).expect("at most 128"),
+                    data: bytes,
+                })
+            }
+            FuzzPacketType::Ack { fragment_index } => PacketType::Ack(Ack { fragment_index }),
+            FuzzPacketType::Nack { fragment_index, nack_type } => PacketType::Nack(Nack { fragment_index, nack_type: nack_type.into() }),
+            FuzzPacketType::FloodRequest { flood_id, initiator_id, path_trace } => PacketType::FloodRequest(FloodRequest {
+                flood_id,
+                initiator_id: initiator_id.into(),
+                path_trace: trace(path_trace),
+            }),
+            FuzzPacketType::FloodResponse { flood_id, path_trace } => {
+                PacketType::FloodResponse(FloodResponse { flood_id, path_trace: trace(path_trace) })
+            }
+        };
+        Packet {
+            pack_type,
+            routing_header: SourceRoutingHeader {
+                hop_index: usize::from(packet.hop_index),
+                hops: packet.hops.into_iter().map(NodeId::from).collect(),
+            },
+            session_id: packet.session_id,
+        }
+    }
+}
+
+impl From<FuzzNackType> for NackType {
+    fn from(nack_type: FuzzNackType) -> Self {
+        match nack_type {
+            FuzzNackType::ErrorInRouting(node) => NackType::ErrorInRouting(node.into()),
+            FuzzNackType::DestinationIsDrone => NackType::DestinationIsDrone,
+            FuzzNackType::Dropped => NackType::Dropped,
+            FuzzNackType::UnexpectedRecipient(node) => NackType::UnexpectedRecipient(node.into()),
+        }
+    }
+}
+
+impl From<FuzzNodeType> for NodeType {
+    fn from(node_type: FuzzNodeType) -> Self {
+        match node_type {
+            FuzzNodeType::Client => NodeType::Client,
+            FuzzNodeType::Drone => NodeType::Drone,
+            FuzzNodeType::Server => NodeType::Server,
+        }
+    }
+}
+
+/// Decodes raw bytes like the `packet_path` target does, e.g. to replay a crash artifact in a test.
+#[must_use]
+pub fn decode(data: &[u8]) -> Vec<FuzzOp> {
+    Vec::arbitrary_take_rest(arbitrary::Unstructured::new(data)).unwrap_or_default()
+}
+
+/// Feeds the inputs to `handle_packet` and `handle_command` of a drone, in the calling thread.
+///
+/// # Panics
+/// Panics if the drone emits `PacketSent` for a packet no neighbor received.
+pub fn drive(ops: Vec<FuzzOp>) {
+    let mut direct = DirectDrone::new(DRONE, &NEIGHBORS, 0.5, 0);
+    for op in ops {
+        match op {
+            FuzzOp::Packet(packet) => direct.drone.handle_packet(packet.into()),
+            FuzzOp::AddSender { node, live } => direct.add_sender(node.into(), live),
+            FuzzOp::RemoveSender(node) => direct.drone.handle_command(DroneCommand::RemoveSender(node.into())),
+            FuzzOp::SetPacketDropRate(pdr) => direct.drone.handle_command(DroneCommand::SetPacketDropRate(pdr)),
+            FuzzOp::Crash => direct.drone.handle_command(DroneCommand::Crash),
+        }
+    }
+
+    let (packets, events) = direct.outputs();
+    let mut received: Vec<Packet> = packets.into_iter().map(|(_, packet)| packet).collect();
+    for event in events {
+        if let DroneEvent::PacketSent(packet) = event {
+            match received.iter().position(|p| *p == packet) {
+                Some(index) => {
+                    received.swap_remove(index);
+                }
+                None => panic!("PacketSent for a packet that was not sent: {packet:?}"),
+            }
+        }
+    }
+}
+
+/// Sends the inputs to a drone running in its own thread, then stops it by sending `Crash` or,
+/// if `disconnect`, by closing its channels. A drone that never stops hangs the fuzz target.
+pub fn run(ops: Vec<FuzzOp>, disconnect: bool) {
+    let DirectDrone { mut drone, commands, packets, mut neighbors, events } = DirectDrone::new(DRONE, &NEIGHBORS, 0.5, 0);
+    let handle = thread::spawn(move || drone.run());
+
+    for op in ops {
+        let command = match op {
+            FuzzOp::Packet(packet) => {
+                let _ = packets.send(packet.into());
+                continue;
+            }
+            FuzzOp::AddSender { node, live } => {
+                let (send, recv) = unbounded();
+                if live {
+                    neighbors.push((node.into(), recv));
+                }
+                DroneCommand::AddSender(node.into(), send)
+            }
+            FuzzOp::RemoveSender(node) => DroneCommand::RemoveSender(node.into()),
+            FuzzOp::SetPacketDropRate(pdr) => DroneCommand::SetPacketDropRate(pdr),
+            FuzzOp::Crash => DroneCommand::Crash,
+        };
+        let _ = commands.send(command);
+    }
+    if disconnect {
+        drop(commands);
+        drop(packets);
+    } else {
+        let _ = commands.send(DroneCommand::Crash);
+    }
+    handle.join().expect("the drone does not panic");
+    drop((neighbors, events));
+}
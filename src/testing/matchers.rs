@@ -0,0 +1,373 @@
+//! Matchers on the packets and events a drone produces, checked field by field.
+//!
+//! Every mismatching field is reported on its own line, next to a one-line summary of the packet
+//! that was received instead of its full `Debug` output.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use wg_2024::controller::DroneEvent;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{NackType, NodeType, Packet, PacketType};
+use crate::repl::{describe_event, describe_packet};
+use super::{DroneFixture, TIMEOUT};
+
+/// Values captured by [`PacketMatcher::session_var`] while checking a list of expectations.
+pub type Bindings = HashMap<String, u64>;
+
+type Check = Box<dyn Fn(&Packet, &mut Bindings) -> Result<(), String>>;
+
+/// A pattern on a `Packet`.
+pub struct PacketMatcher {
+    description: Vec<String>,
+    checks: Vec<Check>,
+}
+
+impl PacketMatcher {
+    /// Matches any packet.
+    #[must_use]
+    pub fn any() -> Self {
+        Self { description: Vec::new(), checks: Vec::new() }
+    }
+
+    /// Matches a `MsgFragment`.
+    #[must_use]
+    pub fn fragment() -> Self {
+        Self::kind("MsgFragment", |t| matches!(t, PacketType::MsgFragment(_)))
+    }
+
+    /// Matches an `Ack`.
+    #[must_use]
+    pub fn ack() -> Self {
+        Self::kind("Ack", |t| matches!(t, PacketType::Ack(_)))
+    }
+
+    /// Matches a `Nack` of any type.
+    #[must_use]
+    pub fn nack() -> Self {
+        Self::kind("Nack", |t| matches!(t, PacketType::Nack(_)))
+    }
+
+    /// Matches a `FloodRequest`.
+    #[must_use]
+    pub fn flood_request() -> Self {
+        Self::kind("FloodRequest", |t| matches!(t, PacketType::FloodRequest(_)))
+    }
+
+    /// Matches a `FloodResponse`.
+    #[must_use]
+    pub fn flood_response() -> Self {
+        Self::kind("FloodResponse", |t| matches!(t, PacketType::FloodResponse(_)))
+    }
+
+    fn kind(name: &'static str, accept: fn(&PacketType) -> bool) -> Self {
+        Self::any().with(name.to_string(), move |packet, _| {
+            if accept(&packet.pack_type) {
+                Ok(())
+            } else {
+                Err(format!("type: expected {name}, got {}", kind_name(&packet.pack_type)))
+            }
+        })
+    }
+
+    /// Requires the type of a `Nack`.
+    #[must_use]
+    pub fn nack_type(self, expected: NackType) -> Self {
+        self.with(format!("type {expected:?}"), move |packet, _| match &packet.pack_type {
+            PacketType::Nack(nack) => compare("nack_type", &expected, &nack.nack_type),
+            other => Err(format!("nack_type: expected {expected:?}, got a {}", kind_name(other))),
+        })
+    }
+
+    /// Requires the fragment index of a `MsgFragment`, `Ack` or `Nack`.
+    #[must_use]
+    pub fn fragment_index(self, expected: u64) -> Self {
+        self.with(format!("fragment {expected}"), move |packet, _| match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => compare("fragment_index", &expected, &fragment.fragment_index),
+            PacketType::Ack(ack) => compare("fragment_index", &expected, &ack.fragment_index),
+            PacketType::Nack(nack) => compare("fragment_index", &expected, &nack.fragment_index),
+            other => Err(format!("fragment_index: expected {expected}, but a {} has none", kind_name(other))),
+        })
+    }
+
+    /// Requires the session ID.
+    #[must_use]
+    pub fn session(self, expected: u64) -> Self {
+        self.with(format!("session {expected}"), move |packet, _| compare("session_id", &expected, &packet.session_id))
+    }
+
+    /// Captures the session ID under `name` the first time, then requires the same session ID.
+    #[must_use]
+    pub fn session_var(self, name: &str) -> Self {
+        let name = name.to_string();
+        self.with(format!("session ${name}"), move |packet, bindings| {
+            match bindings.get(&name) {
+                Some(&expected) => compare(&format!("session_id (${name})"), &expected, &packet.session_id),
+                None => {
+                    bindings.insert(name.clone(), packet.session_id);
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    /// Requires the hops of the routing header.
+    #[must_use]
+    pub fn hops(self, expected: &[NodeId]) -> Self {
+        let expected = expected.to_vec();
+        self.with(format!("hops {expected:?}"), move |packet, _| compare("hops", &expected, &packet.routing_header.hops))
+    }
+
+    /// Requires the hop index of the routing header.
+    #[must_use]
+    pub fn hop_index(self, expected: usize) -> Self {
+        self.with(format!("hop_index {expected}"), move |packet, _| compare("hop_index", &expected, &packet.routing_header.hop_index))
+    }
+
+    /// Requires the flood ID of a `FloodRequest` or `FloodResponse`.
+    #[must_use]
+    pub fn flood_id(self, expected: u64) -> Self {
+        self.with(format!("flood {expected}"), move |packet, _| match &packet.pack_type {
+            PacketType::FloodRequest(request) => compare("flood_id", &expected, &request.flood_id),
+            PacketType::FloodResponse(response) => compare("flood_id", &expected, &response.flood_id),
+            other => Err(format!("flood_id: expected {expected}, but a {} has none", kind_name(other))),
+        })
+    }
+
+    /// Requires the path trace of a `FloodRequest` or `FloodResponse`.
+    #[must_use]
+    pub fn path_trace(self, expected: &[(NodeId, NodeType)]) -> Self {
+        let expected = expected.to_vec();
+        self.with(format!("path trace {expected:?}"), move |packet, _| match &packet.pack_type {
+            PacketType::FloodRequest(request) => compare("path_trace", &expected, &request.path_trace),
+            PacketType::FloodResponse(response) => compare("path_trace", &expected, &response.path_trace),
+            other => Err(format!("path_trace: expected {expected:?}, but a {} has none", kind_name(other))),
+        })
+    }
+
+    /// Requires a custom property.
+    ///
+    /// # Parameters
+    /// - `description`: What the property means, printed in failures.
+    /// - `accept`: Returns whether a packet has the property.
+    #[must_use]
+    pub fn satisfying(self, description: &str, accept: impl Fn(&Packet) -> bool + 'static) -> Self {
+        let name = description.to_string();
+        self.with(name.clone(), move |packet, _| {
+            if accept(packet) {
+                Ok(())
+            } else {
+                Err(format!("expected {name}"))
+            }
+        })
+    }
+
+    fn with(mut self, description: String, check: impl Fn(&Packet, &mut Bindings) -> Result<(), String> + 'static) -> Self {
+        self.description.push(description);
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    /// Checks a packet.
+    ///
+    /// # Returns
+    /// One line per mismatching field, or nothing if the packet matches. Variables are only
+    /// captured if the whole packet matches.
+    pub fn mismatches(&self, packet: &Packet, bindings: &mut Bindings) -> Vec<String> {
+        let mut captured = bindings.clone();
+        let mismatches: Vec<String> = self.checks.iter().filter_map(|check| check(packet, &mut captured).err()).collect();
+        if mismatches.is_empty() {
+            *bindings = captured;
+        }
+        mismatches
+    }
+
+    /// Returns whether a packet matches, without variables.
+    #[must_use]
+    pub fn matches(&self, packet: &Packet) -> bool {
+        self.mismatches(packet, &mut Bindings::new()).is_empty()
+    }
+}
+
+impl std::fmt::Display for PacketMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.description.is_empty() {
+            write!(f, "any packet")
+        } else {
+            write!(f, "{}", self.description.join(", "))
+        }
+    }
+}
+
+/// A pattern on a `DroneEvent`.
+pub struct EventMatcher {
+    kind: EventKind,
+    packet: PacketMatcher,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EventKind {
+    PacketSent,
+    PacketDropped,
+    ControllerShortcut,
+}
+
+impl EventMatcher {
+    /// Matches `PacketSent` with a packet matching `packet`.
+    #[must_use]
+    pub fn packet_sent(packet: PacketMatcher) -> Self {
+        Self { kind: EventKind::PacketSent, packet }
+    }
+
+    /// Matches `PacketDropped` with a packet matching `packet`.
+    #[must_use]
+    pub fn packet_dropped(packet: PacketMatcher) -> Self {
+        Self { kind: EventKind::PacketDropped, packet }
+    }
+
+    /// Matches `ControllerShortcut` with a packet matching `packet`.
+    #[must_use]
+    pub fn controller_shortcut(packet: PacketMatcher) -> Self {
+        Self { kind: EventKind::ControllerShortcut, packet }
+    }
+
+    /// Checks an event, see [`PacketMatcher::mismatches`].
+    pub fn mismatches(&self, event: &DroneEvent, bindings: &mut Bindings) -> Vec<String> {
+        let (kind, packet) = match event {
+            DroneEvent::PacketSent(packet) => (EventKind::PacketSent, packet),
+            DroneEvent::PacketDropped(packet) => (EventKind::PacketDropped, packet),
+            DroneEvent::ControllerShortcut(packet) => (EventKind::ControllerShortcut, packet),
+        };
+        if kind == self.kind {
+            self.packet.mismatches(packet, bindings)
+        } else {
+            vec![format!("event: expected {:?}, got {kind:?}", self.kind)]
+        }
+    }
+}
+
+impl std::fmt::Display for EventMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} of {}", self.kind, self.packet)
+    }
+}
+
+/// Where an expectation reads from.
+enum Step {
+    Neighbor(NodeId, PacketMatcher),
+    Controller(EventMatcher),
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Neighbor(id, matcher) => write!(f, "node {id} receives {matcher}"),
+            Step::Controller(matcher) => write!(f, "controller receives {matcher}"),
+        }
+    }
+}
+
+/// A list of expectations on the channels of a [`DroneFixture`].
+///
+/// Expectations on the same channel must be met in order; expectations on different channels
+/// are independent, since the drone gives no ordering guarantee between its channels.
+/// Variables captured by [`PacketMatcher::session_var`] are shared by all the expectations.
+pub struct Expectations<'a> {
+    fixture: &'a DroneFixture,
+    steps: Vec<Step>,
+    skip_unmatched: bool,
+}
+
+impl<'a> Expectations<'a> {
+    pub(super) fn new(fixture: &'a DroneFixture) -> Self {
+        Self { fixture, steps: Vec::new(), skip_unmatched: false }
+    }
+
+    /// Expects the next packet received by a mock neighbor to match `matcher`.
+    #[must_use]
+    pub fn neighbor(mut self, neighbor: NodeId, matcher: PacketMatcher) -> Self {
+        self.steps.push(Step::Neighbor(neighbor, matcher));
+        self
+    }
+
+    /// Expects the next event of the controller to match `matcher`.
+    #[must_use]
+    pub fn controller(mut self, matcher: EventMatcher) -> Self {
+        self.steps.push(Step::Controller(matcher));
+        self
+    }
+
+    /// Skips the packets and events that match no expectation instead of failing.
+    #[must_use]
+    pub fn skip_unmatched(mut self) -> Self {
+        self.skip_unmatched = true;
+        self
+    }
+
+    /// Checks every expectation, waiting up to [`TIMEOUT`] for each message.
+    ///
+    /// # Returns
+    /// The captured variables.
+    ///
+    /// # Panics
+    /// Panics with a report of the met expectations and the mismatching fields of the first
+    /// expectation not met.
+    pub fn verify(self) -> Bindings {
+        let mut bindings = Bindings::new();
+        let mut report = String::new();
+        for (index, step) in self.steps.iter().enumerate() {
+            let mut skipped = Vec::new();
+            loop {
+                let (received, mismatches) = match step {
+                    Step::Neighbor(id, matcher) => match self.fixture.neighbor(*id).recv_timeout(TIMEOUT) {
+                        Ok(packet) => (Some(describe_packet(&packet)), matcher.mismatches(&packet, &mut bindings)),
+                        Err(_) => (None, vec![format!("no packet within {TIMEOUT:?}")]),
+                    },
+                    Step::Controller(matcher) => match self.fixture.events.recv_timeout(TIMEOUT) {
+                        Ok(event) => (Some(describe_event(&event)), matcher.mismatches(&event, &mut bindings)),
+                        Err(_) => (None, vec![format!("no event within {TIMEOUT:?}")]),
+                    },
+                };
+                if mismatches.is_empty() {
+                    let _ = writeln!(report, "  ✓ {step}");
+                    break;
+                }
+                if let (true, Some(received)) = (self.skip_unmatched, &received) {
+                    skipped.push(received.clone());
+                    continue;
+                }
+                let _ = writeln!(report, "  ✗ {step}");
+                for mismatch in mismatches {
+                    let _ = writeln!(report, "      {mismatch}");
+                }
+                let _ = writeln!(report, "    received: {}", received.as_deref().unwrap_or("nothing"));
+                for packet in skipped {
+                    let _ = writeln!(report, "    skipped:  {packet}");
+                }
+                let remaining = self.steps.len() - index - 1;
+                if remaining > 0 {
+                    let _ = writeln!(report, "  ({remaining} more expectations not checked)");
+                }
+                panic!("expectation {} of {} not met:\n{report}", index + 1, self.steps.len());
+            }
+        }
+        bindings
+    }
+}
+
+fn compare<T: PartialEq + std::fmt::Debug>(field: &str, expected: &T, actual: &T) -> Result<(), String> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(format!("{field}: expected {expected:?}, got {actual:?}"))
+    }
+}
+
+fn kind_name(pack_type: &PacketType) -> &'static str {
+    match pack_type {
+        PacketType::MsgFragment(_) => "MsgFragment",
+        PacketType::Ack(_) => "Ack",
+        PacketType::Nack(_) => "Nack",
+        PacketType::FloodRequest(_) => "FloodRequest",
+        PacketType::FloodResponse(_) => "FloodResponse",
+    }
+}
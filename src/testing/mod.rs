@@ -0,0 +1,444 @@
+//! Test support: a fixture running one drone between mock neighbors and a mock controller,
+//! one running a whole network of drones, helpers to build packets and assertions on what the
+//! drone sends.
+//!
+//! ```ignore
+//! let drone = DroneFixture::spawn(11, &[1, 12], 0.0);
+//! let packet = fragment(1, &[1, 11, 12]);
+//! drone.send(&packet);
+//! drone.assert_packet(12, &forwarded(&packet));
+//! drone.assert_event(&DroneEvent::PacketSent(forwarded(&packet)));
+//! ```
+//!
+//! For packets where only a few fields matter, [`matchers`] describes them field by field:
+//!
+//! ```ignore
+//! drone.expect()
+//!     .neighbor(1, PacketMatcher::nack().nack_type(NackType::Dropped).fragment_index(1).session_var("s"))
+//!     .controller(EventMatcher::packet_dropped(PacketMatcher::fragment().session_var("s")))
+//!     .verify();
+//! ```
+
+pub mod matchers;
+pub mod model;
+pub mod pdr;
+pub mod scenario;
+pub mod trace;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use wg_2024::config::Config;
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::drone::Drone;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType};
+use crate::simulation::Endpoint;
+use crate::BetterCallDrone;
+use matchers::Expectations;
+
+/// How long the assertions wait for a packet or an event.
+pub const TIMEOUT: Duration = Duration::from_millis(400);
+
+/// A drone under test, its mock neighbors and its mock controller.
+///
+/// The drone is crashed when the fixture is dropped.
+pub struct DroneFixture {
+    id: NodeId,
+    commands: Sender<DroneCommand>,
+    events: Receiver<DroneEvent>,
+    packets: Sender<Packet>,
+    neighbors: HashMap<NodeId, Receiver<Packet>>,
+    stopped: Receiver<()>,
+}
+
+impl DroneFixture {
+    /// Runs a `BetterCallDrone` in its own thread.
+    ///
+    /// # Parameters
+    /// - `id`: The ID of the drone.
+    /// - `neighbors`: The IDs of the mock neighbors.
+    /// - `pdr`: The Packet Drop Rate of the drone.
+    #[must_use]
+    pub fn spawn(id: NodeId, neighbors: &[NodeId], pdr: f32) -> Self {
+        Self::spawn_with::<BetterCallDrone>(id, neighbors, pdr)
+    }
+
+    /// Runs a drone of type `D` in its own thread.
+    #[must_use]
+    pub fn spawn_with<D: Drone + Send + 'static>(id: NodeId, neighbors: &[NodeId], pdr: f32) -> Self {
+        let (mut fixture, drone) = Self::build::<D>(id, neighbors, pdr);
+        fixture.start(drone);
+        fixture
+    }
+
+    /// Creates a drone of type `D` without running it, so packets and commands can be queued
+    /// before it starts.
+    ///
+    /// # Returns
+    /// The fixture and the drone, to give to [`DroneFixture::start`].
+    #[must_use]
+    pub fn build<D: Drone + Send + 'static>(id: NodeId, neighbors: &[NodeId], pdr: f32) -> (Self, D) {
+        let (commands, command_recv) = unbounded();
+        let (event_send, events) = unbounded();
+        let (packets, packet_recv) = unbounded();
+        let mut senders = HashMap::new();
+        let mut receivers = HashMap::new();
+        for &neighbor in neighbors {
+            let (send, recv) = unbounded();
+            senders.insert(neighbor, send);
+            receivers.insert(neighbor, recv);
+        }
+        let drone = D::new(id, event_send, command_recv, packet_recv, senders, pdr);
+        let (_, stopped) = unbounded();
+        (Self { id, commands, events, packets, neighbors: receivers, stopped }, drone)
+    }
+
+    /// Runs a drone created by [`DroneFixture::build`] in its own thread.
+    pub fn start<D: Drone + Send + 'static>(&mut self, mut drone: D) {
+        let (done, stopped) = unbounded();
+        self.stopped = stopped;
+        thread::spawn(move || {
+            drone.run();
+            let _ = done.send(());
+        });
+    }
+
+    /// Returns the ID of the drone.
+    #[must_use]
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Creates a new mock neighbor, without telling the drone.
+    ///
+    /// # Returns
+    /// The sender to give to the drone with `DroneCommand::AddSender`.
+    pub fn add_neighbor(&mut self, neighbor: NodeId) -> Sender<Packet> {
+        let (send, recv) = unbounded();
+        self.neighbors.insert(neighbor, recv);
+        send
+    }
+
+    /// Sends a packet to the drone, as if a neighbor had sent it.
+    ///
+    /// # Panics
+    /// Panics if the drone has stopped and dropped its channel.
+    pub fn send(&self, packet: &Packet) {
+        self.packets.send(packet.clone()).expect("the drone is running");
+    }
+
+    /// Sends a command to the drone.
+    ///
+    /// # Panics
+    /// Panics if the drone has stopped and dropped its channel.
+    pub fn command(&self, command: DroneCommand) {
+        self.commands.send(command).expect("the drone is running");
+    }
+
+    /// Returns the next packet received by a mock neighbor.
+    ///
+    /// # Panics
+    /// Panics if `neighbor` is not a mock neighbor or receives nothing within [`TIMEOUT`].
+    #[must_use]
+    pub fn recv(&self, neighbor: NodeId) -> Packet {
+        self.neighbor(neighbor).recv_timeout(TIMEOUT)
+            .unwrap_or_else(|_| panic!("node {neighbor} did not receive a packet"))
+    }
+
+    /// Checks that the next packet received by a mock neighbor is `expected`.
+    ///
+    /// # Panics
+    /// Panics if the packet differs or does not arrive within [`TIMEOUT`].
+    pub fn assert_packet(&self, neighbor: NodeId, expected: &Packet) {
+        assert_eq!(&self.recv(neighbor), expected, "unexpected packet received by node {neighbor}");
+    }
+
+    /// Checks that a mock neighbor receives nothing within [`TIMEOUT`].
+    ///
+    /// # Panics
+    /// Panics if a packet arrives.
+    pub fn assert_no_packet(&self, neighbor: NodeId) {
+        if let Ok(packet) = self.neighbor(neighbor).recv_timeout(TIMEOUT) {
+            panic!("node {neighbor} received an unexpected packet: {packet:?}");
+        }
+    }
+
+    /// Returns the next event sent to the controller.
+    ///
+    /// # Panics
+    /// Panics if no event arrives within [`TIMEOUT`].
+    #[must_use]
+    pub fn recv_event(&self) -> DroneEvent {
+        self.events.recv_timeout(TIMEOUT).expect("the controller did not receive an event")
+    }
+
+    /// Checks that the next event sent to the controller is `expected`.
+    ///
+    /// # Panics
+    /// Panics if the event differs or does not arrive within [`TIMEOUT`].
+    pub fn assert_event(&self, expected: &DroneEvent) {
+        assert_eq!(&self.recv_event(), expected, "unexpected event received by the controller");
+    }
+
+    /// Waits for an event matching `accept`, skipping the other events.
+    ///
+    /// # Panics
+    /// Panics if no matching event arrives within [`TIMEOUT`].
+    pub fn wait_for_event(&self, accept: impl Fn(&DroneEvent) -> bool) -> DroneEvent {
+        while let Ok(event) = self.events.recv_timeout(TIMEOUT) {
+            if accept(&event) {
+                return event;
+            }
+        }
+        panic!("the controller did not receive the expected event");
+    }
+
+    /// Checks that the drone stops within [`TIMEOUT`].
+    ///
+    /// # Panics
+    /// Panics if the drone is still running.
+    pub fn assert_stopped(&self) {
+        assert!(self.stopped.recv_timeout(TIMEOUT).is_ok(), "the drone did not stop");
+    }
+
+    /// Starts a list of expectations on the packets of the neighbors and the events of the
+    /// controller, checked by [`Expectations::verify`].
+    #[must_use]
+    pub fn expect(&self) -> Expectations<'_> {
+        Expectations::new(self)
+    }
+
+    fn neighbor(&self, neighbor: NodeId) -> &Receiver<Packet> {
+        self.neighbors.get(&neighbor).unwrap_or_else(|| panic!("node {neighbor} is not a mock neighbor"))
+    }
+}
+
+impl Drop for DroneFixture {
+    /// Stops the drone, so it does not outlive the test.
+    fn drop(&mut self) {
+        let _ = self.commands.send(DroneCommand::Crash);
+    }
+}
+
+/// A `BetterCallDrone` called directly from the test thread, with the receivers of its neighbors
+/// and of its controller. A neighbor can have a closed channel, so that every send to it fails.
+pub struct DirectDrone {
+    /// The drone, to call its handlers on.
+    pub drone: BetterCallDrone,
+    commands: Sender<DroneCommand>,
+    packets: Sender<Packet>,
+    neighbors: Vec<(NodeId, Receiver<Packet>)>,
+    events: Receiver<DroneEvent>,
+}
+
+impl DirectDrone {
+    /// Creates a seeded drone.
+    ///
+    /// # Parameters
+    /// - `id`: The ID of the drone.
+    /// - `neighbors`: The neighbors, and whether their channel is open.
+    /// - `pdr`: The Packet Drop Rate of the drone.
+    /// - `seed`: The seed of the drops.
+    #[must_use]
+    pub fn new(id: NodeId, neighbors: &[(NodeId, bool)], pdr: f32, seed: u64) -> Self {
+        let (event_send, events) = unbounded();
+        let (commands, command_recv) = unbounded();
+        let (packets, packet_recv) = unbounded();
+        let mut senders = HashMap::new();
+        let mut receivers = Vec::new();
+        for &(node_id, live) in neighbors {
+            let (send, recv) = unbounded();
+            senders.insert(node_id, send);
+            if live {
+                receivers.push((node_id, recv));
+            }
+        }
+        let mut drone = BetterCallDrone::new(id, event_send, command_recv, packet_recv, senders, pdr);
+        drone.set_seed(seed);
+        Self { drone, commands, packets, neighbors: receivers, events }
+    }
+
+    /// Gives the drone `AddSender` with a new channel, kept open if `live`.
+    pub fn add_sender(&mut self, node_id: NodeId, live: bool) {
+        let (send, recv) = unbounded();
+        if live {
+            self.neighbors.push((node_id, recv));
+        }
+        self.drone.handle_command(DroneCommand::AddSender(node_id, send));
+    }
+
+    /// Queues a packet in the channel of the drone, where `Crash` finds it.
+    pub fn queue(&self, packet: Packet) {
+        let _ = self.packets.send(packet);
+    }
+
+    /// Queues a command in the channel of the drone, read by [`Drone::run`].
+    pub fn command(&self, command: DroneCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    /// Takes every packet received by a neighbor with an open channel, and every event.
+    #[must_use]
+    pub fn outputs(&self) -> (Vec<(NodeId, Packet)>, Vec<DroneEvent>) {
+        let mut packets = Vec::new();
+        for (node_id, receiver) in &self.neighbors {
+            packets.extend(receiver.try_iter().map(|packet| (*node_id, packet)));
+        }
+        (packets, self.events.try_iter().collect())
+    }
+}
+
+/// The drones of a configuration, each running in its own thread, with a mock channel for every
+/// client and server.
+///
+/// Unlike [`crate::simulation::Simulation`] the configuration is not validated, so a test can build
+/// networks the WG rules do not allow, e.g. a server behind a single drone.
+///
+/// The drones are crashed when the fixture is dropped.
+pub struct NetworkFixture {
+    config: Config,
+    commands: Vec<Sender<DroneCommand>>,
+    events: Receiver<DroneEvent>,
+    packets: HashMap<NodeId, Sender<Packet>>,
+    endpoints: HashMap<NodeId, Receiver<Packet>>,
+}
+
+impl NetworkFixture {
+    /// Runs a `BetterCallDrone` for every drone of the configuration, with its PDR.
+    ///
+    /// # Parameters
+    /// - `config`: The network, clients and servers get a mock channel.
+    #[must_use]
+    pub fn spawn(config: &Config) -> Self {
+        let mut packets = HashMap::new();
+        let mut receivers = HashMap::new();
+        let ids = config.drone.iter().map(|d| d.id)
+            .chain(config.client.iter().map(|c| c.id))
+            .chain(config.server.iter().map(|s| s.id));
+        for id in ids {
+            let (send, recv) = unbounded();
+            packets.insert(id, send);
+            receivers.insert(id, recv);
+        }
+
+        let (event_send, events) = unbounded();
+        let mut commands = Vec::new();
+        for d in &config.drone {
+            let (command_send, command_recv) = unbounded();
+            let neighbors = d.connected_node_ids.iter().map(|id| (*id, packets[id].clone())).collect();
+            let packet_recv = receivers.remove(&d.id).expect("every node has a channel");
+            let mut drone = BetterCallDrone::new(d.id, event_send.clone(), command_recv, packet_recv, neighbors, d.pdr);
+            thread::spawn(move || drone.run());
+            commands.push(command_send);
+        }
+        Self { config: config.clone(), commands, events, packets, endpoints: receivers }
+    }
+
+    /// Sends a packet to a node, as if a neighbor had sent it.
+    ///
+    /// # Panics
+    /// Panics if the node is not in the configuration.
+    pub fn send(&self, node_id: NodeId, packet: &Packet) {
+        let sender = self.packets.get(&node_id).unwrap_or_else(|| panic!("node {node_id} does not exist"));
+        let _ = sender.send(packet.clone());
+    }
+
+    /// Returns the receiver of the packets sent to a client or server.
+    ///
+    /// # Panics
+    /// Panics if the node is not a client or server, or its channels were taken.
+    #[must_use]
+    pub fn endpoint(&self, node_id: NodeId) -> &Receiver<Packet> {
+        self.endpoints.get(&node_id).unwrap_or_else(|| panic!("node {node_id} is not a mock client or server"))
+    }
+
+    /// Takes the channels of a client or server, to run a real node on them.
+    ///
+    /// # Panics
+    /// Panics if the node is not a client or server, or its channels were taken.
+    pub fn take_endpoint(&mut self, node_id: NodeId) -> Endpoint {
+        let neighbors = self.config.client.iter().find(|c| c.id == node_id).map(|c| &c.connected_drone_ids)
+            .or_else(|| self.config.server.iter().find(|s| s.id == node_id).map(|s| &s.connected_drone_ids))
+            .unwrap_or_else(|| panic!("node {node_id} is not a client or server"));
+        let packet_send = neighbors.iter().map(|id| (*id, self.packets[id].clone())).collect();
+        let packet_recv = self.endpoints.remove(&node_id)
+            .unwrap_or_else(|| panic!("the channels of node {node_id} were already taken"));
+        Endpoint { packet_recv, packet_send }
+    }
+
+    /// Returns the receiver of the events sent by the drones.
+    #[must_use]
+    pub fn events(&self) -> &Receiver<DroneEvent> {
+        &self.events
+    }
+}
+
+impl Drop for NetworkFixture {
+    /// Stops the drones, so they do not outlive the test.
+    fn drop(&mut self) {
+        for commands in &self.commands {
+            let _ = commands.send(DroneCommand::Crash);
+        }
+    }
+}
+
+/// Returns a routing header.
+#[must_use]
+pub fn route(hop_index: usize, hops: &[NodeId]) -> SourceRoutingHeader {
+    SourceRoutingHeader { hop_index, hops: hops.to_vec() }
+}
+
+/// Returns a full fragment with index 1 of session 1.
+#[must_use]
+pub fn fragment(hop_index: usize, hops: &[NodeId]) -> Packet {
+    Packet::new_fragment(route(hop_index, hops), 1, Fragment {
+        fragment_index: 1,
+        total_n_fragments: 1,
+        length: 128,
+        data: [1; 128],
+    })
+}
+
+/// Returns the `Ack` of fragment 1 of session 1.
+#[must_use]
+pub fn ack(hop_index: usize, hops: &[NodeId]) -> Packet {
+    Packet {
+        pack_type: PacketType::Ack(Ack { fragment_index: 1 }),
+        routing_header: route(hop_index, hops),
+        session_id: 1,
+    }
+}
+
+/// Returns a `Nack` of fragment 1 of session 1.
+#[must_use]
+pub fn nack(hop_index: usize, hops: &[NodeId], nack_type: NackType) -> Packet {
+    Packet::new_nack(route(hop_index, hops), 1, Nack { fragment_index: 1, nack_type })
+}
+
+/// Returns a `FloodRequest` of session 1, started by the first node of `path_trace`.
+#[must_use]
+pub fn flood_request(flood_id: u64, path_trace: &[(NodeId, NodeType)]) -> Packet {
+    let request = FloodRequest { flood_id, initiator_id: path_trace[0].0, path_trace: path_trace.to_vec() };
+    Packet::new_flood_request(SourceRoutingHeader::empty_route(), 1, request)
+}
+
+/// Returns a `FloodResponse` of session 1, routed back along `path_trace` and leaving its first hop.
+#[must_use]
+pub fn flood_response(flood_id: u64, path_trace: &[(NodeId, NodeType)]) -> Packet {
+    let hops: Vec<NodeId> = path_trace.iter().rev().map(|(id, _)| *id).collect();
+    Packet::new_flood_response(route(1, &hops), 1, FloodResponse { flood_id, path_trace: path_trace.to_vec() })
+}
+
+/// Returns `packet` as the next hop receives it, with `hop_index` incremented.
+#[must_use]
+pub fn forwarded(packet: &Packet) -> Packet {
+    let mut packet = packet.clone();
+    packet.routing_header.hop_index += 1;
+    packet
+}
@@ -0,0 +1,333 @@
+//! A reference model of the WG drone protocol, and a harness checking a `BetterCallDrone`
+//! against it.
+//!
+//! [`step`] is a pure function from a [`ModelDrone`] and an [`Input`] to the next state and the
+//! [`Output`]s of the drone. The randomness of the drops is an argument: the uniform draw in
+//! `[0, 1)` the drone takes for each fragment it receives. A packet is checked in the order of
+//! the protocol:
+//!
+//! 1. `hops[hop_index]` must be the drone, else `UnexpectedRecipient`;
+//! 2. there must be a next hop, else `DestinationIsDrone`;
+//! 3. the next hop must be a neighbor, else `ErrorInRouting`;
+//! 4. a fragment is dropped if the draw is below the PDR, with `Dropped`;
+//! 5. the packet is sent to the next hop, and if the send fails, `ErrorInRouting`.
+//!
+//! An error on an ack, a nack or a flood response gives the packet to the controller, with its
+//! `hop_index` moved to the unreachable hop after `ErrorInRouting`.
+//! Otherwise a nack is sent back along the hops before the drone, or to the controller if the
+//! previous hop cannot be reached.
+//!
+//! [`Differential`] feeds the same inputs to a seeded `BetterCallDrone` and to the model, with
+//! the draws of the drone, and reports the first input where their outputs differ.
+
+use std::collections::{BTreeMap, BTreeSet};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use wg_2024::controller::{DroneCommand, DroneEvent};
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{FloodRequest, FloodResponse, Nack, NackType, NodeType, Packet, PacketType};
+use super::DirectDrone;
+
+/// The state of a drone in the model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelDrone {
+    /// The ID of the drone.
+    pub id: NodeId,
+    /// The Packet Drop Rate.
+    pub pdr: f32,
+    /// The neighbors, and whether their channel is open.
+    pub neighbors: BTreeMap<NodeId, bool>,
+    /// The `(flood_id, initiator_id)` of the flood requests already seen.
+    pub floods: BTreeSet<(u64, NodeId)>,
+    /// Whether the drone has crashed; a crashed drone ignores every input.
+    pub crashed: bool,
+}
+
+/// An input of the drone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    /// A packet received from a neighbor.
+    Packet(Packet),
+    /// `AddSender`, with an open or a closed channel.
+    AddSender(NodeId, bool),
+    /// `RemoveSender`.
+    RemoveSender(NodeId),
+    /// `SetPacketDropRate`.
+    SetPdr(f32),
+    /// `Crash`, with the packets still queued for the drone.
+    Crash(Vec<Packet>),
+}
+
+/// An output of the drone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    /// A packet received by a neighbor.
+    Packet(NodeId, Packet),
+    /// An event received by the controller.
+    Event(DroneEvent),
+}
+
+impl ModelDrone {
+    /// Creates the state of a drone that has seen no flood request.
+    ///
+    /// # Parameters
+    /// - `id`: The ID of the drone.
+    /// - `neighbors`: The neighbors, and whether their channel is open.
+    /// - `pdr`: The Packet Drop Rate.
+    #[must_use]
+    pub fn new(id: NodeId, neighbors: &[(NodeId, bool)], pdr: f32) -> Self {
+        Self { id, pdr, neighbors: neighbors.iter().copied().collect(), floods: BTreeSet::new(), crashed: false }
+    }
+
+    fn is_live(&self, node_id: NodeId) -> bool {
+        self.neighbors.get(&node_id) == Some(&true)
+    }
+}
+
+/// Returns whether an input draws a drop: every fragment received does, even if it is not
+/// dropped.
+#[must_use]
+pub fn draws(input: &Input) -> bool {
+    matches!(input, Input::Packet(Packet { pack_type: PacketType::MsgFragment(_), .. }))
+}
+
+/// Applies an input to a drone.
+///
+/// # Parameters
+/// - `state`: The drone before the input.
+/// - `input`: The input.
+/// - `draw`: The uniform draw in `[0, 1)` deciding the drop of a fragment, see [`draws`].
+///
+/// # Returns
+/// The drone after the input, and its outputs in the order they are produced.
+#[must_use]
+pub fn step(state: &ModelDrone, input: &Input, draw: f32) -> (ModelDrone, Vec<Output>) {
+    let mut next = state.clone();
+    let mut outputs = Vec::new();
+    if state.crashed {
+        return (next, outputs);
+    }
+    match input {
+        Input::Packet(packet) => match &packet.pack_type {
+            PacketType::FloodRequest(request) => flood(&mut next, request, packet.session_id, &mut outputs),
+            PacketType::MsgFragment(_) => route(&next, packet, Some(draw), &mut outputs),
+            _ => route(&next, packet, None, &mut outputs),
+        },
+        Input::AddSender(node_id, live) => {
+            next.neighbors.entry(*node_id).or_insert(*live);
+        }
+        Input::RemoveSender(node_id) => {
+            next.neighbors.remove(node_id);
+        }
+        Input::SetPdr(pdr) => {
+            if (0.0..=1.0).contains(pdr) {
+                next.pdr = *pdr;
+            }
+        }
+        Input::Crash(queued) => {
+            for packet in queued {
+                match &packet.pack_type {
+                    PacketType::MsgFragment(_) => error(&next, packet, NackType::ErrorInRouting(next.id), &mut outputs),
+                    PacketType::FloodRequest(_) => {}
+                    _ => route(&next, packet, None, &mut outputs),
+                }
+            }
+            next.crashed = true;
+        }
+    }
+    (next, outputs)
+}
+
+/// Sends a packet to its next hop. `draw` is the drop draw of a fragment.
+fn route(state: &ModelDrone, packet: &Packet, draw: Option<f32>, outputs: &mut Vec<Output>) {
+    let header = &packet.routing_header;
+    if header.hops.get(header.hop_index) != Some(&state.id) {
+        return error(state, packet, NackType::UnexpectedRecipient(state.id), outputs);
+    }
+    let Some(&next_hop) = header.hops.get(header.hop_index + 1) else {
+        return error(state, packet, NackType::DestinationIsDrone, outputs);
+    };
+    if !state.neighbors.contains_key(&next_hop) {
+        return error(state, packet, NackType::ErrorInRouting(next_hop), outputs);
+    }
+    if draw.is_some_and(|draw| draw < state.pdr) {
+        error(state, packet, NackType::Dropped, outputs);
+        outputs.push(Output::Event(DroneEvent::PacketDropped(packet.clone())));
+        return;
+    }
+    if !state.is_live(next_hop) {
+        return error(state, packet, NackType::ErrorInRouting(next_hop), outputs);
+    }
+    let mut forwarded = packet.clone();
+    forwarded.routing_header.hop_index += 1;
+    outputs.push(Output::Packet(next_hop, forwarded.clone()));
+    outputs.push(Output::Event(DroneEvent::PacketSent(forwarded)));
+}
+
+/// Reports a packet that cannot be sent to its next hop.
+fn error(state: &ModelDrone, packet: &Packet, nack_type: NackType, outputs: &mut Vec<Output>) {
+    let fragment_index = match &packet.pack_type {
+        PacketType::MsgFragment(fragment) => fragment.fragment_index,
+        PacketType::FloodRequest(_) => 0,
+        PacketType::Ack(_) | PacketType::Nack(_) | PacketType::FloodResponse(_) => {
+            let mut packet = packet.clone();
+            if let NackType::ErrorInRouting(_) = nack_type {
+                packet.routing_header.hop_index += 1;
+            }
+            outputs.push(Output::Event(DroneEvent::ControllerShortcut(packet)));
+            return;
+        }
+    };
+    let header = &packet.routing_header;
+    let previous = &header.hops[..header.hop_index.min(header.hops.len())];
+    let mut hops = vec![state.id];
+    hops.extend(previous.iter().rev());
+    let nack = Packet {
+        pack_type: PacketType::Nack(Nack { fragment_index, nack_type }),
+        routing_header: SourceRoutingHeader { hop_index: 1, hops },
+        session_id: packet.session_id,
+    };
+    match nack.routing_header.hops.get(1) {
+        Some(&previous_hop) if state.is_live(previous_hop) => outputs.push(Output::Packet(previous_hop, nack)),
+        _ => outputs.push(Output::Event(DroneEvent::ControllerShortcut(nack))),
+    }
+}
+
+/// Forwards a flood request to every neighbor but the one it came from, or answers it if it
+/// was already seen or there is no other neighbor.
+fn flood(state: &mut ModelDrone, request: &FloodRequest, session_id: u64, outputs: &mut Vec<Output>) {
+    let previous = request.path_trace.last().map(|(id, _)| *id);
+    let mut request = request.clone();
+    request.path_trace.push((state.id, NodeType::Drone));
+    let targets: Vec<NodeId> = state.neighbors.keys().copied().filter(|&id| Some(id) != previous).collect();
+    if !state.floods.insert((request.flood_id, request.initiator_id)) || targets.is_empty() {
+        let response = Packet {
+            pack_type: PacketType::FloodResponse(FloodResponse { flood_id: request.flood_id, path_trace: request.path_trace.clone() }),
+            routing_header: SourceRoutingHeader { hop_index: 0, hops: request.path_trace.iter().rev().map(|(id, _)| *id).collect() },
+            session_id,
+        };
+        return route(state, &response, None, outputs);
+    }
+    for target in targets {
+        if state.is_live(target) {
+            let packet = Packet::new_flood_request(SourceRoutingHeader::empty_route(), session_id, request.clone());
+            outputs.push(Output::Packet(target, packet));
+        }
+    }
+}
+
+/// The outputs of an input, seen the way they can be observed: the packets received by each
+/// neighbor in order, and the events in order.
+type Observed = (BTreeMap<NodeId, Vec<Packet>>, Vec<DroneEvent>);
+
+fn observe(outputs: Vec<Output>) -> Observed {
+    let mut packets: BTreeMap<NodeId, Vec<Packet>> = BTreeMap::new();
+    let mut events = Vec::new();
+    for output in outputs {
+        match output {
+            Output::Packet(node_id, packet) => packets.entry(node_id).or_default().push(packet),
+            Output::Event(event) => events.push(event),
+        }
+    }
+    (packets, events)
+}
+
+/// The first input where the drone and the model disagree.
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    /// The index of the input.
+    pub index: usize,
+    /// The input.
+    pub input: Input,
+    /// The draw given to the model.
+    pub draw: f32,
+    /// The outputs of the model.
+    pub expected: Vec<Output>,
+    /// The outputs of the drone.
+    pub actual: Vec<Output>,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "input {} differs from the model: {:?} (draw {})", self.index, self.input, self.draw)?;
+        for output in &self.expected {
+            write!(f, "\n  - {output:?}")?;
+        }
+        for output in &self.actual {
+            write!(f, "\n  + {output:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A `BetterCallDrone` called directly, next to the model of the same drone.
+pub struct Differential {
+    direct: DirectDrone,
+    model: ModelDrone,
+    rng: StdRng,
+    index: usize,
+}
+
+impl Differential {
+    /// Creates a drone and its model.
+    ///
+    /// # Parameters
+    /// - `id`: The ID of the drone.
+    /// - `neighbors`: The neighbors, and whether their channel is open.
+    /// - `pdr`: The Packet Drop Rate.
+    /// - `seed`: The seed of the drops of the drone, also used to compute the draws of the model.
+    #[must_use]
+    pub fn new(id: NodeId, neighbors: &[(NodeId, bool)], pdr: f32, seed: u64) -> Self {
+        Self {
+            direct: DirectDrone::new(id, neighbors, pdr, seed),
+            model: ModelDrone::new(id, neighbors, pdr),
+            rng: StdRng::seed_from_u64(seed),
+            index: 0,
+        }
+    }
+
+    /// Returns the state of the model.
+    #[must_use]
+    pub fn model(&self) -> &ModelDrone {
+        &self.model
+    }
+
+    /// Gives an input to the drone and to the model and compares their outputs. After `Crash`
+    /// the drone is no longer called.
+    ///
+    /// # Errors
+    /// Returns a `Mismatch` if a neighbor or the controller receives something else from the
+    /// drone than from the model.
+    pub fn apply(&mut self, input: Input) -> Result<(), Box<Mismatch>> {
+        let draw = if draws(&input) && !self.model.crashed { self.rng.gen::<f32>() } else { 0.0 };
+        let (model, expected) = step(&self.model, &input, draw);
+
+        if !self.model.crashed {
+            let drone = &mut self.direct.drone;
+            match input.clone() {
+                Input::Packet(packet) => drone.handle_packet(packet),
+                Input::AddSender(node_id, live) => self.direct.add_sender(node_id, live),
+                Input::RemoveSender(node_id) => drone.handle_command(DroneCommand::RemoveSender(node_id)),
+                Input::SetPdr(pdr) => drone.handle_command(DroneCommand::SetPacketDropRate(pdr)),
+                Input::Crash(queued) => {
+                    for packet in queued {
+                        self.direct.queue(packet);
+                    }
+                    self.direct.drone.handle_command(DroneCommand::Crash);
+                }
+            }
+        }
+        let (packets, events) = self.direct.outputs();
+        let mut actual: Vec<Output> = packets.into_iter().map(|(node_id, packet)| Output::Packet(node_id, packet)).collect();
+        actual.extend(events.into_iter().map(Output::Event));
+
+        let index = self.index;
+        self.index += 1;
+        self.model = model;
+        if observe(expected.clone()) == observe(actual.clone()) {
+            Ok(())
+        } else {
+            Err(Box::new(Mismatch { index, input, draw, expected, actual }))
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Statistical checks of the Packet Drop Rate: [`DropSample::measure`] pushes fragments through
+//! `handle_fragment` of a seeded drone and counts the drops, and [`DropSample::z_score`] tells
+//! how far the observed drop fraction is from the configured PDR.
+//!
+//! ```ignore
+//! let sample = DropSample::measure(0.3, 20_000, 7);
+//! assert!(sample.is_consistent(4.0), "{sample}");
+//! ```
+
+use wg_2024::controller::DroneEvent;
+use wg_2024::network::SourceRoutingHeader;
+use wg_2024::packet::{Fragment, NackType, Packet, PacketType};
+use super::DirectDrone;
+
+/// Fragments handled between two drains of the channels of the drone.
+const BATCH: u64 = 1024;
+
+/// The drops of a drone over a number of fragments.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DropSample {
+    /// The configured PDR.
+    pub pdr: f32,
+    /// The fragments handled.
+    pub fragments: u64,
+    /// The fragments dropped.
+    pub dropped: u64,
+}
+
+impl DropSample {
+    /// Sends fragments through `handle_fragment` of a drone with ID 11 on the route
+    /// `[1, 11, 12]`, and counts the `PacketDropped` events.
+    ///
+    /// # Parameters
+    /// - `pdr`: The PDR of the drone.
+    /// - `fragments`: The number of fragments.
+    /// - `seed`: The seed of the drops.
+    ///
+    /// # Panics
+    /// Panics if a fragment gives anything but a forward or a `Dropped` nack with its
+    /// `PacketDropped` event.
+    #[must_use]
+    pub fn measure(pdr: f32, fragments: u64, seed: u64) -> Self {
+        let mut direct = DirectDrone::new(11, &[(1, true), (12, true)], pdr, seed);
+        let header = SourceRoutingHeader { hop_index: 1, hops: vec![1, 11, 12] };
+        let (mut dropped, mut forwarded, mut nacks) = (0, 0, 0);
+        let mut handled = 0;
+        while handled < fragments {
+            let batch = BATCH.min(fragments - handled);
+            for fragment_index in handled..handled + batch {
+                let fragment = Fragment { fragment_index, total_n_fragments: fragments, length: 0, data: [0; 128] };
+                direct.drone.handle_fragment(Packet::new_fragment(header.clone(), 0, fragment));
+            }
+            handled += batch;
+
+            let (packets, events) = direct.outputs();
+            for event in events {
+                match event {
+                    DroneEvent::PacketDropped(_) => dropped += 1,
+                    DroneEvent::PacketSent(_) => {}
+                    DroneEvent::ControllerShortcut(packet) => panic!("unexpected shortcut: {packet:?}"),
+                }
+            }
+            for (node_id, packet) in packets {
+                match packet.pack_type {
+                    PacketType::MsgFragment(_) if node_id == 12 => forwarded += 1,
+                    PacketType::Nack(nack) if node_id == 1 && nack.nack_type == NackType::Dropped => nacks += 1,
+                    other => panic!("unexpected packet to node {node_id}: {other:?}"),
+                }
+            }
+        }
+        assert_eq!(nacks, dropped, "every drop sends one Dropped nack");
+        assert_eq!(forwarded + dropped, fragments, "every fragment is forwarded or dropped");
+        Self { pdr, fragments, dropped }
+    }
+
+    /// Returns the fraction of the fragments that were dropped.
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        if self.fragments == 0 {
+            return 0.0;
+        }
+        self.dropped as f64 / self.fragments as f64
+    }
+
+    /// Returns how many standard deviations the drops are from the number expected with the
+    /// configured PDR, using the normal approximation of the binomial distribution.
+    ///
+    /// # Returns
+    /// The absolute z-score. With a PDR of 0 or 1 the expected number is exact, so any
+    /// difference gives infinity.
+    #[must_use]
+    pub fn z_score(&self) -> f64 {
+        let p = f64::from(self.pdr).clamp(0.0, 1.0);
+        let n = self.fragments as f64;
+        let expected = n * p;
+        let variance = n * p * (1.0 - p);
+        let difference = (self.dropped as f64 - expected).abs();
+        if variance == 0.0 {
+            return if difference == 0.0 { 0.0 } else { f64::INFINITY };
+        }
+        difference / variance.sqrt()
+    }
+
+    /// Checks the drops against the configured PDR.
+    ///
+    /// # Parameters
+    /// - `max_z`: The largest accepted z-score, e.g. 4.0 for a false failure about once in
+    ///   16000 runs.
+    ///
+    /// # Returns
+    /// `true` if the z-score is at most `max_z`.
+    #[must_use]
+    pub fn is_consistent(&self, max_z: f64) -> bool {
+        self.z_score() <= max_z
+    }
+}
+
+impl std::fmt::Display for DropSample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PDR {}: {} of {} fragments dropped ({:.4}, z = {:.2})",
+            self.pdr, self.dropped, self.fragments, self.fraction(), self.z_score())
+    }
+}
@@ -0,0 +1,568 @@
+//! Declarative drone tests: a TOML file describes a network, a timeline of injected packets and
+//! commands, and what the clients, servers and controller must receive after each step.
+//!
+//! ```toml
+//! name = "fragment dropped by the second drone"
+//!
+//! [[topology.drone]]
+//! id = 11
+//! connected_node_ids = [1, 12]
+//! pdr = 0.0
+//! # ... the other drones, clients and servers, as in a WG configuration file
+//!
+//! [[step]]
+//! inject = { node = 11, packet = { type = "fragment", hops = [1, 11, 12, 21] } }
+//! expect = [
+//!     { node = 1, packet = { type = "nack", nack = "Dropped", hops = [12, 11, 1] } },
+//!     { event = "PacketDropped", packet = { type = "fragment" } },
+//!     { node = 21, absent = true },
+//! ]
+//! ```
+//!
+//! Steps run one after the other. After its action, a step waits for the network to go quiet and
+//! checks its expectations against everything received in the meantime, in any order. Only the
+//! packets reaching clients and servers can be observed: they are not spawned, see [`Simulation`].
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+use serde::Deserialize;
+use wg_2024::config::Config;
+use wg_2024::controller::DroneEvent;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType};
+use crate::repl::{describe_event, describe_packet};
+use crate::simulation::{Simulation, SimulationError};
+use super::matchers::{Bindings, EventMatcher, PacketMatcher};
+
+/// How long the network must stay quiet before the expectations of a step are checked.
+const SETTLE: Duration = Duration::from_millis(100);
+/// The longest a step waits for the network to go quiet.
+const MAX_STEP: Duration = Duration::from_secs(5);
+
+/// A scenario read from a TOML file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// Printed in reports.
+    pub name: String,
+    /// Seeds the drops of the drones when the scenario is traced, see [`super::trace`].
+    #[serde(default)]
+    pub seed: u64,
+    /// The network, in the format of a WG configuration file.
+    pub topology: Config,
+    /// The timeline.
+    #[serde(rename = "step", default)]
+    pub steps: Vec<Step>,
+}
+
+/// One action and what must be received after it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// Printed in reports instead of the step number.
+    pub description: Option<String>,
+    /// A packet put into the channel of a node, as if a neighbor had sent it.
+    pub inject: Option<Injection>,
+    /// A command given to the simulation controller.
+    pub command: Option<CommandSpec>,
+    /// What must, or must not, be received after the action.
+    #[serde(default)]
+    pub expect: Vec<Expectation>,
+}
+
+/// A packet to inject, see [`Step::inject`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Injection {
+    /// The node receiving the packet.
+    pub node: NodeId,
+    /// The packet.
+    pub packet: PacketSpec,
+}
+
+/// A command of the simulation controller. Commands breaking the WG rules fail the step.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum CommandSpec {
+    /// Crashes a drone.
+    Crash { drone: NodeId },
+    /// Changes the PDR of a drone.
+    SetPdr { drone: NodeId, pdr: f32 },
+    /// Connects two nodes.
+    AddLink { nodes: [NodeId; 2] },
+    /// Disconnects two nodes.
+    RemoveLink { nodes: [NodeId; 2] },
+}
+
+/// A packet, or a pattern on packets.
+///
+/// As a pattern every field is optional and only the given ones are checked. As an injected
+/// packet `type` is required and so are `hops` for fragments, acks and nacks, `nack` for nacks
+/// and `flood_id` and `path_trace` for floods; the other fields have defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PacketSpec {
+    /// The packet type.
+    #[serde(rename = "type")]
+    pub kind: Option<PacketKind>,
+    /// The session ID, 0 by default.
+    pub session: Option<u64>,
+    /// The fragment index of a fragment, ack or nack, 0 by default.
+    pub fragment_index: Option<u64>,
+    /// The number of fragments of a fragment, 1 by default.
+    pub total_n_fragments: Option<u64>,
+    /// The content of a fragment, empty by default.
+    pub data: Option<String>,
+    /// The type of a nack.
+    pub nack: Option<NackSpec>,
+    /// The route.
+    pub hops: Option<Vec<NodeId>>,
+    /// The hop index, 1 by default: the packet was sent by the previous hop.
+    pub hop_index: Option<usize>,
+    /// The flood ID of a flood request or response.
+    pub flood_id: Option<u64>,
+    /// The initiator of a flood request, the first node of the path trace by default.
+    pub initiator: Option<NodeId>,
+    /// The path trace of a flood request or response.
+    pub path_trace: Option<Vec<(NodeId, NodeKind)>>,
+}
+
+/// The type of a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PacketKind {
+    Fragment,
+    Ack,
+    Nack,
+    FloodRequest,
+    FloodResponse,
+}
+
+/// A `NackType`: `"Dropped"`, `"DestinationIsDrone"`, `{ ErrorInRouting = 12 }` or
+/// `{ UnexpectedRecipient = 12 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum NackSpec {
+    ErrorInRouting(NodeId),
+    DestinationIsDrone,
+    Dropped,
+    UnexpectedRecipient(NodeId),
+}
+
+impl From<NackSpec> for NackType {
+    fn from(nack: NackSpec) -> Self {
+        match nack {
+            NackSpec::ErrorInRouting(id) => NackType::ErrorInRouting(id),
+            NackSpec::DestinationIsDrone => NackType::DestinationIsDrone,
+            NackSpec::Dropped => NackType::Dropped,
+            NackSpec::UnexpectedRecipient(id) => NackType::UnexpectedRecipient(id),
+        }
+    }
+}
+
+/// A `NodeType` in a path trace: `"Client"`, `"Drone"` or `"Server"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum NodeKind {
+    Client,
+    Drone,
+    Server,
+}
+
+impl From<NodeKind> for NodeType {
+    fn from(kind: NodeKind) -> Self {
+        match kind {
+            NodeKind::Client => NodeType::Client,
+            NodeKind::Drone => NodeType::Drone,
+            NodeKind::Server => NodeType::Server,
+        }
+    }
+}
+
+/// The type of a `DroneEvent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum EventKind {
+    PacketSent,
+    PacketDropped,
+    ControllerShortcut,
+}
+
+/// A packet received by a client or server, or an event received by the controller.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expectation {
+    /// The client or server receiving the packet.
+    pub node: Option<NodeId>,
+    /// The type of the event received by the controller.
+    pub event: Option<EventKind>,
+    /// The packet, or the packet of the event. Any packet matches if it is missing.
+    #[serde(default)]
+    pub packet: PacketSpec,
+    /// Requires that nothing matching is received instead.
+    #[serde(default)]
+    pub absent: bool,
+}
+
+/// Errors returned when loading a scenario.
+#[derive(Debug)]
+pub enum ScenarioError {
+    /// The file cannot be read.
+    Io(std::io::Error),
+    /// The file is not a valid scenario.
+    Parse(toml::de::Error),
+    /// A step cannot be run.
+    InvalidStep { step: usize, reason: String },
+}
+
+impl std::fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScenarioError::Io(e) => write!(f, "cannot read the scenario: {e}"),
+            ScenarioError::Parse(e) => write!(f, "invalid scenario: {e}"),
+            ScenarioError::InvalidStep { step, reason } => write!(f, "step {step}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl Scenario {
+    /// Parses a scenario and checks that every step can be run.
+    ///
+    /// # Errors
+    /// Returns a `ScenarioError` if the content is not a valid scenario.
+    pub fn parse(content: &str) -> Result<Self, ScenarioError> {
+        let scenario: Scenario = toml::from_str(content).map_err(ScenarioError::Parse)?;
+        for (index, step) in scenario.steps.iter().enumerate() {
+            step.check().map_err(|reason| ScenarioError::InvalidStep { step: index + 1, reason })?;
+        }
+        Ok(scenario)
+    }
+
+    /// Reads and parses a scenario file.
+    ///
+    /// # Errors
+    /// Returns a `ScenarioError` if the file cannot be read or is not a valid scenario.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        let content = std::fs::read_to_string(path).map_err(ScenarioError::Io)?;
+        Self::parse(&content)
+    }
+
+    /// Runs the scenario against a network of `BetterCallDrone`s.
+    ///
+    /// Every step is run even if an earlier one failed.
+    #[must_use]
+    pub fn run(&self) -> ScenarioReport {
+        let mut report = ScenarioReport { name: self.name.clone(), steps: self.steps.len(), failures: Vec::new() };
+        let mut simulation = match Simulation::spawn(self.topology.clone()) {
+            Ok(simulation) => simulation,
+            Err(e) => {
+                report.failures.push(StepFailure { step: 0, description: "topology".to_string(), lines: vec![e.to_string()] });
+                return report;
+            }
+        };
+        for (index, step) in self.steps.iter().enumerate() {
+            let lines = step.run(&mut simulation);
+            if !lines.is_empty() {
+                let description = step.description.clone().unwrap_or_else(|| format!("step {}", index + 1));
+                report.failures.push(StepFailure { step: index + 1, description, lines });
+            }
+        }
+        simulation.shutdown();
+        report
+    }
+}
+
+impl Step {
+    /// Checks that the step has at most one action, that its packet can be built and that
+    /// every expectation reads from one channel.
+    fn check(&self) -> Result<(), String> {
+        if self.inject.is_some() && self.command.is_some() {
+            return Err("a step has either `inject` or `command`, not both".to_string());
+        }
+        if let Some(injection) = &self.inject {
+            injection.packet.to_packet()?;
+        }
+        for expectation in &self.expect {
+            if expectation.node.is_some() == expectation.event.is_some() {
+                return Err("an expectation has either `node` or `event`".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the action, waits for the network to go quiet and checks the expectations.
+    ///
+    /// # Returns
+    /// One line per problem, or nothing if the step passed.
+    fn run(&self, simulation: &mut Simulation) -> Vec<String> {
+        let action = match (&self.inject, &self.command) {
+            (Some(injection), _) => injection.packet.to_packet()
+                .and_then(|packet| simulation.inject(injection.node, packet).map_err(|e| e.to_string())),
+            (None, Some(command)) => command.apply(simulation).map_err(|e| e.to_string()),
+            (None, None) => Ok(()),
+        };
+        if let Err(e) = action {
+            return vec![format!("action failed: {e}")];
+        }
+
+        let (packets, events) = settle(simulation);
+        let mut packets: Vec<(NodeId, Packet, bool)> = packets.into_iter().map(|(id, p)| (id, p, false)).collect();
+        let mut events: Vec<(DroneEvent, bool)> = events.into_iter().map(|e| (e, false)).collect();
+        let mut lines = Vec::new();
+        for expectation in &self.expect {
+            let result = match (expectation.node, expectation.event) {
+                (Some(node), _) => {
+                    let matcher = expectation.packet.to_matcher();
+                    let received = packets.iter_mut().filter(|(id, _, _)| *id == node).map(|(_, p, used)| (&*p, used));
+                    check(&format!("node {node} receives {matcher}"), expectation.absent, received,
+                          |p| matcher.mismatches(p, &mut Bindings::new()), describe_packet)
+                }
+                (None, Some(kind)) => {
+                    let matcher = kind.matcher(expectation.packet.to_matcher());
+                    let received = events.iter_mut().map(|(e, used)| (&*e, used));
+                    check(&format!("controller receives {matcher}"), expectation.absent, received,
+                          |e| matcher.mismatches(e, &mut Bindings::new()), describe_event)
+                }
+                (None, None) => Ok(()),
+            };
+            if let Err(failure) = result {
+                lines.extend(failure);
+            }
+        }
+        lines
+    }
+}
+
+/// Checks one expectation against the messages of its channel, marking the one it matches.
+fn check<'a, T: 'a>(
+    description: &str,
+    absent: bool,
+    received: impl Iterator<Item = (&'a T, &'a mut bool)>,
+    mismatches: impl Fn(&T) -> Vec<String>,
+    describe: impl Fn(&T) -> String,
+) -> Result<(), Vec<String>> {
+    let mut closest: Option<(Vec<String>, String)> = None;
+    for (message, used) in received {
+        let found = mismatches(message);
+        if found.is_empty() {
+            if absent {
+                return Err(vec![format!("✗ nothing like: {description}"), format!("    received: {}", describe(message))]);
+            }
+            if !*used {
+                *used = true;
+                return Ok(());
+            }
+        } else if closest.as_ref().is_none_or(|(best, _)| found.len() < best.len()) {
+            closest = Some((found, describe(message)));
+        }
+    }
+    if absent {
+        return Ok(());
+    }
+    let mut lines = vec![format!("✗ {description}")];
+    match closest {
+        Some((found, message)) => {
+            lines.extend(found.into_iter().map(|mismatch| format!("      {mismatch}")));
+            lines.push(format!("    closest: {message}"));
+        }
+        None => lines.push("    received: nothing".to_string()),
+    }
+    Err(lines)
+}
+
+/// Collects the packets reaching clients and servers and the events of the controller until
+/// nothing has arrived for [`SETTLE`].
+fn settle(simulation: &Simulation) -> (Vec<(NodeId, Packet)>, Vec<DroneEvent>) {
+    let mut packets = Vec::new();
+    let mut events = Vec::new();
+    let start = Instant::now();
+    let mut last = Instant::now();
+    while last.elapsed() < SETTLE && start.elapsed() < MAX_STEP {
+        let before = packets.len() + events.len();
+        for (&id, receiver) in simulation.endpoint_packets() {
+            packets.extend(receiver.try_iter().map(|packet| (id, packet)));
+        }
+        events.extend(simulation.events().try_iter());
+        if packets.len() + events.len() > before {
+            last = Instant::now();
+        } else {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+    (packets, events)
+}
+
+impl CommandSpec {
+    fn apply(&self, simulation: &mut Simulation) -> Result<(), SimulationError> {
+        match *self {
+            CommandSpec::Crash { drone } => simulation.crash(drone),
+            CommandSpec::SetPdr { drone, pdr } => simulation.set_pdr(drone, pdr),
+            CommandSpec::AddLink { nodes: [a, b] } => simulation.add_link(a, b),
+            CommandSpec::RemoveLink { nodes: [a, b] } => simulation.remove_link(a, b),
+        }
+    }
+}
+
+impl EventKind {
+    fn matcher(self, packet: PacketMatcher) -> EventMatcher {
+        match self {
+            EventKind::PacketSent => EventMatcher::packet_sent(packet),
+            EventKind::PacketDropped => EventMatcher::packet_dropped(packet),
+            EventKind::ControllerShortcut => EventMatcher::controller_shortcut(packet),
+        }
+    }
+}
+
+impl PacketSpec {
+    /// Builds the packet to inject.
+    ///
+    /// # Errors
+    /// Returns which field is missing.
+    pub fn to_packet(&self) -> Result<Packet, String> {
+        let kind = self.kind.ok_or("an injected packet needs a `type`")?;
+        let fragment_index = self.fragment_index.unwrap_or(0);
+        let path_trace = || -> Result<Vec<(NodeId, NodeType)>, String> {
+            let trace = self.path_trace.as_ref().ok_or("a flood needs a `path_trace`")?;
+            Ok(trace.iter().map(|&(id, kind)| (id, kind.into())).collect())
+        };
+        let flood_id = || self.flood_id.ok_or("a flood needs a `flood_id`");
+        let hops = || self.hops.clone().ok_or(format!("a {kind:?} needs `hops`"));
+
+        let (pack_type, hops) = match kind {
+            PacketKind::Fragment => {
+                let text = self.data.as_deref().unwrap_or_default().as_bytes();
+                if text.len() > 128 {
+                    return Err("the `data` of a fragment is at most 128 bytes".to_string());
+                }
+                let mut data = [0; 128];
+                data[..text.len()].copy_from_slice(text);
+                let fragment = Fragment {
+                    fragment_index,
+                    total_n_fragments: self.total_n_fragments.unwrap_or(1),
+                    length: u8::try_from(text.len()).expect("checked above"),
+                    data,
+                };
+                (PacketType::MsgFragment(fragment), hops()?)
+            }
+            PacketKind::Ack => (PacketType::Ack(Ack { fragment_index }), hops()?),
+            PacketKind::Nack => {
+                let nack_type = self.nack.ok_or("a nack needs a `nack` type")?.into();
+                (PacketType::Nack(Nack { fragment_index, nack_type }), hops()?)
+            }
+            PacketKind::FloodRequest => {
+                let path_trace = path_trace()?;
+                let initiator_id = self.initiator.or(path_trace.first().map(|(id, _)| *id))
+                    .ok_or("a flood request needs an `initiator`")?;
+                let request = FloodRequest { flood_id: flood_id()?, initiator_id, path_trace };
+                (PacketType::FloodRequest(request), self.hops.clone().unwrap_or_default())
+            }
+            PacketKind::FloodResponse => {
+                let path_trace = path_trace()?;
+                let hops = self.hops.clone().unwrap_or_else(|| path_trace.iter().rev().map(|(id, _)| *id).collect());
+                (PacketType::FloodResponse(FloodResponse { flood_id: flood_id()?, path_trace }), hops)
+            }
+        };
+        let default_index = if kind == PacketKind::FloodRequest { 0 } else { 1 };
+        Ok(Packet {
+            pack_type,
+            routing_header: SourceRoutingHeader { hop_index: self.hop_index.unwrap_or(default_index), hops },
+            session_id: self.session.unwrap_or(0),
+        })
+    }
+
+    /// Builds a matcher checking the given fields.
+    #[must_use]
+    pub fn to_matcher(&self) -> PacketMatcher {
+        let mut matcher = match self.kind {
+            None => PacketMatcher::any(),
+            Some(PacketKind::Fragment) => PacketMatcher::fragment(),
+            Some(PacketKind::Ack) => PacketMatcher::ack(),
+            Some(PacketKind::Nack) => PacketMatcher::nack(),
+            Some(PacketKind::FloodRequest) => PacketMatcher::flood_request(),
+            Some(PacketKind::FloodResponse) => PacketMatcher::flood_response(),
+        };
+        if let Some(session) = self.session {
+            matcher = matcher.session(session);
+        }
+        if let Some(index) = self.fragment_index {
+            matcher = matcher.fragment_index(index);
+        }
+        if let Some(total) = self.total_n_fragments {
+            matcher = matcher.satisfying(&format!("{total} fragments"), move |p| {
+                matches!(&p.pack_type, PacketType::MsgFragment(f) if f.total_n_fragments == total)
+            });
+        }
+        if let Some(text) = self.data.clone() {
+            matcher = matcher.satisfying(&format!("data {text:?}"), move |p| {
+                matches!(&p.pack_type, PacketType::MsgFragment(f) if f.data[..f.length as usize] == *text.as_bytes())
+            });
+        }
+        if let Some(nack) = self.nack {
+            matcher = matcher.nack_type(nack.into());
+        }
+        if let Some(hops) = &self.hops {
+            matcher = matcher.hops(hops);
+        }
+        if let Some(index) = self.hop_index {
+            matcher = matcher.hop_index(index);
+        }
+        if let Some(flood_id) = self.flood_id {
+            matcher = matcher.flood_id(flood_id);
+        }
+        if let Some(initiator) = self.initiator {
+            matcher = matcher.satisfying(&format!("initiator {initiator}"), move |p| {
+                matches!(&p.pack_type, PacketType::FloodRequest(r) if r.initiator_id == initiator)
+            });
+        }
+        if let Some(trace) = &self.path_trace {
+            let trace: Vec<(NodeId, NodeType)> = trace.iter().map(|&(id, kind)| (id, kind.into())).collect();
+            matcher = matcher.path_trace(&trace);
+        }
+        matcher
+    }
+}
+
+/// A step whose expectations were not met.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepFailure {
+    /// The number of the step, from 1, or 0 if the topology is invalid.
+    pub step: usize,
+    /// The description of the step.
+    pub description: String,
+    /// The unmet expectations, with the mismatching fields of the closest message received.
+    pub lines: Vec<String>,
+}
+
+/// Outcome of [`Scenario::run`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioReport {
+    /// The name of the scenario.
+    pub name: String,
+    /// The number of steps.
+    pub steps: usize,
+    /// The failed steps.
+    pub failures: Vec<StepFailure>,
+}
+
+impl ScenarioReport {
+    /// Returns whether every step passed.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl std::fmt::Display for ScenarioReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.passed() {
+            return write!(f, "{}: {} steps passed", self.name, self.steps);
+        }
+        write!(f, "{}: {} of {} steps failed", self.name, self.failures.len(), self.steps)?;
+        for failure in &self.failures {
+            write!(f, "\n  {}:", failure.description)?;
+            for line in &failure.lines {
+                write!(f, "\n    {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+//! `proptest` strategies for the inputs of a drone. Needs the `proptest` feature.
+//!
+//! Node IDs are mostly taken from [`IDS`], so that routes often go through the drone and its
+//! neighbors, and indexes are mostly small, so that fragments and floods repeat.
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn never_panics(packet in packet()) {
+//!         let mut direct = DirectDrone::new(DRONE, &NEIGHBORS, 0.5, 0);
+//!         direct.drone.handle_packet(packet);
+//!     }
+//! }
+//! ```
+
+use proptest::prelude::*;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType};
+
+/// The ID of the drone under test.
+pub const DRONE: NodeId = 11;
+/// IDs used in routes: the drone, its neighbors and a few other nodes.
+pub const IDS: [NodeId; 6] = [1, DRONE, 12, 13, 14, 21];
+/// The neighbors of the drone, for [`super::DirectDrone::new`]; the channel of 14 is closed.
+pub const NEIGHBORS: [(NodeId, bool); 4] = [(1, true), (12, true), (13, true), (14, false)];
+
+/// A node ID, usually one of [`IDS`].
+pub fn node_id() -> impl Strategy<Value = NodeId> {
+    prop_oneof![4 => prop::sample::select(IDS.to_vec()), 1 => any::<NodeId>()]
+}
+
+/// A fragment index, flood ID or session ID, usually below 4.
+pub fn index() -> impl Strategy<Value = u64> {
+    prop_oneof![4 => 0u64..4, 1 => any::<u64>()]
+}
+
+/// A routing header with up to 5 hops and a `hop_index` that can be past the end.
+pub fn header() -> impl Strategy<Value = SourceRoutingHeader> {
+    (prop::collection::vec(node_id(), 0..6), 0usize..8).prop_map(|(hops, hop_index)| SourceRoutingHeader { hop_index, hops })
+}
+
+/// A path trace with up to 4 entries of any type.
+pub fn path_trace() -> impl Strategy<Value = Vec<(NodeId, NodeType)>> {
+    let node_type = prop::sample::select(vec![NodeType::Client, NodeType::Drone, NodeType::Server]);
+    prop::collection::vec((node_id(), node_type), 0..5)
+}
+
+/// Any nack type.
+pub fn nack_type() -> impl Strategy<Value = NackType> {
+    prop_oneof![
+        node_id().prop_map(NackType::ErrorInRouting),
+        Just(NackType::DestinationIsDrone),
+        Just(NackType::Dropped),
+        node_id().prop_map(NackType::UnexpectedRecipient),
+    ]
+}
+
+/// A fragment with any length, including inconsistent indexes.
+pub fn fragment() -> impl Strategy<Value = Fragment> {
+    (index(), index(), 0u8..=128).prop_map(|(fragment_index, total_n_fragments, length)| Fragment {
+        fragment_index,
+        total_n_fragments,
+        length,
+        data: [0; 128],
+    })
+}
+
+/// Any packet type, mostly fragments and flood requests.
+pub fn pack_type() -> impl Strategy<Value = PacketType> {
+    prop_oneof![
+        3 => fragment().prop_map(PacketType::MsgFragment),
+        1 => index().prop_map(|fragment_index| PacketType::Ack(Ack { fragment_index })),
+        1 => (index(), nack_type()).prop_map(|(fragment_index, nack_type)| PacketType::Nack(Nack { fragment_index, nack_type })),
+        2 => (index(), node_id(), path_trace()).prop_map(|(flood_id, initiator_id, path_trace)| {
+            PacketType::FloodRequest(FloodRequest { flood_id, initiator_id, path_trace })
+        }),
+        1 => (index(), path_trace()).prop_map(|(flood_id, path_trace)| PacketType::FloodResponse(FloodResponse { flood_id, path_trace })),
+    ]
+}
+
+/// Any packet, with any route.
+pub fn packet() -> impl Strategy<Value = Packet> {
+    (pack_type(), header(), index()).prop_map(|(pack_type, routing_header, session_id)| Packet { pack_type, routing_header, session_id })
+}
+
+/// A PDR that never, sometimes or always drops.
+pub fn pdr() -> impl Strategy<Value = f32> {
+    prop::sample::select(vec![0.0, 0.5, 1.0])
+}
@@ -0,0 +1,306 @@
+//! Deterministic traces of a scenario, compared with checked-in golden files.
+//!
+//! [`TraceNetwork`] runs the drones of a topology in the calling thread. It delivers one queued
+//! packet per node per round, in the order of the node IDs, and the drops of every drone are
+//! seeded from the `seed` of the scenario, so a scenario always produces the same trace. Each
+//! line of a trace is a step, a packet delivered to a node or an event of a drone:
+//!
+//! ```text
+//! # step 1: dropped by the second drone
+//! inject 11 session 5 MsgFragment 2/1 (0 bytes) route [1, 11, 12, 21]@1
+//! 11 <- session 5 MsgFragment 2/1 (0 bytes) route [1, 11, 12, 21]@1
+//! 11 PacketSent session 5 MsgFragment 2/1 (0 bytes) route [1, 11, 12, 21]@2
+//! ```
+//!
+//! [`check_golden`] compares the trace of a scenario file with the `.trace` file next to it.
+//! After checking that a difference is intended, the golden files are rewritten with
+//! `BCD_UPDATE_GOLDEN=1 cargo test --test golden_tests` or `bcd_scenario --update-golden`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use wg_2024::config::Config;
+use wg_2024::controller::DroneEvent;
+use wg_2024::drone::Drone;
+use wg_2024::network::NodeId;
+use wg_2024::packet::{Packet, PacketType};
+use crate::BetterCallDrone;
+use super::scenario::{CommandSpec, Scenario, ScenarioError};
+
+/// Deliveries after which a trace is cut, in case packets loop forever.
+const MAX_DELIVERIES: usize = 100_000;
+
+/// A network of `BetterCallDrone`s stepped by hand and recording what happens.
+pub struct TraceNetwork {
+    drones: BTreeMap<NodeId, BetterCallDrone>,
+    queues: BTreeMap<NodeId, Receiver<Packet>>,
+    senders: HashMap<NodeId, Sender<Packet>>,
+    events: Receiver<DroneEvent>,
+    lines: Vec<String>,
+}
+
+impl TraceNetwork {
+    /// Creates the drones of a topology. Clients and servers only collect their packets.
+    ///
+    /// # Parameters
+    /// - `config`: The topology.
+    /// - `seed`: The seed of the drops, combined with the ID of each drone.
+    #[must_use]
+    pub fn new(config: &Config, seed: u64) -> Self {
+        let mut queues = BTreeMap::new();
+        let mut senders = HashMap::new();
+        let ids = config.drone.iter().map(|d| d.id)
+            .chain(config.client.iter().map(|c| c.id))
+            .chain(config.server.iter().map(|s| s.id));
+        for id in ids {
+            let (send, recv) = unbounded();
+            senders.insert(id, send);
+            queues.insert(id, recv);
+        }
+
+        let (event_send, events) = unbounded();
+        let mut drones = BTreeMap::new();
+        for d in &config.drone {
+            let (_, command_recv) = unbounded();
+            let neighbors = d.connected_node_ids.iter()
+                .filter_map(|id| senders.get(id).map(|sender: &Sender<Packet>| (*id, sender.clone())))
+                .collect();
+            let mut drone = BetterCallDrone::new(d.id, event_send.clone(), command_recv, queues[&d.id].clone(), neighbors, d.pdr);
+            drone.set_seed(seed ^ u64::from(d.id));
+            drones.insert(d.id, drone);
+        }
+
+        Self { drones, queues, senders, events, lines: Vec::new() }
+    }
+
+    /// Puts a packet into the queue of a node, as if a neighbor had sent it.
+    pub fn inject(&mut self, node_id: NodeId, packet: Packet) {
+        self.lines.push(format!("inject {node_id} {}", format_packet(&packet)));
+        match self.senders.get(&node_id) {
+            Some(sender) => {
+                let _ = sender.send(packet);
+            }
+            None => self.lines.push(format!("  node {node_id} does not exist")),
+        }
+    }
+
+    /// Gives a command to the drones, like the simulation controller would, without checking
+    /// the topology rules.
+    pub fn command(&mut self, command: &CommandSpec) {
+        self.lines.push(format!("command {command:?}"));
+        match *command {
+            CommandSpec::Crash { drone: id } => {
+                for drone in self.drones.values_mut() {
+                    drone.remove_sender(id);
+                }
+                if let Some(mut drone) = self.drones.remove(&id) {
+                    drone.crash_drone();
+                    self.record_events(id);
+                }
+                self.queues.remove(&id);
+                self.senders.remove(&id);
+            }
+            CommandSpec::SetPdr { drone, pdr } => {
+                if let Some(drone) = self.drones.get_mut(&drone) {
+                    drone.set_pdr(pdr);
+                }
+            }
+            CommandSpec::AddLink { nodes: [a, b] } => {
+                for (from, to) in [(a, b), (b, a)] {
+                    if let (Some(drone), Some(sender)) = (self.drones.get_mut(&from), self.senders.get(&to)) {
+                        drone.add_sender(to, sender.clone());
+                    }
+                }
+            }
+            CommandSpec::RemoveLink { nodes: [a, b] } => {
+                for (from, to) in [(a, b), (b, a)] {
+                    if let Some(drone) = self.drones.get_mut(&from) {
+                        drone.remove_sender(to);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Delivers the queued packets until every queue is empty.
+    pub fn run(&mut self) {
+        let mut deliveries = 0;
+        loop {
+            let mut delivered = false;
+            let ids: Vec<NodeId> = self.queues.keys().copied().collect();
+            for id in ids {
+                let Ok(packet) = self.queues[&id].try_recv() else {
+                    continue;
+                };
+                delivered = true;
+                deliveries += 1;
+                self.lines.push(format!("{id} <- {}", format_packet(&packet)));
+                if let Some(drone) = self.drones.get_mut(&id) {
+                    drone.handle_packet(packet);
+                    self.record_events(id);
+                }
+            }
+            if !delivered {
+                return;
+            }
+            if deliveries >= MAX_DELIVERIES {
+                self.lines.push(format!("stopped after {MAX_DELIVERIES} deliveries"));
+                return;
+            }
+        }
+    }
+
+    /// Adds a line to the trace.
+    pub fn note(&mut self, line: String) {
+        self.lines.push(line);
+    }
+
+    /// Returns the lines recorded so far.
+    #[must_use]
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    fn record_events(&mut self, drone_id: NodeId) {
+        while let Ok(event) = self.events.try_recv() {
+            let line = match &event {
+                DroneEvent::PacketSent(packet) => format!("{drone_id} PacketSent {}", format_packet(packet)),
+                DroneEvent::PacketDropped(packet) => format!("{drone_id} PacketDropped {}", format_packet(packet)),
+                DroneEvent::ControllerShortcut(packet) => format!("{drone_id} ControllerShortcut {}", format_packet(packet)),
+            };
+            self.lines.push(line);
+        }
+    }
+}
+
+/// Formats every field of a packet but the content of fragments.
+fn format_packet(packet: &Packet) -> String {
+    let kind = match &packet.pack_type {
+        PacketType::MsgFragment(f) => format!("MsgFragment {}/{} ({} bytes)", f.fragment_index, f.total_n_fragments, f.length),
+        PacketType::Ack(ack) => format!("Ack {}", ack.fragment_index),
+        PacketType::Nack(nack) => format!("Nack {} {:?}", nack.fragment_index, nack.nack_type),
+        PacketType::FloodRequest(request) => format!("FloodRequest {} from {} trace {:?}",
+            request.flood_id, request.initiator_id, request.path_trace),
+        PacketType::FloodResponse(response) => format!("FloodResponse {} trace {:?}", response.flood_id, response.path_trace),
+    };
+    format!("session {} {kind} route {:?}@{}", packet.session_id, packet.routing_header.hops, packet.routing_header.hop_index)
+}
+
+/// Runs a scenario through a [`TraceNetwork`] seeded with the `seed` of the scenario.
+///
+/// # Returns
+/// The trace, one line per delivery, event, action and step.
+#[must_use]
+pub fn record(scenario: &Scenario) -> String {
+    let mut network = TraceNetwork::new(&scenario.topology, scenario.seed);
+    for (index, step) in scenario.steps.iter().enumerate() {
+        match &step.description {
+            Some(description) => network.note(format!("# step {}: {description}", index + 1)),
+            None => network.note(format!("# step {}", index + 1)),
+        }
+        if let Some(injection) = &step.inject {
+            match injection.packet.to_packet() {
+                Ok(packet) => network.inject(injection.node, packet),
+                Err(reason) => network.note(format!("invalid packet: {reason}")),
+            }
+        }
+        if let Some(command) = &step.command {
+            network.command(command);
+        }
+        network.run();
+    }
+    let mut trace = network.lines().join("\n");
+    trace.push('\n');
+    trace
+}
+
+/// Errors returned by [`check_golden`] and [`update_golden`].
+#[derive(Debug)]
+pub enum GoldenError {
+    /// The scenario cannot be loaded.
+    Scenario(ScenarioError),
+    /// The golden file does not exist yet.
+    Missing(PathBuf),
+    /// The golden file cannot be read or written.
+    Io(PathBuf, std::io::Error),
+    /// The trace differs from the golden file.
+    Differs {
+        /// The golden file.
+        golden: PathBuf,
+        /// The first differing line, from 1.
+        line: usize,
+        /// The golden lines from the first difference.
+        expected: Vec<String>,
+        /// The recorded lines from the first difference.
+        actual: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GoldenError::Scenario(e) => write!(f, "{e}"),
+            GoldenError::Missing(path) => write!(f, "{} does not exist, set BCD_UPDATE_GOLDEN=1 to create it", path.display()),
+            GoldenError::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            GoldenError::Differs { golden, line, expected, actual } => {
+                write!(f, "the trace differs from {} at line {line}:", golden.display())?;
+                for text in expected {
+                    write!(f, "\n  - {text}")?;
+                }
+                for text in actual {
+                    write!(f, "\n  + {text}")?;
+                }
+                write!(f, "\nset BCD_UPDATE_GOLDEN=1 to accept the new trace")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+/// Lines of each side shown after the first difference.
+const DIFF_CONTEXT: usize = 5;
+
+/// Returns the golden file of a scenario file: the same path with the `trace` extension.
+#[must_use]
+pub fn golden_path(scenario: &Path) -> PathBuf {
+    scenario.with_extension("trace")
+}
+
+/// Compares the trace of a scenario file with its golden file, or rewrites the golden file if
+/// the `BCD_UPDATE_GOLDEN` environment variable is set.
+///
+/// # Errors
+/// Returns a `GoldenError` if the scenario cannot be loaded, the golden file cannot be read or
+/// the traces differ.
+pub fn check_golden(path: impl AsRef<Path>) -> Result<(), GoldenError> {
+    let path = path.as_ref();
+    if env::var("BCD_UPDATE_GOLDEN").is_ok() {
+        return update_golden(path);
+    }
+    let trace = record(&Scenario::load(path).map_err(GoldenError::Scenario)?);
+    let golden = golden_path(path);
+    let expected = fs::read_to_string(&golden).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => GoldenError::Missing(golden.clone()),
+        _ => GoldenError::Io(golden.clone(), e),
+    })?;
+    let (expected, actual): (Vec<&str>, Vec<&str>) = (expected.lines().collect(), trace.lines().collect());
+    let Some(line) = (0..expected.len().max(actual.len())).find(|&i| expected.get(i) != actual.get(i)) else {
+        return Ok(());
+    };
+    let context = |lines: &[&str]| lines.iter().skip(line).take(DIFF_CONTEXT).map(ToString::to_string).collect();
+    Err(GoldenError::Differs { golden, line: line + 1, expected: context(&expected), actual: context(&actual) })
+}
+
+/// Records the trace of a scenario file and writes it to its golden file.
+///
+/// # Errors
+/// Returns a `GoldenError` if the scenario cannot be loaded or the golden file cannot be written.
+pub fn update_golden(path: impl AsRef<Path>) -> Result<(), GoldenError> {
+    let path = path.as_ref();
+    let trace = record(&Scenario::load(path).map_err(GoldenError::Scenario)?);
+    let golden = golden_path(path);
+    fs::write(&golden, trace).map_err(|e| GoldenError::Io(golden, e))
+}
@@ -0,0 +1,316 @@
+use std::collections::{BTreeSet, HashSet};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use wg_2024::config::{Client, Config, Drone, Server};
+use wg_2024::network::NodeId;
+
+/// Where clients and servers are attached to the generated drone graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Each client is attached to one or two random drones, each server to two random drones.
+    Random,
+    /// Clients are attached to the first drones of the topology, servers to the last ones.
+    Ends,
+}
+
+/// Parameters shared by every topology generator.
+///
+/// Drones get the ids `1..=n`, clients the following ones and servers the ones after the
+/// clients, so a generated network can contain at most 255 nodes.
+///
+/// The generators panic if the options cannot produce a valid network: no drones, servers with
+/// less than two drones, more than 255 nodes, or a PDR range that is empty or outside `[0, 1]`.
+#[derive(Debug, Clone)]
+pub struct GeneratorOptions {
+    /// The lowest and highest PDR a drone can get, each drone is assigned a uniform value in between.
+    pub pdr: (f32, f32),
+    /// The number of clients to attach.
+    pub clients: usize,
+    /// The number of servers to attach.
+    pub servers: usize,
+    /// How clients and servers are attached to the drones.
+    pub placement: Placement,
+    /// The seed of every random choice made by the generator.
+    pub seed: u64,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            pdr: (0.0, 0.0),
+            clients: 1,
+            servers: 1,
+            placement: Placement::Ends,
+            seed: 0,
+        }
+    }
+}
+
+/// Generates drones connected in a line: `1 - 2 - ... - n`.
+///
+/// # Panics
+/// Panics if the options are invalid, see [`GeneratorOptions`].
+#[must_use]
+pub fn line(n: usize, options: &GeneratorOptions) -> Config {
+    let edges = (1..n).map(|i| (i - 1, i)).collect();
+    build(n, edges, options)
+}
+
+/// Generates drones connected in a ring, a ring of less than three drones is a line.
+///
+/// # Panics
+/// Panics if the options are invalid, see [`GeneratorOptions`].
+#[must_use]
+pub fn ring(n: usize, options: &GeneratorOptions) -> Config {
+    let mut edges: Vec<(usize, usize)> = (1..n).map(|i| (i - 1, i)).collect();
+    if n >= 3 {
+        edges.push((n - 1, 0));
+    }
+    build(n, edges, options)
+}
+
+/// Generates a `rows` x `cols` grid of drones, each connected to its horizontal and vertical neighbors.
+///
+/// # Panics
+/// Panics if the options are invalid, see [`GeneratorOptions`].
+#[must_use]
+pub fn grid(rows: usize, cols: usize, options: &GeneratorOptions) -> Config {
+    let mut edges = Vec::new();
+    for r in 0..rows {
+        for c in 0..cols {
+            let i = r * cols + c;
+            if c + 1 < cols {
+                edges.push((i, i + 1));
+            }
+            if r + 1 < rows {
+                edges.push((i, i + cols));
+            }
+        }
+    }
+    build(rows * cols, edges, options)
+}
+
+/// Generates a star: the first drone is connected to all the others.
+///
+/// # Panics
+/// Panics if the options are invalid, see [`GeneratorOptions`].
+#[must_use]
+pub fn star(n: usize, options: &GeneratorOptions) -> Config {
+    let edges = (1..n).map(|i| (0, i)).collect();
+    build(n, edges, options)
+}
+
+/// Generates two parallel chains of `length` drones, with each drone connected to its twin in the other chain.
+///
+/// # Panics
+/// Panics if the options are invalid, see [`GeneratorOptions`].
+#[must_use]
+pub fn double_chain(length: usize, options: &GeneratorOptions) -> Config {
+    let mut edges = Vec::new();
+    for i in 0..length {
+        edges.push((i, i + length));
+        if i + 1 < length {
+            edges.push((i, i + 1));
+            edges.push((i + length, i + 1 + length));
+        }
+    }
+    build(2 * length, edges, options)
+}
+
+/// Generates a butterfly network of the given dimension: `dimension + 1` levels of `2^dimension` drones.
+///
+/// The drone in row `r` of level `l` is connected to rows `r` and `r ^ 2^l` of level `l + 1`.
+///
+/// # Panics
+/// Panics if the options are invalid, see [`GeneratorOptions`].
+#[must_use]
+pub fn butterfly(dimension: u32, options: &GeneratorOptions) -> Config {
+    let rows = 1usize << dimension;
+    let levels = dimension as usize + 1;
+    let mut edges = Vec::new();
+    for level in 0..dimension as usize {
+        for row in 0..rows {
+            let i = level * rows + row;
+            edges.push((i, i + rows));
+            edges.push((i, (level + 1) * rows + (row ^ (1 << level))));
+        }
+    }
+    build(levels * rows, edges, options)
+}
+
+/// Generates a complete tree where every inner drone has `branching` children.
+///
+/// # Panics
+/// Panics if the options are invalid, see [`GeneratorOptions`].
+#[must_use]
+pub fn tree(depth: u32, branching: usize, options: &GeneratorOptions) -> Config {
+    let mut n = 1;
+    let mut level_size = 1;
+    for _ in 0..depth {
+        level_size *= branching;
+        n += level_size;
+    }
+    let edges = (1..n).map(|i| ((i - 1) / branching, i)).collect();
+    build(n, edges, options)
+}
+
+/// Generates an Erdős–Rényi random graph: every pair of drones is connected with probability `p`.
+///
+/// If the result is not connected, the components are joined with random edges.
+///
+/// # Panics
+/// Panics if `p` is not in `[0, 1]` or if the options are invalid, see [`GeneratorOptions`].
+#[must_use]
+pub fn erdos_renyi(n: usize, p: f64, options: &GeneratorOptions) -> Config {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut edges = Vec::new();
+    for a in 0..n {
+        for b in a + 1..n {
+            if rng.gen_bool(p) {
+                edges.push((a, b));
+            }
+        }
+    }
+    connect_components(n, &mut edges, &mut rng);
+    build_with_rng(n, edges, options, &mut rng)
+}
+
+/// Generates a scale-free graph with the Barabási–Albert model.
+///
+/// The first `m` drones form a line, every following drone is then connected to `m` existing drones
+/// chosen with a probability proportional to their degree.
+///
+/// # Panics
+/// Panics if the options are invalid, see [`GeneratorOptions`].
+#[must_use]
+pub fn scale_free(n: usize, m: usize, options: &GeneratorOptions) -> Config {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let m = m.clamp(1, n.max(1));
+    let mut edges: Vec<(usize, usize)> = (1..m.min(n)).map(|i| (i - 1, i)).collect();
+    // Every drone appears once per link, plus once so that isolated drones can be chosen.
+    let mut weighted: Vec<usize> = (0..m.min(n)).collect();
+    for &(a, b) in &edges {
+        weighted.push(a);
+        weighted.push(b);
+    }
+    for new in m..n {
+        let mut targets = HashSet::new();
+        while targets.len() < m {
+            targets.insert(*weighted.choose(&mut rng).expect("at least one drone exists"));
+        }
+        for target in targets {
+            edges.push((target, new));
+            weighted.push(target);
+            weighted.push(new);
+        }
+    }
+    build_with_rng(n, edges, options, &mut rng)
+}
+
+/// Joins the connected components of a graph by linking random drones of consecutive components.
+fn connect_components(n: usize, edges: &mut Vec<(usize, usize)>, rng: &mut StdRng) {
+    let mut parent: Vec<usize> = (0..n).collect();
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        parent[i] = root;
+        root
+    }
+    for &(a, b) in edges.iter() {
+        let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+        parent[ra] = rb;
+    }
+    let mut components: Vec<Vec<usize>> = Vec::new();
+    let mut roots: Vec<usize> = Vec::new();
+    for i in 0..n {
+        let root = find(&mut parent, i);
+        if let Some(position) = roots.iter().position(|&r| r == root) {
+            components[position].push(i);
+        } else {
+            roots.push(root);
+            components.push(vec![i]);
+        }
+    }
+    for pair in components.windows(2) {
+        let a = *pair[0].choose(rng).expect("components are not empty");
+        let b = *pair[1].choose(rng).expect("components are not empty");
+        edges.push((a, b));
+    }
+}
+
+/// Builds the configuration of a drone graph, seeding the random choices from the options.
+fn build(n: usize, edges: Vec<(usize, usize)>, options: &GeneratorOptions) -> Config {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    build_with_rng(n, edges, options, &mut rng)
+}
+
+/// Builds the configuration of a drone graph and attaches clients and servers to it.
+///
+/// # Parameters
+/// - `n`: The number of drones, indexed from `0`.
+/// - `edges`: The links between drones, as pairs of indexes.
+/// - `options`: The generator options.
+/// - `rng`: The random generator used for PDRs and placement.
+///
+/// # Panics
+/// Panics if there are no drones, if servers are requested with less than two drones,
+/// if the network has more than 255 nodes or if the PDR range is empty or outside `[0, 1]`.
+fn build_with_rng(n: usize, edges: Vec<(usize, usize)>, options: &GeneratorOptions, rng: &mut StdRng) -> Config {
+    assert!(n > 0, "a topology needs at least one drone");
+    assert!(options.servers == 0 || n >= 2, "servers need at least two drones");
+    assert!(n + options.clients + options.servers <= 255, "too many nodes for u8 ids");
+    let (min_pdr, max_pdr) = options.pdr;
+    assert!((0.0..=max_pdr).contains(&min_pdr) && max_pdr <= 1.0, "invalid PDR range {min_pdr}..={max_pdr}");
+
+    let drone_id = |i: usize| (i + 1) as NodeId;
+    let mut links: Vec<BTreeSet<NodeId>> = vec![BTreeSet::new(); n];
+    for (a, b) in edges {
+        if a != b {
+            links[a].insert(drone_id(b));
+            links[b].insert(drone_id(a));
+        }
+    }
+
+    let mut client = Vec::with_capacity(options.clients);
+    for c in 0..options.clients {
+        let id = (n + c + 1) as NodeId;
+        let drones = match options.placement {
+            Placement::Random => {
+                let amount = if n >= 2 { rng.gen_range(1..=2) } else { 1 };
+                rand::seq::index::sample(rng, n, amount).into_vec()
+            }
+            Placement::Ends => vec![c % n],
+        };
+        for &d in &drones {
+            links[d].insert(id);
+        }
+        client.push(Client { id, connected_drone_ids: drones.into_iter().map(drone_id).collect() });
+    }
+
+    let mut server = Vec::with_capacity(options.servers);
+    for s in 0..options.servers {
+        let id = (n + options.clients + s + 1) as NodeId;
+        let drones = match options.placement {
+            Placement::Random => rand::seq::index::sample(rng, n, 2).into_vec(),
+            Placement::Ends => vec![n - 1 - (2 * s) % n, n - 1 - (2 * s + 1) % n],
+        };
+        for &d in &drones {
+            links[d].insert(id);
+        }
+        server.push(Server { id, connected_drone_ids: drones.into_iter().map(drone_id).collect() });
+    }
+
+    let drone = links.into_iter()
+        .enumerate()
+        .map(|(i, neighbors)| Drone {
+            id: drone_id(i),
+            connected_node_ids: neighbors.into_iter().collect(),
+            pdr: rng.gen_range(min_pdr..=max_pdr),
+        })
+        .collect();
+
+    Config { drone, client, server }
+}
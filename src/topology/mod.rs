@@ -0,0 +1,5 @@
+pub mod generators;
+pub mod validation;
+
+pub use generators::{GeneratorOptions, Placement};
+pub use validation::{parse_config, validate_config, TopologyError};
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use wg_2024::config::Config;
+use wg_2024::network::NodeId;
+use wg_2024::packet::NodeType;
+
+/// A single violation of the WG network rules found in a configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TopologyError {
+    /// The same id is used by more than one node.
+    DuplicateId(NodeId),
+    /// A node lists itself among its neighbors.
+    SelfNeighbor(NodeId),
+    /// A node lists the same neighbor more than once.
+    DuplicateNeighbor { node: NodeId, neighbor: NodeId },
+    /// A node lists a neighbor that is not defined anywhere in the configuration.
+    UnknownNeighbor { node: NodeId, neighbor: NodeId },
+    /// `from` lists `to` as a neighbor, but `to` does not list `from`.
+    NotBidirectional { from: NodeId, to: NodeId },
+    /// A client or server is connected to a node that is not a drone.
+    NotADrone { node: NodeId, neighbor: NodeId },
+    /// A client is connected to less than one or more than two drones.
+    InvalidClientDegree { client: NodeId, degree: usize },
+    /// A server is connected to less than two drones.
+    InvalidServerDegree { server: NodeId, degree: usize },
+    /// A drone has a PDR outside of `[0, 1]`.
+    InvalidPdr { drone: NodeId, pdr: f32 },
+    /// Some nodes cannot be reached from the rest of the network.
+    Disconnected { unreachable: Vec<NodeId> },
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopologyError::DuplicateId(id) => write!(f, "id {id} is used by more than one node"),
+            TopologyError::SelfNeighbor(id) => write!(f, "node {id} lists itself as a neighbor"),
+            TopologyError::DuplicateNeighbor { node, neighbor } => {
+                write!(f, "node {node} lists neighbor {neighbor} more than once")
+            }
+            TopologyError::UnknownNeighbor { node, neighbor } => {
+                write!(f, "node {node} lists neighbor {neighbor}, which does not exist")
+            }
+            TopologyError::NotBidirectional { from, to } => {
+                write!(f, "node {from} lists {to} as a neighbor, but {to} does not list {from}")
+            }
+            TopologyError::NotADrone { node, neighbor } => {
+                write!(f, "node {node} can only be connected to drones, but lists {neighbor}")
+            }
+            TopologyError::InvalidClientDegree { client, degree } => {
+                write!(f, "client {client} is connected to {degree} drones, expected 1 or 2")
+            }
+            TopologyError::InvalidServerDegree { server, degree } => {
+                write!(f, "server {server} is connected to {degree} drones, expected at least 2")
+            }
+            TopologyError::InvalidPdr { drone, pdr } => {
+                write!(f, "drone {drone} has PDR {pdr}, expected a value in [0, 1]")
+            }
+            TopologyError::Disconnected { unreachable } => {
+                write!(f, "the network is not connected, unreachable nodes: {unreachable:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TopologyError {}
+
+/// Parses a network configuration file in the WG TOML format.
+///
+/// # Parameters
+/// - `content`: The content of the configuration file.
+///
+/// # Errors
+/// Returns the `toml` deserialization error if the content is not a valid configuration.
+pub fn parse_config(content: &str) -> Result<Config, toml::de::Error> {
+    toml::from_str(content)
+}
+
+/// Checks a parsed network configuration against the WG rules.
+///
+/// The rules are:
+/// - every id is unique and no node lists itself as a neighbor;
+/// - every link is bidirectional and points to an existing node;
+/// - clients are connected to one or two drones, servers to at least two;
+/// - every PDR is in `[0, 1]`;
+/// - the network is connected. Clients and servers do not forward packets, so they are
+///   only considered as endpoints and never to connect two parts of the network.
+///
+/// # Parameters
+/// - `config`: The configuration to validate.
+///
+/// # Errors
+/// Returns every violation found, in the order the nodes appear in the configuration.
+pub fn validate_config(config: &Config) -> Result<(), Vec<TopologyError>> {
+    let mut errors = Vec::new();
+
+    let mut nodes: HashMap<NodeId, (NodeType, &[NodeId])> = HashMap::new();
+    let all_nodes = config.drone.iter().map(|d| (d.id, NodeType::Drone, d.connected_node_ids.as_slice()))
+        .chain(config.client.iter().map(|c| (c.id, NodeType::Client, c.connected_drone_ids.as_slice())))
+        .chain(config.server.iter().map(|s| (s.id, NodeType::Server, s.connected_drone_ids.as_slice())));
+    for (id, node_type, neighbors) in all_nodes {
+        if nodes.insert(id, (node_type, neighbors)).is_some() && !errors.contains(&TopologyError::DuplicateId(id)) {
+            errors.push(TopologyError::DuplicateId(id));
+        }
+    }
+    if !errors.is_empty() {
+        // Links cannot be checked reliably when ids are ambiguous.
+        return Err(errors);
+    }
+
+    for drone in &config.drone {
+        if !(0.0..=1.0).contains(&drone.pdr) {
+            errors.push(TopologyError::InvalidPdr { drone: drone.id, pdr: drone.pdr });
+        }
+        check_links(drone.id, &drone.connected_node_ids, &nodes, &mut errors);
+    }
+    for client in &config.client {
+        check_links(client.id, &client.connected_drone_ids, &nodes, &mut errors);
+        let degree = drone_degree(&client.connected_drone_ids);
+        if !(1..=2).contains(&degree) {
+            errors.push(TopologyError::InvalidClientDegree { client: client.id, degree });
+        }
+    }
+    for server in &config.server {
+        check_links(server.id, &server.connected_drone_ids, &nodes, &mut errors);
+        let degree = drone_degree(&server.connected_drone_ids);
+        if degree < 2 {
+            errors.push(TopologyError::InvalidServerDegree { server: server.id, degree });
+        }
+    }
+
+    let unreachable = unreachable_nodes(&nodes);
+    if !unreachable.is_empty() {
+        errors.push(TopologyError::Disconnected { unreachable });
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Checks the neighbor list of a single node.
+///
+/// # Parameters
+/// - `id`: The ID of the node.
+/// - `neighbors`: The neighbors listed by the node.
+/// - `nodes`: Every node of the configuration, with its type and neighbors.
+/// - `errors`: The list the violations are appended to.
+fn check_links(id: NodeId, neighbors: &[NodeId], nodes: &HashMap<NodeId, (NodeType, &[NodeId])>, errors: &mut Vec<TopologyError>) {
+    let node_type = nodes[&id].0;
+    let mut seen = HashSet::new();
+    for &neighbor in neighbors {
+        if neighbor == id {
+            errors.push(TopologyError::SelfNeighbor(id));
+            continue;
+        }
+        if !seen.insert(neighbor) {
+            errors.push(TopologyError::DuplicateNeighbor { node: id, neighbor });
+            continue;
+        }
+        match nodes.get(&neighbor) {
+            None => errors.push(TopologyError::UnknownNeighbor { node: id, neighbor }),
+            Some((neighbor_type, neighbor_links)) => {
+                if node_type != NodeType::Drone && *neighbor_type != NodeType::Drone {
+                    errors.push(TopologyError::NotADrone { node: id, neighbor });
+                }
+                if !neighbor_links.contains(&id) {
+                    errors.push(TopologyError::NotBidirectional { from: id, to: neighbor });
+                }
+            }
+        }
+    }
+}
+
+/// Counts the distinct neighbors listed by a client or server.
+fn drone_degree(neighbors: &[NodeId]) -> usize {
+    neighbors.iter().collect::<HashSet<_>>().len()
+}
+
+/// Returns the nodes that cannot be reached from the first drone, sorted by id.
+///
+/// Only drones are expanded during the visit, so a network whose drones are split in two
+/// parts joined only by a client or a server is reported as disconnected.
+fn unreachable_nodes(nodes: &HashMap<NodeId, (NodeType, &[NodeId])>) -> Vec<NodeId> {
+    let start = nodes.iter()
+        .filter(|(_, (node_type, _))| *node_type == NodeType::Drone)
+        .map(|(&id, _)| id)
+        .min()
+        .or_else(|| nodes.keys().min().copied());
+    let Some(start) = start else {
+        return Vec::new();
+    };
+
+    let mut visited = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(id) = queue.pop_front() {
+        let (node_type, neighbors) = nodes[&id];
+        if node_type != NodeType::Drone && id != start {
+            continue;
+        }
+        for &neighbor in neighbors {
+            if nodes.contains_key(&neighbor) && visited.insert(neighbor) {
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    let mut unreachable: Vec<NodeId> = nodes.keys().filter(|id| !visited.contains(id)).copied().collect();
+    unreachable.sort_unstable();
+    unreachable
+}
@@ -0,0 +1,273 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use wg_2024::network::NodeId;
+use wg_2024::packet::Fragment;
+use crate::fragmentation::{fragment_message, Reassembler};
+
+/// Parameters of the selective repeat protocol.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransportConfig {
+    /// How many fragments of a session can be unacknowledged at the same time.
+    pub window: usize,
+    /// How long to wait for the `Ack` of a fragment before sending it again.
+    pub timeout: Duration,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            window: 32,
+            timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Counters of a `Transport`, for reports and benchmarks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransportStats {
+    /// Fragments sent for the first time.
+    pub fragments_sent: u64,
+    /// Fragments sent again after a `Nack` or a timeout.
+    pub retransmissions: u64,
+    /// Retransmissions caused by a timeout.
+    pub timeouts: u64,
+    /// `Nack`s received for pending fragments.
+    pub nacks: u64,
+    /// Messages whose fragments were all acknowledged.
+    pub messages_delivered: u64,
+    /// Messages fully reassembled.
+    pub messages_received: u64,
+}
+
+/// A fragment the caller has to route and send.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutgoingFragment {
+    pub destination: NodeId,
+    pub session_id: u64,
+    pub fragment: Fragment,
+}
+
+/// A message reassembled by the receiving side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedMessage {
+    pub source: NodeId,
+    pub session_id: u64,
+    pub data: Vec<u8>,
+}
+
+/// The sending state of a message.
+#[derive(Debug, Clone)]
+struct OutgoingSession {
+    destination: NodeId,
+    fragments: Vec<Fragment>,
+    acked: Vec<bool>,
+    missing: usize,
+    /// Index of the first fragment never sent.
+    next_new: usize,
+    /// Unacknowledged fragments in flight, with the time they were last sent.
+    in_flight: HashMap<u64, Instant>,
+    /// Fragments reported by a `Nack`, to send again as soon as possible.
+    retransmit: VecDeque<u64>,
+}
+
+impl OutgoingSession {
+    fn outstanding(&self) -> usize {
+        self.in_flight.len() + self.retransmit.len()
+    }
+}
+
+/// A reliable transport on top of the drone network, using selective repeat.
+///
+/// Messages are chunked into fragments and each session keeps at most `window` fragments
+/// unacknowledged. Only the fragments reported in a `Nack` or whose `Ack` did not arrive in time
+/// are sent again. The transport does not do any I/O: the caller routes and sends the fragments
+/// returned by [`Transport::poll_transmit`] and feeds back the `Ack`s, `Nack`s and fragments it receives.
+#[derive(Debug, Clone)]
+pub struct Transport {
+    config: TransportConfig,
+    next_session_id: u64,
+    outgoing: HashMap<u64, OutgoingSession>,
+    delivered: VecDeque<u64>,
+    reassembler: Reassembler,
+    completed: HashSet<(NodeId, u64)>,
+    received: VecDeque<ReceivedMessage>,
+    stats: TransportStats,
+}
+
+impl Transport {
+    /// Creates a transport with no sessions.
+    #[must_use]
+    pub fn new(config: TransportConfig) -> Self {
+        Self {
+            config,
+            next_session_id: 0,
+            outgoing: HashMap::new(),
+            delivered: VecDeque::new(),
+            reassembler: Reassembler::new(),
+            completed: HashSet::new(),
+            received: VecDeque::new(),
+            stats: TransportStats::default(),
+        }
+    }
+
+    /// Starts sending a message.
+    ///
+    /// # Parameters
+    /// - `destination`: The node the message is for.
+    /// - `data`: The message.
+    ///
+    /// # Returns
+    /// The `session_id` of the message.
+    pub fn send_message(&mut self, destination: NodeId, data: &[u8]) -> u64 {
+        let session_id = self.next_session_id;
+        self.next_session_id += 1;
+        let fragments = fragment_message(data);
+        self.outgoing.insert(session_id, OutgoingSession {
+            destination,
+            acked: vec![false; fragments.len()],
+            missing: fragments.len(),
+            fragments,
+            next_new: 0,
+            in_flight: HashMap::new(),
+            retransmit: VecDeque::new(),
+        });
+        session_id
+    }
+
+    /// Returns the next reassembled message, if any.
+    pub fn receive_message(&mut self) -> Option<ReceivedMessage> {
+        self.received.pop_front()
+    }
+
+    /// Returns the `session_id` of the next message whose fragments were all acknowledged, if any.
+    pub fn delivered_message(&mut self) -> Option<u64> {
+        self.delivered.pop_front()
+    }
+
+    /// Records the `Ack` of a fragment.
+    pub fn handle_ack(&mut self, session_id: u64, fragment_index: u64) {
+        let Some(session) = self.outgoing.get_mut(&session_id) else {
+            return;
+        };
+        let Some(acked) = usize::try_from(fragment_index).ok().and_then(|i| session.acked.get_mut(i)) else {
+            return;
+        };
+        if *acked {
+            return;
+        }
+        *acked = true;
+        session.missing -= 1;
+        session.in_flight.remove(&fragment_index);
+        session.retransmit.retain(|&i| i != fragment_index);
+        if session.missing == 0 {
+            self.outgoing.remove(&session_id);
+            self.delivered.push_back(session_id);
+            self.stats.messages_delivered += 1;
+        }
+    }
+
+    /// Records a `Nack`: the fragment will be returned again by the next [`Transport::poll_transmit`].
+    pub fn handle_nack(&mut self, session_id: u64, fragment_index: u64) {
+        let Some(session) = self.outgoing.get_mut(&session_id) else {
+            return;
+        };
+        let pending = usize::try_from(fragment_index).ok()
+            .and_then(|i| session.acked.get(i))
+            .is_some_and(|acked| !acked);
+        if pending && session.in_flight.remove(&fragment_index).is_some() {
+            session.retransmit.push_back(fragment_index);
+            self.stats.nacks += 1;
+        }
+    }
+
+    /// Stores a received fragment and queues the message once complete.
+    ///
+    /// Fragments of messages already reassembled are ignored, so retransmissions caused by
+    /// lost `Ack`s do not deliver a message twice.
+    ///
+    /// # Returns
+    /// `true` if the fragment completed a message.
+    pub fn handle_fragment(&mut self, source: NodeId, session_id: u64, fragment: &Fragment) -> bool {
+        if self.completed.contains(&(source, session_id)) {
+            return false;
+        }
+        let Some(data) = self.reassembler.insert(source, session_id, fragment) else {
+            return false;
+        };
+        self.completed.insert((source, session_id));
+        self.received.push_back(ReceivedMessage { source, session_id, data });
+        self.stats.messages_received += 1;
+        true
+    }
+
+    /// Returns the fragments to send now: fragments reported by a `Nack`, fragments whose `Ack`
+    /// timed out, then new fragments as long as the window of their session allows.
+    ///
+    /// # Parameters
+    /// - `now`: The current time.
+    pub fn poll_transmit(&mut self, now: Instant) -> Vec<OutgoingFragment> {
+        let mut out = Vec::new();
+        let mut session_ids: Vec<u64> = self.outgoing.keys().copied().collect();
+        session_ids.sort_unstable();
+        for session_id in session_ids {
+            let session = self.outgoing.get_mut(&session_id).expect("the session exists");
+            let mut send = |index: u64, session: &mut OutgoingSession| {
+                session.in_flight.insert(index, now);
+                out.push(OutgoingFragment {
+                    destination: session.destination,
+                    session_id,
+                    fragment: session.fragments[index as usize].clone(),
+                });
+            };
+
+            while let Some(index) = session.retransmit.pop_front() {
+                send(index, session);
+                self.stats.retransmissions += 1;
+            }
+            let mut expired: Vec<u64> = session.in_flight.iter()
+                .filter(|(_, &sent)| now.duration_since(sent) >= self.config.timeout)
+                .map(|(&index, _)| index)
+                .collect();
+            expired.sort_unstable();
+            for index in expired {
+                send(index, session);
+                self.stats.retransmissions += 1;
+                self.stats.timeouts += 1;
+            }
+            while session.next_new < session.fragments.len() && session.outstanding() < self.config.window {
+                let index = session.next_new as u64;
+                session.next_new += 1;
+                send(index, session);
+                self.stats.fragments_sent += 1;
+            }
+        }
+        out
+    }
+
+    /// Returns when the next fragment times out, to know how long the caller can sleep.
+    #[must_use]
+    pub fn next_timeout(&self) -> Option<Instant> {
+        self.outgoing.values()
+            .flat_map(|session| session.in_flight.values())
+            .min()
+            .map(|&sent| sent + self.config.timeout)
+    }
+
+    /// Returns the destination of a session not fully acknowledged.
+    #[must_use]
+    pub fn destination(&self, session_id: u64) -> Option<NodeId> {
+        self.outgoing.get(&session_id).map(|session| session.destination)
+    }
+
+    /// Returns the number of messages not fully acknowledged.
+    #[must_use]
+    pub fn pending_messages(&self) -> usize {
+        self.outgoing.len()
+    }
+
+    /// Returns the counters of the transport.
+    #[must_use]
+    pub fn stats(&self) -> TransportStats {
+        self.stats
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod chat_tests {
+    use std::time::Duration;
+    use crossbeam_channel::unbounded;
+    use drone_bettercalldrone::chat::{run_scenario, ChatMessage, ChatServer, DecodeError};
+    use drone_bettercalldrone::client::ClientCommand;
+    use drone_bettercalldrone::topology::{generators, GeneratorOptions, Placement};
+
+    #[test]
+    fn test_encode_decode() {
+        let messages = [
+            ChatMessage::Register { name: "Saul".to_string() },
+            ChatMessage::ListClients,
+            ChatMessage::SendTo { peer: 3, text: "S'all good, man ✓".to_string() },
+            ChatMessage::Registered,
+            ChatMessage::ClientList(vec![(1, "Saul".to_string()), (2, String::new())]),
+            ChatMessage::Message { from: 1, text: "x".repeat(1000) },
+            ChatMessage::Error("oops".to_string()),
+        ];
+        for message in messages {
+            let bytes = message.encode();
+            assert_eq!(ChatMessage::decode(&bytes), Ok(message));
+            assert_eq!(ChatMessage::decode(&bytes[..bytes.len() - 1]), Err(DecodeError::Truncated));
+            let mut longer = bytes.clone();
+            longer.push(0);
+            assert_eq!(ChatMessage::decode(&longer), Err(DecodeError::TrailingBytes));
+        }
+        assert_eq!(ChatMessage::decode(&[9]), Err(DecodeError::UnknownTag(9)));
+        assert_eq!(ChatMessage::decode(&[0, 1, 0, 0, 0, 0xff]), Err(DecodeError::InvalidText));
+    }
+
+    #[test]
+    fn test_server_requests() {
+        let (command_send, command_recv) = unbounded();
+        let (_event_send, event_recv) = unbounded();
+        let mut server = ChatServer::new(21, command_send, event_recv);
+        let reply = || match command_recv.try_recv().unwrap() {
+            ClientCommand::SendMessage { destination, data } => (destination, ChatMessage::decode(&data).unwrap()),
+            command => panic!("unexpected command {command:?}"),
+        };
+
+        server.handle_request(1, ChatMessage::SendTo { peer: 2, text: "hi".to_string() });
+        assert_eq!(reply(), (1, ChatMessage::Error("not registered".to_string())));
+
+        server.handle_request(2, ChatMessage::Register { name: "Kim".to_string() });
+        assert_eq!(reply(), (2, ChatMessage::Registered));
+        server.handle_request(1, ChatMessage::Register { name: "Saul".to_string() });
+        assert_eq!(reply(), (1, ChatMessage::Registered));
+
+        server.handle_request(2, ChatMessage::ListClients);
+        assert_eq!(reply(), (2, ChatMessage::ClientList(vec![(1, "Saul".to_string()), (2, "Kim".to_string())])));
+
+        server.handle_request(1, ChatMessage::SendTo { peer: 2, text: "hi".to_string() });
+        assert_eq!(reply(), (2, ChatMessage::Message { from: 1, text: "hi".to_string() }));
+        server.handle_request(1, ChatMessage::SendTo { peer: 5, text: "hi".to_string() });
+        assert_eq!(reply(), (1, ChatMessage::Error("client 5 is not registered".to_string())));
+    }
+
+    #[test]
+    fn test_chat_over_lossy_network() {
+        let options = GeneratorOptions {
+            pdr: (0.1, 0.3),
+            clients: 3,
+            servers: 1,
+            placement: Placement::Random,
+            seed: 35,
+        };
+        for config in [generators::ring(8, &options), generators::grid(3, 3, &options)] {
+            let report = run_scenario(config, 5, Duration::from_secs(20)).unwrap();
+            assert_eq!(report.sent, 3 * 2 * 5);
+            assert_eq!(report.received, report.sent);
+        }
+    }
+}
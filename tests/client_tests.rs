@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod client_tests {
+    use std::collections::HashMap;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crossbeam_channel::{unbounded, Receiver, Sender};
+    use wg_2024::config::{Client as ClientConfig, Config, Drone as DroneConfig};
+    use wg_2024::network::{NodeId, SourceRoutingHeader};
+    use wg_2024::packet::{FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType};
+    use drone_bettercalldrone::client::{Client, ClientCommand, ClientEvent, FLOOD_INTERVAL};
+    use drone_bettercalldrone::fragmentation::{fragment_message, Reassembler};
+    use drone_bettercalldrone::simulation::Simulation;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn spawn_client(simulation: &mut Simulation, id: NodeId) -> (Sender<ClientCommand>, Receiver<ClientEvent>) {
+        let endpoint = simulation.take_endpoint(id).unwrap();
+        let (command_send, command_recv) = unbounded();
+        let (event_send, event_recv) = unbounded();
+        let mut client = Client::new(id, event_send, command_recv, endpoint.packet_recv, endpoint.packet_send);
+        thread::spawn(move || client.run());
+        (command_send, event_recv)
+    }
+
+    fn wait_for(events: &Receiver<ClientEvent>, accept: impl Fn(&ClientEvent) -> bool) -> ClientEvent {
+        let deadline = Instant::now() + TIMEOUT;
+        while let Ok(event) = events.recv_deadline(deadline) {
+            if accept(&event) {
+                return event;
+            }
+        }
+        panic!("Timeout: expected event not received");
+    }
+
+    /// Ring of drones 11..=16 with client 1 on drone 11 and client 2 on drone 14.
+    fn ring(pdr: f32) -> Config {
+        let ids: Vec<NodeId> = (11..=16).collect();
+        let drone = ids.iter().enumerate().map(|(i, &id)| {
+            let mut connected_node_ids = vec![ids[(i + 5) % 6], ids[(i + 1) % 6]];
+            match id {
+                11 => connected_node_ids.push(1),
+                14 => connected_node_ids.push(2),
+                _ => {}
+            }
+            DroneConfig { id, connected_node_ids, pdr }
+        }).collect();
+        Config {
+            drone,
+            client: vec![
+                ClientConfig { id: 1, connected_drone_ids: vec![11] },
+                ClientConfig { id: 2, connected_drone_ids: vec![14] },
+            ],
+            server: vec![],
+        }
+    }
+
+    #[test]
+    fn test_fragment_and_reassemble() {
+        for size in [0usize, 1, 127, 128, 129, 1000] {
+            let message: Vec<u8> = (0..size).map(|i| i as u8).collect();
+            let mut fragments = fragment_message(&message);
+            assert_eq!(fragments.len(), size.div_ceil(128).max(1));
+            fragments.reverse();
+
+            let mut reassembler = Reassembler::new();
+            let last = fragments.pop().unwrap();
+            for fragment in &fragments {
+                assert_eq!(reassembler.insert(1, 7, fragment), None);
+                assert_eq!(reassembler.insert(1, 7, fragment), None);
+            }
+            assert_eq!(reassembler.insert(1, 7, &last), Some(message));
+            assert_eq!(reassembler.pending(), 0);
+        }
+    }
+
+    #[test]
+    fn test_huge_fragment_count() {
+        let mut reassembler = Reassembler::new();
+        let huge = Fragment { fragment_index: 3, total_n_fragments: u64::MAX >> 8, length: 1, data: [7; 128] };
+        assert_eq!(reassembler.insert(1, 7, &huge), None);
+        assert_eq!(reassembler.pending(), 1);
+
+        let message = vec![1, 2, 3];
+        assert_eq!(reassembler.insert(1, 8, &fragment_message(&message)[0]), Some(message));
+    }
+
+    #[test]
+    fn test_message_over_lossy_drones() {
+        let mut simulation = Simulation::spawn(ring(0.3)).unwrap();
+        let (sender, sender_events) = spawn_client(&mut simulation, 1);
+        let (_receiver, receiver_events) = spawn_client(&mut simulation, 2);
+
+        let data: Vec<u8> = (0..2000).map(|i| (i % 251) as u8).collect();
+        sender.send(ClientCommand::SendMessage { destination: 2, data: data.clone() }).unwrap();
+
+        let received = wait_for(&receiver_events, |e| matches!(e, ClientEvent::MessageReceived { .. }));
+        assert_eq!(received, ClientEvent::MessageReceived { source: 1, session_id: 0, data });
+        wait_for(&sender_events, |e| *e == ClientEvent::MessageDelivered { session_id: 0 });
+    }
+
+    #[test]
+    fn test_rediscovers_route_after_crash() {
+        let mut simulation = Simulation::spawn(ring(0.0)).unwrap();
+        let (sender, sender_events) = spawn_client(&mut simulation, 1);
+        let (_receiver, receiver_events) = spawn_client(&mut simulation, 2);
+
+        sender.send(ClientCommand::SendMessage { destination: 2, data: b"first".to_vec() }).unwrap();
+        wait_for(&sender_events, |e| *e == ClientEvent::MessageDelivered { session_id: 0 });
+        let ClientEvent::MessageReceived { data, .. } = wait_for(&receiver_events, |e| matches!(e, ClientEvent::MessageReceived { .. })) else {
+            unreachable!()
+        };
+        assert_eq!(data, b"first");
+
+        // Both routes of the ring have the same length, crash a drone on each side in turn.
+        for (session_id, drone) in [(1, 12), (2, 16)] {
+            if drone == 16 {
+                simulation.add_link(11, 13).unwrap();
+            }
+            simulation.crash(drone).unwrap();
+            sender.send(ClientCommand::SendMessage { destination: 2, data: vec![session_id as u8; 300] }).unwrap();
+            wait_for(&sender_events, |e| *e == ClientEvent::MessageDelivered { session_id });
+            wait_for(&receiver_events, |e| matches!(e, ClientEvent::MessageReceived { session_id: s, .. } if *s == session_id));
+        }
+    }
+
+    #[test]
+    fn test_nacks_do_not_flood_more_than_once_per_interval() {
+        let (neighbor_send, neighbor_recv) = unbounded();
+        let (_packet_send, packet_recv) = unbounded();
+        let (_command_send, command_recv) = unbounded();
+        let (event_send, _event_recv) = unbounded();
+        let mut client = Client::new(1, event_send, command_recv, packet_recv, HashMap::from([(11, neighbor_send)]));
+        let floods = |recv: &Receiver<Packet>| recv.try_iter().filter(|p| matches!(p.pack_type, PacketType::FloodRequest(_))).count();
+
+        client.handle_command(ClientCommand::DiscoverTopology);
+        let PacketType::FloodRequest(request) = neighbor_recv.try_recv().unwrap().pack_type else {
+            panic!("expected a FloodRequest");
+        };
+        let path_trace = vec![(1, NodeType::Client), (11, NodeType::Drone), (2, NodeType::Client)];
+        let response = FloodResponse { flood_id: request.flood_id, path_trace };
+        client.handle_packet(Packet::new_flood_response(SourceRoutingHeader { hop_index: 2, hops: vec![2, 11, 1] }, 0, response));
+        client.send_message(2, b"hi");
+        client.tick(Instant::now());
+        let fragment = neighbor_recv.try_recv().unwrap();
+        assert_eq!(fragment.routing_header.hops, vec![1, 11, 2]);
+
+        // Drone 11 reported the nack, so it is still reachable and the fragment is resent through it.
+        let nack = |nack_type| Packet::new_nack(SourceRoutingHeader { hop_index: 1, hops: vec![11, 1] }, 0, Nack { fragment_index: 0, nack_type });
+        client.handle_packet(nack(NackType::UnexpectedRecipient(11)));
+        let resent: Vec<Packet> = neighbor_recv.try_iter().collect();
+        assert_eq!(resent.len(), 1, "{resent:?}");
+        assert_eq!(resent[0].routing_header.hops, vec![1, 11, 2]);
+
+        for _ in 0..32 {
+            client.handle_packet(nack(NackType::ErrorInRouting(2)));
+        }
+        assert_eq!(floods(&neighbor_recv), 0);
+        client.tick(Instant::now() + FLOOD_INTERVAL);
+        assert_eq!(floods(&neighbor_recv), 1);
+    }
+}
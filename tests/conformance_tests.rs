@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod conformance_tests {
+    use drone_bettercalldrone::conformance;
+    use drone_bettercalldrone::BetterCallDrone;
+
+    #[test]
+    fn test_fragment_forward() {
+        conformance::fragment_forward::<BetterCallDrone>();
+    }
+
+    #[test]
+    fn test_nack_error_in_routing() {
+        conformance::nack_error_in_routing::<BetterCallDrone>();
+    }
+
+    #[test]
+    fn test_nack_destination_is_drone() {
+        conformance::nack_destination_is_drone::<BetterCallDrone>();
+    }
+
+    #[test]
+    fn test_nack_unexpected_recipient() {
+        conformance::nack_unexpected_recipient::<BetterCallDrone>();
+    }
+
+    #[test]
+    fn test_nack_dropped() {
+        conformance::nack_dropped::<BetterCallDrone>();
+    }
+
+    #[test]
+    fn test_nack_forward() {
+        conformance::nack_forward::<BetterCallDrone>();
+    }
+
+    #[test]
+    fn test_flood_deduplication() {
+        conformance::flood_deduplication::<BetterCallDrone>();
+    }
+
+    #[test]
+    fn test_flood_dead_end() {
+        conformance::flood_dead_end::<BetterCallDrone>();
+    }
+
+    #[test]
+    fn test_controller_shortcut() {
+        conformance::controller_shortcut::<BetterCallDrone>();
+    }
+
+    #[test]
+    fn test_crash_draining() {
+        conformance::crash_draining::<BetterCallDrone>();
+    }
+
+    #[test]
+    fn test_commands() {
+        conformance::commands::<BetterCallDrone>();
+    }
+}
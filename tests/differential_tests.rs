@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod differential_tests {
+    use proptest::prelude::*;
+    use wg_2024::controller::DroneEvent;
+    use wg_2024::network::{NodeId, SourceRoutingHeader};
+    use wg_2024::packet::{Fragment, NackType, Packet, PacketType};
+    use drone_bettercalldrone::testing::model::{step, Differential, Input, ModelDrone, Output};
+    use drone_bettercalldrone::testing::strategies::{node_id, packet, pdr, DRONE, NEIGHBORS};
+
+    fn fragment(hops: &[NodeId], hop_index: usize) -> Packet {
+        let header = SourceRoutingHeader { hop_index, hops: hops.to_vec() };
+        Packet::new_fragment(header, 5, Fragment { fragment_index: 3, total_n_fragments: 4, length: 0, data: [0; 128] })
+    }
+
+    /// Returns the nack type of the only output of a fragment.
+    fn nack_type(outputs: &[Output]) -> Option<NackType> {
+        outputs.iter().find_map(|output| match output {
+            Output::Packet(_, packet) | Output::Event(DroneEvent::ControllerShortcut(packet)) => match &packet.pack_type {
+                PacketType::Nack(nack) => Some(nack.nack_type),
+                _ => None,
+            },
+            Output::Event(_) => None,
+        })
+    }
+
+    #[test]
+    fn test_unexpected_recipient_before_drop() {
+        let model = ModelDrone::new(DRONE, &NEIGHBORS, 1.0);
+        let (_, outputs) = step(&model, &Input::Packet(fragment(&[1, 12, 21], 1)), 0.0);
+        assert_eq!(nack_type(&outputs), Some(NackType::UnexpectedRecipient(DRONE)));
+        assert!(!outputs.iter().any(|output| matches!(output, Output::Event(DroneEvent::PacketDropped(_)))));
+    }
+
+    #[test]
+    fn test_error_in_routing_before_drop() {
+        let model = ModelDrone::new(DRONE, &NEIGHBORS, 1.0);
+        let (_, outputs) = step(&model, &Input::Packet(fragment(&[1, DRONE, 21], 1)), 0.0);
+        assert_eq!(nack_type(&outputs), Some(NackType::ErrorInRouting(21)));
+        let (_, outputs) = step(&model, &Input::Packet(fragment(&[1, DRONE], 1)), 0.0);
+        assert_eq!(nack_type(&outputs), Some(NackType::DestinationIsDrone));
+    }
+
+    #[test]
+    fn test_drop_before_closed_channel() {
+        let model = ModelDrone::new(DRONE, &NEIGHBORS, 1.0);
+        let (_, outputs) = step(&model, &Input::Packet(fragment(&[1, DRONE, 14], 1)), 0.0);
+        assert_eq!(nack_type(&outputs), Some(NackType::Dropped));
+    }
+
+    #[test]
+    fn test_drone_follows_precedence() {
+        let mut differential = Differential::new(DRONE, &NEIGHBORS, 1.0, 0);
+        for packet in [fragment(&[1, 12, 21], 1), fragment(&[1, DRONE, 21], 1), fragment(&[1, DRONE], 1), fragment(&[1, DRONE, 14], 1)] {
+            if let Err(mismatch) = differential.apply(Input::Packet(packet)) {
+                panic!("{mismatch}");
+            }
+        }
+    }
+
+    fn input() -> impl Strategy<Value = Input> {
+        prop_oneof![
+            12 => packet().prop_map(Input::Packet),
+            1 => (node_id(), any::<bool>()).prop_map(|(id, live)| Input::AddSender(id, live)),
+            1 => node_id().prop_map(Input::RemoveSender),
+            1 => prop_oneof![pdr(), any::<f32>()].prop_map(Input::SetPdr),
+            1 => prop::collection::vec(packet(), 0..4).prop_map(Input::Crash),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn drone_matches_model(pdr in pdr(), seed in any::<u64>(), inputs in prop::collection::vec(input(), 0..40)) {
+            let mut differential = Differential::new(DRONE, &NEIGHBORS, pdr, seed);
+            for input in inputs {
+                if let Err(mismatch) = differential.apply(input) {
+                    prop_assert!(false, "{}", mismatch);
+                }
+            }
+        }
+    }
+}
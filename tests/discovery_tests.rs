@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod discovery_tests {
+    use std::collections::BTreeSet;
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+    use crossbeam_channel::unbounded;
+    use wg_2024::drone::Drone;
+    use wg_2024::packet::{FloodResponse, NodeType, Packet};
+    use drone_bettercalldrone::discovery::{NetworkGraph, TopologyChange, TopologyDiscovery};
+    use drone_bettercalldrone::testing::flood_response;
+    use drone_bettercalldrone::topology::{generators, GeneratorOptions};
+    use drone_bettercalldrone::BetterCallDrone;
+
+    const WINDOW: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn test_shortest_route_uses_only_drones() {
+        let mut graph = NetworkGraph::new();
+        graph.add_path(&[(1, NodeType::Client), (2, NodeType::Client), (21, NodeType::Server)]);
+        graph.add_path(&[(1, NodeType::Client), (11, NodeType::Drone), (12, NodeType::Drone), (13, NodeType::Drone), (21, NodeType::Server)]);
+        assert_eq!(graph.shortest_route(1, 21), Some(vec![1, 11, 12, 13, 21]));
+
+        graph.remove_link(12, 13);
+        assert_eq!(graph.shortest_route(1, 21), None);
+    }
+
+    #[test]
+    fn test_flood_lifecycle() {
+        let start = Instant::now();
+        let mut discovery = TopologyDiscovery::new(1, NodeType::Client, WINDOW);
+
+        let request = discovery.start_flood(start);
+        assert_eq!(request.path_trace, vec![(1, NodeType::Client)]);
+        assert!(discovery.is_flooding());
+
+        let response = FloodResponse {
+            flood_id: request.flood_id,
+            path_trace: vec![(1, NodeType::Client), (11, NodeType::Drone), (21, NodeType::Server)],
+        };
+        assert!(discovery.handle_response(&response));
+        assert!(!discovery.handle_response(&FloodResponse { flood_id: request.flood_id.wrapping_add(1000), ..response.clone() }));
+        assert_eq!(discovery.graph().shortest_route(1, 21), Some(vec![1, 11, 21]));
+
+        assert_eq!(discovery.poll(start + WINDOW / 2), None);
+        let change = discovery.poll(start + WINDOW).unwrap();
+        assert_eq!(change.added_nodes, BTreeSet::from([11, 21]));
+        assert_eq!(change.added_links, BTreeSet::from([(1, 11), (11, 21)]));
+        assert!(!discovery.is_flooding());
+
+        // Drone 11 disappeared, drone 12 took its place.
+        let second = discovery.start_flood(start + WINDOW * 2);
+        assert_ne!(second.flood_id, request.flood_id);
+        discovery.handle_response(&FloodResponse {
+            flood_id: second.flood_id,
+            path_trace: vec![(1, NodeType::Client), (12, NodeType::Drone), (21, NodeType::Server)],
+        });
+        let change = discovery.poll(start + WINDOW * 3).unwrap();
+        assert_eq!(change, TopologyChange {
+            added_nodes: BTreeSet::from([12]),
+            removed_nodes: BTreeSet::from([11]),
+            added_links: BTreeSet::from([(1, 12), (12, 21)]),
+            removed_links: BTreeSet::from([(1, 11), (11, 21)]),
+        });
+
+        discovery.start_flood(start + WINDOW * 4);
+        discovery.handle_response(&FloodResponse {
+            flood_id: second.flood_id + 1,
+            path_trace: vec![(1, NodeType::Client), (12, NodeType::Drone), (21, NodeType::Server)],
+        });
+        assert!(discovery.poll(start + WINDOW * 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_removed_link_survives_flood() {
+        let start = Instant::now();
+        let mut discovery = TopologyDiscovery::new(1, NodeType::Client, WINDOW);
+        let request = discovery.start_flood(start);
+        discovery.handle_response(&FloodResponse {
+            flood_id: request.flood_id,
+            path_trace: vec![(1, NodeType::Client), (11, NodeType::Drone), (12, NodeType::Drone), (21, NodeType::Server)],
+        });
+
+        discovery.remove_link(11, 12);
+        discovery.remove_node(21);
+        discovery.poll(start + WINDOW).unwrap();
+        assert_eq!(discovery.graph().links(), BTreeSet::from([(1, 11)]));
+        assert_eq!(discovery.graph().node_type(21), None);
+    }
+
+    #[test]
+    fn test_added_route_survives_flood() {
+        let start = Instant::now();
+        let mut discovery = TopologyDiscovery::new(1, NodeType::Client, WINDOW);
+        let path_trace = vec![(1, NodeType::Client), (11, NodeType::Drone), (12, NodeType::Drone), (21, NodeType::Server)];
+        let request = discovery.start_flood(start);
+        discovery.handle_response(&FloodResponse { flood_id: request.flood_id, path_trace: path_trace.clone() });
+        discovery.poll(start + WINDOW).unwrap();
+
+        // The route of an ack repaired by drone 11.
+        discovery.add_route(&[21, 13, 11, 1]);
+        let request = discovery.start_flood(start + WINDOW);
+        discovery.handle_response(&FloodResponse { flood_id: request.flood_id, path_trace: path_trace.clone() });
+        discovery.poll(start + WINDOW * 2).unwrap();
+        assert_eq!(discovery.graph().links(), BTreeSet::from([(1, 11), (11, 12), (11, 13), (12, 21), (13, 21)]));
+        assert_eq!(discovery.graph().node_type(13), Some(NodeType::Drone));
+        assert_eq!(discovery.graph().node_type(21), Some(NodeType::Server));
+
+        discovery.remove_link(11, 13);
+        let request = discovery.start_flood(start + WINDOW * 2);
+        discovery.handle_response(&FloodResponse { flood_id: request.flood_id, path_trace });
+        discovery.poll(start + WINDOW * 3).unwrap();
+        assert!(!discovery.graph().links().contains(&(11, 13)));
+        assert!(discovery.graph().links().contains(&(13, 21)));
+    }
+
+    #[test]
+    fn test_shortest_route_via_other_neighbors() {
+        let mut graph = NetworkGraph::new();
+        graph.add_path(&[(11, NodeType::Drone), (12, NodeType::Drone), (21, NodeType::Server)]);
+        graph.add_path(&[(11, NodeType::Drone), (13, NodeType::Drone), (14, NodeType::Drone), (21, NodeType::Server)]);
+
+        assert_eq!(graph.shortest_route(11, 21), Some(vec![11, 12, 21]));
+        assert_eq!(graph.shortest_route_via(11, [13], 21), Some(vec![11, 13, 14, 21]));
+        assert_eq!(graph.shortest_route_via(11, [], 21), None);
+    }
+
+    #[test]
+    fn test_graph_from_config() {
+        let graph = NetworkGraph::from_config(&generators::ring(3, &GeneratorOptions::default()));
+        assert_eq!(graph.node_type(1), Some(NodeType::Drone));
+        assert!(graph.links().contains(&(1, 2)));
+        assert!(graph.links().contains(&(1, 3)));
+    }
+
+    #[test]
+    fn test_drone_learns_relayed_responses() {
+        let (event_send, _events) = unbounded();
+        let (_, command_recv) = unbounded();
+        let (_, packet_recv) = unbounded();
+        let (client_send, client_recv) = unbounded::<Packet>();
+        let mut drone = BetterCallDrone::new(11, event_send, command_recv, packet_recv, HashMap::from([(1, client_send)]), 0.0);
+        let trace = [(1, NodeType::Client), (11, NodeType::Drone), (21, NodeType::Server)];
+
+        drone.handle_packet(flood_response(1, &trace));
+        let topology = drone.learn_topology();
+        assert_eq!(topology.snapshot(), NetworkGraph::new());
+
+        drone.handle_packet(flood_response(2, &trace));
+        assert_eq!(client_recv.try_iter().count(), 2);
+        assert_eq!(drone.learn_topology().snapshot().links(), BTreeSet::from([(1, 11), (11, 21)]));
+        assert_eq!(topology.snapshot().node_type(21), Some(NodeType::Server));
+
+        topology.set_learning(false);
+        let trace = [(1, NodeType::Client), (11, NodeType::Drone), (12, NodeType::Drone)];
+        drone.handle_packet(flood_response(3, &trace));
+        assert_eq!(client_recv.try_iter().count(), 1);
+        assert_eq!(topology.snapshot().links(), BTreeSet::from([(1, 11), (11, 21)]));
+    }
+}
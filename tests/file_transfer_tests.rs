@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod file_transfer_tests {
+    use std::time::Duration;
+    use crossbeam_channel::unbounded;
+    use wg_2024::network::NodeId;
+    use drone_bettercalldrone::client::ClientCommand;
+    use drone_bettercalldrone::file_transfer::{digest, run_transfer, FileMessage, FileServer};
+    use drone_bettercalldrone::topology::{generators, GeneratorOptions, Placement};
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    fn file(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let upload = FileMessage::upload("report.pdf", file(1000));
+        assert_eq!(FileMessage::decode(&upload.encode()), Ok(upload));
+        let stored = FileMessage::Stored { name: "report.pdf".to_string() };
+        assert_eq!(FileMessage::decode(&stored.encode()), Ok(stored));
+    }
+
+    #[test]
+    fn test_server_checks_digest() {
+        let (command_send, command_recv) = unbounded();
+        let (_event_send, event_recv) = unbounded();
+        let mut server = FileServer::new(21, command_send, event_recv);
+
+        server.handle_message(1, FileMessage::Upload { name: "bad".to_string(), digest: digest(b"other"), data: file(10) });
+        server.handle_message(1, FileMessage::upload("good", file(10)));
+
+        let replies: Vec<_> = command_recv.try_iter().map(|command| match command {
+            ClientCommand::SendMessage { destination, data } => (destination, FileMessage::decode(&data).unwrap()),
+            command => panic!("unexpected command {command:?}"),
+        }).collect();
+        assert_eq!(replies, vec![
+            (1, FileMessage::Corrupted { name: "bad".to_string() }),
+            (1, FileMessage::Stored { name: "good".to_string() }),
+        ]);
+        assert_eq!(server.files().keys().collect::<Vec<_>>(), vec!["good"]);
+    }
+
+    #[test]
+    fn test_transfer_with_crashes() {
+        let config = generators::grid(3, 3, &GeneratorOptions {
+            pdr: (0.05, 0.2),
+            clients: 1,
+            servers: 1,
+            placement: Placement::Ends,
+            seed: 36,
+        });
+        let endpoints: Vec<NodeId> = config.client.iter().flat_map(|c| c.connected_drone_ids.clone())
+            .chain(config.server.iter().flat_map(|s| s.connected_drone_ids.clone()))
+            .collect();
+        let crash = config.drone.iter().map(|d| d.id).find(|id| !endpoints.contains(id)).unwrap();
+
+        let report = run_transfer(config, file(4 << 20), &[crash], TIMEOUT).unwrap();
+        assert!(report.verified);
+        assert_eq!(report.bytes, 4 << 20);
+        assert_eq!(report.stats.fragments_sent, (4 << 20) / 128 + 1);
+        assert!(report.stats.retransmissions > 0);
+        assert!(report.throughput() > 0.0);
+    }
+}
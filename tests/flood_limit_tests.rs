@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod flood_limit_tests {
+    use std::collections::BTreeMap;
+    use std::time::{Duration, Instant};
+    use wg_2024::packet::NodeType;
+    use drone_bettercalldrone::flood_limit::{FloodLimitHandle, FloodPolicy, FloodPolicyError, ThrottleAction};
+    use drone_bettercalldrone::testing::{flood_request, flood_response, DroneFixture};
+    use drone_bettercalldrone::BetterCallDrone;
+
+    const C: NodeType = NodeType::Client;
+    const D: NodeType = NodeType::Drone;
+
+    fn policy(burst: u32, per_second: f64, action: ThrottleAction) -> Option<FloodPolicy> {
+        Some(FloodPolicy::new(burst, per_second, action).unwrap())
+    }
+
+    #[test]
+    fn test_invalid_policy() {
+        assert_eq!(FloodPolicy::new(0, 1.0, ThrottleAction::Drop), Err(FloodPolicyError::ZeroBurst));
+        assert_eq!(FloodPolicy::new(1, -1.0, ThrottleAction::Drop), Err(FloodPolicyError::InvalidRate(-1.0)));
+        assert!(FloodPolicy::new(1, f64::INFINITY, ThrottleAction::Drop).is_err());
+    }
+
+    #[test]
+    fn test_no_policy_admits_everything() {
+        let limit = FloodLimitHandle::new();
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert_eq!(limit.throttle(1, now), None);
+        }
+        assert_eq!(limit.stats().admitted, 100);
+        assert_eq!(limit.stats().total_throttled(), 0);
+    }
+
+    #[test]
+    fn test_bucket_refills() {
+        let limit = FloodLimitHandle::new();
+        limit.set_policy(policy(2, 4.0, ThrottleAction::Drop));
+        let start = Instant::now();
+
+        assert_eq!(limit.throttle(1, start), None);
+        assert_eq!(limit.throttle(1, start), None);
+        assert_eq!(limit.throttle(1, start), Some(ThrottleAction::Drop));
+        assert_eq!(limit.throttle(1, start + Duration::from_millis(200)), Some(ThrottleAction::Drop));
+        assert_eq!(limit.throttle(1, start + Duration::from_millis(250)), None);
+        // The bucket never holds more than the burst.
+        let later = start + Duration::from_secs(60);
+        assert_eq!(limit.throttle(1, later), None);
+        assert_eq!(limit.throttle(1, later), None);
+        assert_eq!(limit.throttle(1, later), Some(ThrottleAction::Drop));
+    }
+
+    #[test]
+    fn test_buckets_are_per_initiator() {
+        let limit = FloodLimitHandle::new();
+        limit.set_policy(policy(1, 0.0, ThrottleAction::Respond));
+        let now = Instant::now();
+
+        assert_eq!(limit.throttle(1, now), None);
+        assert_eq!(limit.throttle(1, now), Some(ThrottleAction::Respond));
+        assert_eq!(limit.throttle(2, now), None);
+        assert_eq!(limit.throttle(1, now), Some(ThrottleAction::Respond));
+
+        let stats = limit.stats();
+        assert_eq!(stats.admitted, 2);
+        assert_eq!(stats.throttled, BTreeMap::from([(1, 2)]));
+
+        limit.set_policy(policy(1, 0.0, ThrottleAction::Respond));
+        assert_eq!(limit.throttle(1, now), None);
+    }
+
+    fn limited_drone(action: ThrottleAction) -> (DroneFixture, FloodLimitHandle) {
+        let (mut fixture, mut drone) = DroneFixture::build::<BetterCallDrone>(11, &[1, 12], 0.0);
+        let limit = drone.limit_floods();
+        limit.set_policy(policy(2, 0.0, action));
+        fixture.start(drone);
+        (fixture, limit)
+    }
+
+    #[test]
+    fn test_drone_drops_flood_storm() {
+        let (fixture, limit) = limited_drone(ThrottleAction::Drop);
+
+        for flood_id in 0..5 {
+            fixture.send(&flood_request(flood_id, &[(1, C)]));
+        }
+        fixture.assert_packet(12, &flood_request(0, &[(1, C), (11, D)]));
+        fixture.assert_packet(12, &flood_request(1, &[(1, C), (11, D)]));
+        fixture.assert_no_packet(12);
+        fixture.assert_no_packet(1);
+        assert_eq!(limit.stats().throttled, BTreeMap::from([(1, 3)]));
+    }
+
+    #[test]
+    fn test_drone_answers_flood_storm() {
+        let (fixture, limit) = limited_drone(ThrottleAction::Respond);
+
+        for flood_id in 0..3 {
+            fixture.send(&flood_request(flood_id, &[(1, C)]));
+        }
+        fixture.assert_packet(12, &flood_request(0, &[(1, C), (11, D)]));
+        fixture.assert_packet(12, &flood_request(1, &[(1, C), (11, D)]));
+        fixture.assert_packet(1, &flood_response(2, &[(1, C), (11, D)]));
+        assert_eq!(limit.stats().total_throttled(), 1);
+    }
+
+    #[test]
+    fn test_duplicates_of_dropped_flood_are_dropped() {
+        let (fixture, limit) = limited_drone(ThrottleAction::Drop);
+        fixture.send(&flood_request(0, &[(1, C)]));
+        fixture.send(&flood_request(1, &[(1, C)]));
+        fixture.assert_packet(12, &flood_request(0, &[(1, C), (11, D)]));
+        fixture.assert_packet(12, &flood_request(1, &[(1, C), (11, D)]));
+
+        // Flood 2 is over the limit; the copy relayed by drone 12 must not be answered either.
+        fixture.send(&flood_request(2, &[(1, C)]));
+        fixture.send(&flood_request(2, &[(1, C), (12, D)]));
+        fixture.assert_no_packet(1);
+        fixture.assert_no_packet(12);
+        assert_eq!(limit.stats().throttled, BTreeMap::from([(1, 1)]));
+    }
+
+    #[test]
+    fn test_duplicates_do_not_take_tokens() {
+        let (fixture, limit) = limited_drone(ThrottleAction::Drop);
+        let request = flood_request(7, &[(1, C)]);
+
+        fixture.send(&request);
+        fixture.send(&request);
+        fixture.send(&request);
+        fixture.assert_packet(12, &flood_request(7, &[(1, C), (11, D)]));
+        fixture.assert_packet(1, &flood_response(7, &[(1, C), (11, D)]));
+        fixture.assert_packet(1, &flood_response(7, &[(1, C), (11, D)]));
+        fixture.send(&flood_request(8, &[(1, C)]));
+        fixture.assert_packet(12, &flood_request(8, &[(1, C), (11, D)]));
+        assert_eq!(limit.stats().admitted, 2);
+        assert_eq!(limit.stats().total_throttled(), 0);
+    }
+}
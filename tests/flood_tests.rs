@@ -1,351 +1,223 @@
 #[cfg(test)]
 mod flood_tests {
-    use std::collections::HashMap;
+    use std::collections::{BTreeSet, HashSet};
     use std::thread;
-    use crossbeam_channel::unbounded;
-    use wg_2024::drone::Drone;
-    use wg_2024::network::{NodeId, SourceRoutingHeader};
-    use wg_2024::packet::{FloodRequest, NodeType, Packet, PacketType};
-    use drone_bettercalldrone::BetterCallDrone;
-
-    fn create_msg(id: NodeId, fid: u64) -> Packet {
-        Packet {
-            pack_type: PacketType::FloodRequest(FloodRequest {
-                flood_id: fid,
-                initiator_id: id,
-                path_trace: vec![(id, NodeType::Client)],
-            }),
-            routing_header: SourceRoutingHeader {
-                hop_index: 0,
-                hops: vec![],
-            },
-            session_id: 4,
-        }
+    use std::time::{Duration, Instant};
+    use crossbeam_channel::{unbounded, Sender};
+    use wg_2024::config::{Client, Config, Drone, Server};
+    use wg_2024::network::NodeId;
+    use wg_2024::packet::{FloodResponse, NodeType, Packet, PacketType};
+    use drone_bettercalldrone::server::{Server as ServerNode, ServerCommand};
+    use drone_bettercalldrone::testing::{flood_request, route, NetworkFixture, TIMEOUT};
+    use drone_bettercalldrone::topology::{generators, GeneratorOptions, Placement};
+
+    fn drone(id: NodeId, neighbors: &[NodeId]) -> Drone {
+        Drone { id, connected_node_ids: neighbors.to_vec(), pdr: 0.0 }
     }
 
-    #[test]
-    fn test_flood_straight() {
-        let (c_send, c_recv) = unbounded();
-        let (d1_send, d1_recv) = unbounded();
-        let (d2_send, d2_recv) = unbounded();
-        let (d3_send, d3_recv) = unbounded();
-        let (s_send, s_recv) = unbounded();
-        let (_d_comm_send, d_comm_recv) = unbounded();
-        let (d_events_send, _d_events_recv) = unbounded();
-
-        let mut drone1 = BetterCallDrone::new(
-            11,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d1_recv,
-            HashMap::from([(1, c_send.clone()), (12, d2_send.clone())]),
-            0.0,
-        );
-
-        let mut drone2 = BetterCallDrone::new(
-            12,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d2_recv,
-            HashMap::from([(11, d1_send.clone()), (13, d3_send.clone())]),
-            0.0,
-        );
-
-        let mut drone3 = BetterCallDrone::new(
-            13,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d3_recv,
-            HashMap::from([(12, d2_send.clone()), (21, s_send.clone())]),
-            0.0,
-        );
-
-        let mut server = BetterCallDrone::new(
-            21,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            s_recv,
-            HashMap::from([(13, d3_send.clone())]),
-            0.0,
-        );
-
-        thread::spawn(move || {
-            drone1.run();
-        });
-        thread::spawn(move || {
-            drone2.run();
-        });
-        thread::spawn(move || {
-            drone3.run();
-        });
-        thread::spawn(move || {
-            server.run();
-        });
+    fn client(id: NodeId, drones: &[NodeId]) -> Client {
+        Client { id, connected_drone_ids: drones.to_vec() }
+    }
 
-        let msg = create_msg(1, 777);
+    fn server(id: NodeId, drones: &[NodeId]) -> Server {
+        Server { id, connected_drone_ids: drones.to_vec() }
+    }
 
-        d1_send.send(msg).unwrap();
+    /// Runs a real server on the channels of `id`, until the returned sender is dropped.
+    fn spawn_server(network: &mut NetworkFixture, id: NodeId) -> Sender<ServerCommand> {
+        let endpoint = network.take_endpoint(id);
+        let (command_send, command_recv) = unbounded();
+        let (event_send, _event_recv) = unbounded();
+        let mut server = ServerNode::new(id, event_send, command_recv, endpoint.packet_recv, endpoint.packet_send);
+        thread::spawn(move || server.run());
+        command_send
+    }
 
-        if let Ok(packet) = c_recv.recv_timeout(std::time::Duration::from_secs(1)) {
-            match packet.pack_type {
-                PacketType::FloodResponse(response) => {
-                    assert_eq!(response.flood_id, 777);
-                    assert_eq!(response.path_trace, vec![(1, NodeType::Client), (11, NodeType::Drone), (12, NodeType::Drone), (13, NodeType::Drone), (21, NodeType::Drone)]);
-                }
-                _ => panic!("Unexpected packet: {:?}", packet.pack_type),
-            }
-        } else {
-            panic!("Timeout: no packet received");
+    /// Returns the path traces of the flood responses received by a client until it is silent.
+    fn responses(network: &NetworkFixture, client: NodeId, flood_id: u64) -> Vec<Vec<NodeId>> {
+        let mut traces = Vec::new();
+        while let Ok(packet) = network.endpoint(client).recv_timeout(TIMEOUT) {
+            let PacketType::FloodResponse(response) = packet.pack_type else {
+                panic!("Unexpected packet: {:?}", packet.pack_type);
+            };
+            assert_eq!(response.flood_id, flood_id);
+            traces.push(response.path_trace.iter().map(|(id, _)| *id).collect());
         }
+        traces
     }
 
     #[test]
-    fn test_flood_2branch() {
-        let (c_send, c_recv) = unbounded();
-        let (d1_send, d1_recv) = unbounded();
-        let (d2_send, d2_recv) = unbounded();
-        let (d3_send, d3_recv) = unbounded();
-        let (d4_send, d4_recv) = unbounded();
-        let (s_send, s_recv) = unbounded();
-        let (_d_comm_send, d_comm_recv) = unbounded();
-        let (d_events_send, _d_events_recv) = unbounded();
-
-        let mut drone1 = BetterCallDrone::new(
-            11,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d1_recv,
-            HashMap::from([(1, c_send.clone()), (12, d2_send.clone())]),
-            0.0,
-        );
-
-        let mut drone2 = BetterCallDrone::new(
-            12,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d2_recv,
-            HashMap::from([(11, d1_send.clone()), (13, d3_send.clone()), (14, d4_send.clone())]),
-            0.0,
-        );
-
-        let mut drone3 = BetterCallDrone::new(
-            13,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d3_recv,
-            HashMap::from([(12, d2_send.clone()), (21, s_send.clone())]),
-            0.0,
-        );
+    fn test_flood_straight() {
+        let config = Config {
+            drone: vec![drone(11, &[1, 12]), drone(12, &[11, 13]), drone(13, &[12, 21])],
+            client: vec![client(1, &[11])],
+            server: vec![server(21, &[13])],
+        };
+        let mut network = NetworkFixture::spawn(&config);
+        let _server = spawn_server(&mut network, 21);
 
-        let mut drone4 = BetterCallDrone::new(
-            14,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d4_recv,
-            HashMap::from([(12, d2_send.clone()), (21, s_send.clone())]),
-            0.0,
-        );
+        network.send(11, &flood_request(777, &[(1, NodeType::Client)]));
 
-        let mut server = BetterCallDrone::new(
-            21,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            s_recv,
-            HashMap::from([(13, d3_send.clone()), (14, d4_send.clone())]),
-            0.0,
+        let path_trace = vec![(1, NodeType::Client), (11, NodeType::Drone), (12, NodeType::Drone), (13, NodeType::Drone), (21, NodeType::Server)];
+        assert_eq!(
+            network.endpoint(1).recv_timeout(TIMEOUT).expect("Timeout: no packet received"),
+            Packet::new_flood_response(route(4, &[21, 13, 12, 11, 1]), 1, FloodResponse { flood_id: 777, path_trace })
         );
+    }
 
-        thread::spawn(move || {
-            drone1.run();
-        });
-        thread::spawn(move || {
-            drone2.run();
-        });
-        thread::spawn(move || {
-            drone3.run();
-        });
-        thread::spawn(move || {
-            drone4.run();
-        });
-        thread::spawn(move || {
-            server.run();
-        });
-
-        let msg = create_msg(1, 777);
-
-        d1_send.send(msg).unwrap();
+    #[test]
+    fn test_flood_2branch() {
+        let config = Config {
+            drone: vec![drone(11, &[1, 12]), drone(12, &[11, 13, 14]), drone(13, &[12, 21]), drone(14, &[12, 21])],
+            client: vec![client(1, &[11])],
+            server: vec![server(21, &[13, 14])],
+        };
+        let mut network = NetworkFixture::spawn(&config);
+        let _server = spawn_server(&mut network, 21);
+
+        network.send(11, &flood_request(777, &[(1, NodeType::Client)]));
+
+        let traces: BTreeSet<Vec<NodeId>> = responses(&network, 1, 777).into_iter().collect();
+        assert_eq!(traces, BTreeSet::from([vec![1, 11, 12, 13, 21], vec![1, 11, 12, 14, 21]]));
+    }
 
-        for _ in 0..2 {
-            if let Ok(packet) = c_recv.recv_timeout(std::time::Duration::from_secs(1)) {
+    #[test]
+    fn test_flood_double_chain() {
+        let config = Config {
+            drone: vec![
+                drone(11, &[1, 12, 14]),
+                drone(12, &[11, 13, 15]),
+                drone(13, &[12, 21, 16]),
+                drone(14, &[2, 15, 11]),
+                drone(15, &[14, 16, 12]),
+                drone(16, &[15, 13, 22]),
+            ],
+            client: vec![client(1, &[11]), client(2, &[14])],
+            server: vec![server(21, &[13]), server(22, &[16])],
+        };
+        let mut network = NetworkFixture::spawn(&config);
+        let _servers = [spawn_server(&mut network, 21), spawn_server(&mut network, 22)];
+
+        network.send(11, &flood_request(777, &[(1, NodeType::Client)]));
+        network.send(14, &flood_request(555, &[(2, NodeType::Client)]));
+
+        for (client, drone, flood_id) in [(1, 11, 777), (2, 14, 555)] {
+            let mut responses = 0;
+            while let Ok(packet) = network.endpoint(client).recv_timeout(TIMEOUT) {
                 match packet.pack_type {
                     PacketType::FloodResponse(response) => {
-                        assert_eq!(response.flood_id, 777);
+                        assert_eq!(response.flood_id, flood_id);
+                        responses += 1;
                     }
+                    PacketType::FloodRequest(request) => network.send(drone, &request.generate_response(4)),
                     _ => panic!("Unexpected packet: {:?}", packet.pack_type),
                 }
-            } else {
-                panic!("Timeout: no packet received");
             }
+            assert!(responses > 0, "client {client} received no response");
         }
     }
 
-    #[test]
-    fn test_flood_double_chain() {
-        let (c1_send, c1_recv) = unbounded();
-        let (c2_send, c2_recv) = unbounded();
-        let (d1_send, d1_recv) = unbounded();
-        let (d2_send, d2_recv) = unbounded();
-        let (d3_send, d3_recv) = unbounded();
-        let (d4_send, d4_recv) = unbounded();
-        let (d5_send, d5_recv) = unbounded();
-        let (d6_send, d6_recv) = unbounded();
-        let (s1_send, s1_recv) = unbounded();
-        let (s2_send, s2_recv) = unbounded();
-        let (_d_comm_send, d_comm_recv) = unbounded();
-        let (d_events_send, _d_events_recv) = unbounded();
-
-        let mut drone1 = BetterCallDrone::new(
-            11,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d1_recv,
-            HashMap::from([(1, c1_send.clone()), (12, d2_send.clone()), (14, d4_send.clone())]),
-            0.0,
-        );
-
-        let mut drone2 = BetterCallDrone::new(
-            12,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d2_recv,
-            HashMap::from([(11, d1_send.clone()), (13, d3_send.clone()), (15, d5_send.clone())]),
-            0.0,
-        );
-
-        let mut drone3 = BetterCallDrone::new(
-            13,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d3_recv,
-            HashMap::from([(12, d2_send.clone()), (21, s1_send.clone()), (16, d6_send.clone())]),
-            0.0,
-        );
-
-        let mut drone4 = BetterCallDrone::new(
-            14,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d4_recv,
-            HashMap::from([(2, c2_send.clone()), (15, d5_send.clone()), (11, d1_send.clone())]),
-            0.0,
-        );
-
-        let mut drone5 = BetterCallDrone::new(
-            15,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d5_recv,
-            HashMap::from([(14, d4_send.clone()), (16, d6_send.clone()), (12, d2_send.clone())]),
-            0.0,
-        );
-
-        let mut drone6 = BetterCallDrone::new(
-            16,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            d6_recv,
-            HashMap::from([(15, d5_send.clone()), (13, d3_send.clone()), (22, s2_send.clone())]),
-            0.0,
-        );
-
-        let mut server1 = BetterCallDrone::new(
-            21,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            s1_recv,
-            HashMap::from([(13, d3_send.clone())]),
-            0.0,
-        );
-
-        let mut server2 = BetterCallDrone::new(
-            22,
-            d_events_send.clone(),
-            d_comm_recv.clone(),
-            s2_recv,
-            HashMap::from([(16, d3_send.clone())]),
-            0.0,
-        );
-
-        thread::spawn(move || {
-            drone1.run();
-        });
-        thread::spawn(move || {
-            drone2.run();
-        });
-        thread::spawn(move || {
-            drone3.run();
-        });
-        thread::spawn(move || {
-            drone4.run();
-        });
-        thread::spawn(move || {
-            drone5.run();
-        });
-        thread::spawn(move || {
-            drone6.run();
-        });
-        thread::spawn(move || {
-            server1.run();
-        });
-        thread::spawn(move || {
-            server2.run();
-        });
-
-        let msg1 = create_msg(1, 777);
-        let msg2 = create_msg(2, 555);
-
-        d1_send.send(msg1).unwrap();
-        d4_send.send(msg2).unwrap();
-
-        let mut c1_vec = Vec::new();
-        loop {
-            match c1_recv.recv_timeout(std::time::Duration::from_secs(1)) {
-                Ok(packet) => {
-                match packet.pack_type {
-                    PacketType::FloodResponse(response) => {
-                        c1_vec.push(response.flood_id);
-                    },
-                    PacketType::FloodRequest(request) => {
-                        let packet = request.generate_response(4);
-                        d1_send.send(packet).unwrap();
-                    },
-                    _ => panic!("Unexpected packet: {:?}", packet.pack_type),
-                }
-                }
-                Err(_) => break,
-            }
+    /// Runs the drones of the configuration, floods from the first client and returns the ids of
+    /// the drones that appear in the collected responses, as soon as every drone has answered or
+    /// after the network has been silent for a while.
+    fn flood_generated(config: &Config) -> HashSet<NodeId> {
+        let network = NetworkFixture::spawn(config);
+        let client = &config.client[0];
+        for &drone_id in &client.connected_drone_ids {
+            network.send(drone_id, &flood_request(777, &[(client.id, NodeType::Client)]));
         }
 
-        let mut c2_vec = Vec::new();
-        loop {
-            match c2_recv.recv_timeout(std::time::Duration::from_secs(1)) {
-                Ok(packet) => {
+        let endpoints: Vec<(NodeId, NodeType)> = config.client.iter().map(|c| (c.id, NodeType::Client))
+            .chain(config.server.iter().map(|s| (s.id, NodeType::Server)))
+            .collect();
+        let mut reached = HashSet::new();
+        let mut last_packet = Instant::now();
+        while reached.len() < config.drone.len() && last_packet.elapsed() < Duration::from_millis(300) {
+            for (id, node_type) in &endpoints {
+                while let Ok(packet) = network.endpoint(*id).try_recv() {
+                    last_packet = Instant::now();
                     match packet.pack_type {
+                        PacketType::FloodRequest(mut request) => {
+                            request.increment(*id, *node_type);
+                            let mut response = request.generate_response(4);
+                            response.routing_header.hop_index = 1;
+                            network.send(response.routing_header.hops[1], &response);
+                        }
                         PacketType::FloodResponse(response) => {
-                            c2_vec.push(response.flood_id);
-                        },
-                        PacketType::FloodRequest(request) => {
-                            let packet = request.generate_response(4);
-                            d4_send.send(packet).unwrap();
-                        },
+                            assert_eq!(*id, client.id);
+                            assert_eq!(response.flood_id, 777);
+                            reached.extend(response.path_trace.iter()
+                                .filter(|(_, node_type)| *node_type == NodeType::Drone)
+                                .map(|(node_id, _)| *node_id));
+                        }
                         _ => panic!("Unexpected packet: {:?}", packet.pack_type),
                     }
                 }
-                Err(_) => break,
             }
+            thread::sleep(Duration::from_millis(1));
         }
+        reached
+    }
+
+    /// Every generator, with several sizes each.
+    fn shapes(options: &GeneratorOptions) -> Vec<Config> {
+        let mut configs = Vec::new();
+        for n in [2, 3, 5, 8, 13] {
+            configs.push(generators::line(n, options));
+            configs.push(generators::ring(n, options));
+            configs.push(generators::star(n, options));
+        }
+        for (rows, cols) in [(1, 2), (2, 2), (2, 3), (3, 4)] {
+            configs.push(generators::grid(rows, cols, options));
+        }
+        for length in [1, 2, 4, 6] {
+            configs.push(generators::double_chain(length, options));
+        }
+        for dimension in 1..=3 {
+            configs.push(generators::butterfly(dimension, options));
+        }
+        for (depth, branching) in [(1, 2), (2, 2), (2, 3), (3, 2)] {
+            configs.push(generators::tree(depth, branching, options));
+        }
+        for n in [5, 10, 20] {
+            for p in [0.1, 0.3] {
+                configs.push(generators::erdos_renyi(n, p, options));
+            }
+            for m in 1..=3 {
+                configs.push(generators::scale_free(n, m, options));
+            }
+        }
+        configs
+    }
+
+    /// Floods every shape of [`shapes`] for each seed and placement.
+    ///
+    /// # Returns
+    /// How many networks were flooded.
+    fn flood_shapes(seeds: &[u64], placements: &[Placement]) -> usize {
+        let mut tested = 0;
+        for &seed in seeds {
+            for &placement in placements {
+                let options = GeneratorOptions { clients: 2, servers: 2, placement, seed, ..GeneratorOptions::default() };
+                for config in shapes(&options) {
+                    let drones: HashSet<NodeId> = config.drone.iter().map(|d| d.id).collect();
+                    assert_eq!(flood_generated(&config), drones, "{config:?}");
+                    tested += 1;
+                }
+            }
+        }
+        tested
+    }
 
-        assert!(c1_vec.iter().all(|&flood_id| flood_id == 777));
-        assert!(c2_vec.iter().all(|&flood_id| flood_id == 555));
+    #[test]
+    fn test_flood_generated_topologies() {
+        assert_eq!(flood_shapes(&[0], &[Placement::Random]) + flood_shapes(&[1], &[Placement::Ends]), 90);
+    }
+
+    /// Every shape with four seeds and both placements, run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore = "starts 360 networks"]
+    fn test_flood_generated_topologies_exhaustive() {
+        assert_eq!(flood_shapes(&[0, 1, 2, 3], &[Placement::Random, Placement::Ends]), 360);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,23 @@
+#[cfg(test)]
+mod fuzz_tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use drone_bettercalldrone::testing::fuzz::{decode, drive, run};
+
+    /// Runs the fuzz harnesses on random inputs, as a smoke test of the harnesses themselves.
+    #[test]
+    fn test_random_inputs() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..200 {
+            let len = rng.gen_range(0..2048);
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            drive(decode(&data));
+            run(decode(&data), rng.gen());
+        }
+    }
+
+    #[test]
+    fn test_run_stops_when_channels_disconnect() {
+        run(Vec::new(), true);
+    }
+}
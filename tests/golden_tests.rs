@@ -0,0 +1,32 @@
+#[cfg(test)]
+mod golden_tests {
+    use std::fs;
+    use drone_bettercalldrone::testing::scenario::Scenario;
+    use drone_bettercalldrone::testing::trace::{check_golden, record};
+
+    /// Compares every scenario in `tests/scenarios` with its golden trace.
+    /// Run with `BCD_UPDATE_GOLDEN=1` to rewrite the traces.
+    #[test]
+    fn test_golden_traces() {
+        let mut paths: Vec<_> = fs::read_dir("tests/scenarios").unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+        let failures: Vec<String> = paths.iter()
+            .filter_map(|path| check_golden(path).err().map(|e| format!("{}: {e}", path.display())))
+            .collect();
+        assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+    }
+
+    #[test]
+    fn test_trace_is_deterministic() {
+        let mut scenario = Scenario::load("tests/scenarios/lossy.toml").unwrap();
+        let trace = record(&scenario);
+        assert_eq!(record(&scenario), trace);
+        assert!(trace.contains("PacketDropped"), "{trace}");
+
+        scenario.seed += 1;
+        assert_ne!(record(&scenario), trace);
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod matchers_tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use wg_2024::packet::{NackType, NodeType};
+    use drone_bettercalldrone::testing::matchers::{EventMatcher, PacketMatcher};
+    use drone_bettercalldrone::testing::{flood_request, fragment, DroneFixture};
+
+    fn panic_message(f: impl FnOnce()) -> String {
+        let error = panic::catch_unwind(AssertUnwindSafe(f)).expect_err("the expectations should fail");
+        error.downcast_ref::<String>().cloned().unwrap_or_default()
+    }
+
+    #[test]
+    fn test_mismatched_session_var() {
+        let drone = DroneFixture::spawn(11, &[1, 12], 1.0);
+        let mut packet = fragment(1, &[1, 11, 12]);
+        for session_id in [77, 78] {
+            packet.session_id = session_id;
+            drone.send(&packet);
+        }
+
+        let dropped = || PacketMatcher::nack().nack_type(NackType::Dropped).session_var("s");
+        let message = panic_message(|| {
+            drone.expect()
+                .neighbor(1, dropped())
+                .neighbor(1, dropped())
+                .verify();
+        });
+        assert!(message.starts_with("expectation 2 of 2 not met"), "{message}");
+        assert!(message.contains("✓ node 1 receives Nack, type Dropped, session $s"), "{message}");
+        assert!(message.contains("session_id ($s): expected 77, got 78"), "{message}");
+    }
+
+    #[test]
+    fn test_flood_forwarded_to_neighbors() {
+        let drone = DroneFixture::spawn(11, &[1, 12, 13], 0.0);
+        drone.send(&flood_request(5, &[(1, NodeType::Client)]));
+
+        let forwarded = || PacketMatcher::flood_request()
+            .flood_id(5)
+            .path_trace(&[(1, NodeType::Client), (11, NodeType::Drone)]);
+        drone.expect()
+            .neighbor(12, forwarded())
+            .neighbor(13, forwarded())
+            .verify();
+    }
+
+    #[test]
+    fn test_failure_report() {
+        let drone = DroneFixture::spawn(11, &[1], 0.0);
+        drone.send(&fragment(1, &[1, 11, 12]));
+
+        let message = panic_message(|| {
+            drone.expect()
+                .neighbor(1, PacketMatcher::nack().nack_type(NackType::Dropped).fragment_index(1))
+                .controller(EventMatcher::packet_dropped(PacketMatcher::any()))
+                .verify();
+        });
+        assert!(message.starts_with("expectation 1 of 2 not met"), "{message}");
+        assert!(message.contains("✗ node 1 receives Nack, type Dropped, fragment 1"), "{message}");
+        assert!(message.contains("nack_type: expected Dropped, got ErrorInRouting(12)"), "{message}");
+        assert!(!message.contains("fragment_index:"), "{message}");
+        assert!(message.contains("received: (1:1) Nack(ErrorInRouting(12)) route [11, 1]@1"), "{message}");
+        assert!(message.contains("(1 more expectations not checked)"), "{message}");
+    }
+
+    #[test]
+    fn test_skip_unmatched() {
+        let drone = DroneFixture::spawn(11, &[1], 0.0);
+        drone.send(&fragment(1, &[1, 11]));
+        drone.send(&fragment(1, &[1, 11, 13]));
+
+        drone.expect()
+            .skip_unmatched()
+            .neighbor(1, PacketMatcher::nack().nack_type(NackType::ErrorInRouting(13)))
+            .verify();
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod monitor_tests {
+    use wg_2024::controller::DroneEvent;
+    use wg_2024::network::SourceRoutingHeader;
+    use wg_2024::packet::{Fragment, Nack, NackType, Packet};
+    use drone_bettercalldrone::monitor::{NetworkMonitor, LOG_CAPACITY};
+    use drone_bettercalldrone::topology::{generators, GeneratorOptions};
+
+    fn fragment(hop_index: usize) -> Packet {
+        Packet::new_fragment(
+            SourceRoutingHeader { hop_index, hops: vec![6, 1, 2, 3] },
+            1,
+            Fragment { fragment_index: 0, total_n_fragments: 1, length: 128, data: [1; 128] },
+        )
+    }
+
+    #[test]
+    fn test_counters() {
+        let config = generators::ring(5, &GeneratorOptions::default());
+        let mut monitor = NetworkMonitor::new(&config);
+
+        monitor.record(&DroneEvent::PacketSent(fragment(2)));
+        monitor.record(&DroneEvent::PacketSent(fragment(3)));
+        monitor.record(&DroneEvent::PacketDropped(fragment(2)));
+        monitor.record(&DroneEvent::PacketSent(Packet::new_nack(
+            SourceRoutingHeader { hop_index: 1, hops: vec![2, 1, 6] },
+            1,
+            Nack { fragment_index: 0, nack_type: NackType::Dropped },
+        )));
+
+        let drones = monitor.drones();
+        assert_eq!((drones[&1].sent, drones[&1].nacks), (1, 0));
+        assert_eq!((drones[&2].sent, drones[&2].dropped, drones[&2].nacks), (2, 1, 2));
+        assert_eq!(drones[&1].neighbors, vec![2, 5, 6]);
+        assert_eq!(monitor.log().len(), 4);
+    }
+
+    #[test]
+    fn test_shortcuts_are_not_charged_to_a_drone() {
+        let config = generators::ring(5, &GeneratorOptions::default());
+        let mut monitor = NetworkMonitor::new(&config);
+
+        // A nack that reached drone 3 although its current hop is 2, as after `UnexpectedRecipient`.
+        monitor.record(&DroneEvent::ControllerShortcut(Packet::new_nack(
+            SourceRoutingHeader { hop_index: 1, hops: vec![1, 2, 6] },
+            1,
+            Nack { fragment_index: 0, nack_type: NackType::Dropped },
+        )));
+
+        assert_eq!(monitor.shortcuts(), 1);
+        assert!(monitor.drones().values().all(|stats| stats.nacks == 0 && stats.sent == 0));
+        assert_eq!(monitor.log().len(), 1);
+    }
+
+    #[test]
+    fn test_topology_update_and_log_capacity() {
+        let mut config = generators::ring(5, &GeneratorOptions::default());
+        let mut monitor = NetworkMonitor::new(&config);
+        config.drone.retain(|d| d.id != 3);
+        config.drone[0].pdr = 0.5;
+        monitor.update_topology(&config);
+        assert!(!monitor.drones().contains_key(&3));
+        assert_eq!(monitor.drones()[&1].pdr, 0.5);
+
+        for i in 0..LOG_CAPACITY + 10 {
+            monitor.push_log(i.to_string());
+        }
+        assert_eq!(monitor.log().len(), LOG_CAPACITY);
+        assert_eq!(monitor.log().front().unwrap(), "10");
+    }
+}
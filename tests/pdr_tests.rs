@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod pdr_tests {
+    use drone_bettercalldrone::testing::pdr::DropSample;
+    use drone_bettercalldrone::BetterCallDrone;
+
+    /// Fragments of the checks of PDR 0 and 1, where a single wrong decision fails.
+    const EXTREME_FRAGMENTS: u64 = 200_000;
+    /// Fragments of the statistical checks.
+    const FRAGMENTS: u64 = 20_000;
+    /// The largest accepted z-score.
+    const MAX_Z: f64 = 4.0;
+
+    #[test]
+    fn test_pdr_zero_never_drops() {
+        for seed in 0..5 {
+            let sample = DropSample::measure(0.0, EXTREME_FRAGMENTS, seed);
+            assert_eq!(sample.dropped, 0, "{sample}");
+        }
+    }
+
+    #[test]
+    fn test_pdr_one_always_drops() {
+        for seed in 0..5 {
+            let sample = DropSample::measure(1.0, EXTREME_FRAGMENTS, seed);
+            assert_eq!(sample.dropped, sample.fragments, "{sample}");
+        }
+    }
+
+    #[test]
+    fn test_drop_fraction_matches_pdr() {
+        for (seed, pdr) in [0.01, 0.1, 0.25, 0.5, 0.75, 0.9, 0.99].into_iter().enumerate() {
+            let sample = DropSample::measure(pdr, FRAGMENTS, seed as u64);
+            assert!(sample.is_consistent(MAX_Z), "{sample}");
+        }
+    }
+
+    #[test]
+    fn test_wrong_pdr_is_detected() {
+        let sample = DropSample { pdr: 0.5, fragments: FRAGMENTS, dropped: FRAGMENTS / 2 + 500 };
+        assert!(!sample.is_consistent(MAX_Z), "{sample}");
+        let sample = DropSample { pdr: 0.0, fragments: FRAGMENTS, dropped: 1 };
+        assert!(!sample.is_consistent(MAX_Z), "{sample}");
+    }
+
+    #[test]
+    fn test_drop_boundaries() {
+        let below_one = 1.0 - f32::EPSILON / 2.0;
+        for pdr in [0.0, f32::MIN_POSITIVE, 0.25, 0.5, below_one, 1.0] {
+            assert_eq!(BetterCallDrone::is_drop(0.0, pdr), pdr > 0.0, "draw 0 with PDR {pdr}");
+            assert_eq!(BetterCallDrone::is_drop(below_one, pdr), pdr >= 1.0, "largest draw with PDR {pdr}");
+            assert!(!BetterCallDrone::is_drop(pdr, pdr), "draw equal to PDR {pdr}");
+        }
+    }
+}
@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod property_tests {
+    use proptest::prelude::*;
+    use wg_2024::controller::{DroneCommand, DroneEvent};
+    use wg_2024::network::{NodeId, SourceRoutingHeader};
+    use wg_2024::packet::{NackType, Packet, PacketType};
+    use drone_bettercalldrone::testing::strategies::{fragment, header, node_id, packet, pdr, DRONE, NEIGHBORS};
+    use drone_bettercalldrone::testing::DirectDrone;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Packet(Packet),
+        AddSender(NodeId, bool),
+        RemoveSender(NodeId),
+        SetPdr(f32),
+        Crash,
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            6 => packet().prop_map(Op::Packet),
+            1 => (node_id(), any::<bool>()).prop_map(|(id, live)| Op::AddSender(id, live)),
+            1 => node_id().prop_map(Op::RemoveSender),
+            1 => prop_oneof![pdr(), any::<f32>()].prop_map(Op::SetPdr),
+            1 => Just(Op::Crash),
+        ]
+    }
+
+    /// Gives an operation to the drone.
+    fn apply(direct: &mut DirectDrone, op: Op) {
+        match op {
+            Op::Packet(packet) => direct.drone.handle_packet(packet),
+            Op::AddSender(id, live) => direct.add_sender(id, live),
+            Op::RemoveSender(id) => direct.drone.handle_command(DroneCommand::RemoveSender(id)),
+            Op::SetPdr(pdr) => direct.drone.handle_command(DroneCommand::SetPacketDropRate(pdr)),
+            Op::Crash => direct.drone.handle_command(DroneCommand::Crash),
+        }
+    }
+
+    /// Checks that a nack for `original` goes back along the hops before the drone.
+    fn check_nack_route(nack: &Packet, original: &SourceRoutingHeader) -> Result<(), TestCaseError> {
+        let previous = &original.hops[..original.hop_index.min(original.hops.len())];
+        let expected: Vec<NodeId> = std::iter::once(DRONE).chain(previous.iter().rev().copied()).collect();
+        prop_assert_eq!(&nack.routing_header.hops, &expected);
+        prop_assert_eq!(nack.routing_header.hop_index, 1);
+        Ok(())
+    }
+
+    /// Removes one occurrence of `packet` from `packets`, returning whether it was there.
+    fn take(packets: &mut Vec<(NodeId, Packet)>, packet: &Packet) -> bool {
+        match packets.iter().position(|(_, p)| p == packet) {
+            Some(index) => {
+                packets.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn fragment_gives_one_forward_or_one_nack(pdr in pdr(), seed in any::<u64>(), header in header(), fragment in fragment(), session_id in any::<u64>()) {
+            let mut direct = DirectDrone::new(DRONE, &NEIGHBORS, pdr, seed);
+            let packet = Packet::new_fragment(header.clone(), session_id, fragment.clone());
+            direct.drone.handle_packet(packet.clone());
+
+            let (packets, events) = direct.outputs();
+            let shortcuts: Vec<&Packet> = events.iter().filter_map(|event| match event {
+                DroneEvent::ControllerShortcut(packet) => Some(packet),
+                _ => None,
+            }).collect();
+            prop_assert_eq!(packets.len() + shortcuts.len(), 1, "packets {:?}, events {:?}", packets, events);
+
+            let (receiver, output) = match packets.first() {
+                Some((id, packet)) => (Some(*id), packet),
+                None => (None, shortcuts[0]),
+            };
+            match &output.pack_type {
+                PacketType::MsgFragment(_) => {
+                    let mut forwarded = packet.clone();
+                    forwarded.routing_header.hop_index += 1;
+                    prop_assert_eq!(output, &forwarded);
+                    prop_assert_eq!(receiver, Some(header.hops[header.hop_index + 1]));
+                    prop_assert!(events.contains(&DroneEvent::PacketSent(forwarded)));
+                }
+                PacketType::Nack(nack) => {
+                    check_nack_route(output, &header)?;
+                    prop_assert_eq!(nack.fragment_index, fragment.fragment_index);
+                    if let Some(receiver) = receiver {
+                        prop_assert_eq!(receiver, output.routing_header.hops[1]);
+                    }
+                    let dropped = events.contains(&DroneEvent::PacketDropped(packet.clone()));
+                    prop_assert_eq!(dropped, nack.nack_type == NackType::Dropped);
+                }
+                other => prop_assert!(false, "unexpected output {:?}", other),
+            }
+        }
+
+        #[test]
+        fn nack_route_is_reversed_prefix(header in header(), fragment in fragment()) {
+            let mut direct = DirectDrone::new(DRONE, &NEIGHBORS, 1.0, 0);
+            direct.drone.handle_packet(Packet::new_fragment(header.clone(), 1, fragment));
+
+            let (packets, events) = direct.outputs();
+            let nacks = packets.iter().map(|(_, packet)| packet).chain(events.iter().filter_map(|event| match event {
+                DroneEvent::ControllerShortcut(packet) => Some(packet),
+                _ => None,
+            }));
+            for nack in nacks {
+                check_nack_route(nack, &header)?;
+            }
+        }
+
+        #[test]
+        fn packet_sent_only_for_successful_sends(pdr in pdr(), seed in any::<u64>(), packets in prop::collection::vec(packet(), 1..20)) {
+            let mut direct = DirectDrone::new(DRONE, &NEIGHBORS, pdr, seed);
+            for packet in packets {
+                direct.drone.handle_packet(packet);
+            }
+
+            let (mut received, events) = direct.outputs();
+            for event in &events {
+                if let DroneEvent::PacketSent(packet) = event {
+                    prop_assert!(take(&mut received, packet), "PacketSent without a send: {:?}", packet);
+                }
+            }
+        }
+
+        #[test]
+        fn commands_and_packets_never_panic(pdr in pdr(), seed in any::<u64>(), ops in prop::collection::vec(op(), 0..40)) {
+            let mut direct = DirectDrone::new(DRONE, &NEIGHBORS, pdr, seed);
+            for op in ops {
+                apply(&mut direct, op);
+            }
+
+            let (mut received, events) = direct.outputs();
+            for event in &events {
+                if let DroneEvent::PacketSent(packet) = event {
+                    prop_assert!(take(&mut received, packet), "PacketSent without a send: {:?}", packet);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod repair_tests {
+    use std::time::{Duration, Instant};
+    use crossbeam_channel::Receiver;
+    use wg_2024::config::{Client as ClientConfig, Config, Drone as DroneConfig, Server as ServerConfig};
+    use wg_2024::controller::DroneEvent;
+    use wg_2024::network::NodeId;
+    use wg_2024::packet::{NackType, NodeType, Packet, PacketType};
+    use drone_bettercalldrone::client::{ClientCommand, ClientEvent};
+    use drone_bettercalldrone::discovery::{RouteRepair, TopologyHandle};
+    use drone_bettercalldrone::simulation::Simulation;
+    use drone_bettercalldrone::testing::{ack, forwarded, fragment, nack, DroneFixture};
+    use drone_bettercalldrone::BetterCallDrone;
+
+    const C: NodeType = NodeType::Client;
+    const D: NodeType = NodeType::Drone;
+    const S: NodeType = NodeType::Server;
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Drone 11 between client 1 and drone 13, with route repair on and a learned graph.
+    fn repairing_drone(pdr: f32, path_traces: &[&[(NodeId, NodeType)]]) -> (DroneFixture, TopologyHandle) {
+        let (mut fixture, mut drone) = DroneFixture::build::<BetterCallDrone>(11, &[1, 13], pdr);
+        let topology = drone.learn_topology();
+        for path_trace in path_traces {
+            topology.add_path(path_trace);
+        }
+        topology.set_route_repair(true);
+        fixture.start(drone);
+        (fixture, topology)
+    }
+
+    fn with_hops(packet: &Packet, hops: &[NodeId]) -> Packet {
+        let mut packet = packet.clone();
+        packet.routing_header.hops = hops.to_vec();
+        packet
+    }
+
+    #[test]
+    fn test_repair_is_off_by_default() {
+        let (mut fixture, mut drone) = DroneFixture::build::<BetterCallDrone>(11, &[1, 13], 0.0);
+        drone.learn_topology().add_path(&[(1, C), (11, D), (13, D), (21, S)]);
+        fixture.start(drone);
+
+        fixture.send(&fragment(1, &[1, 11, 12, 21]));
+        fixture.assert_packet(1, &nack(1, &[11, 1], NackType::ErrorInRouting(12)));
+    }
+
+    #[test]
+    fn test_repair_around_missing_hop() {
+        let (fixture, topology) = repairing_drone(0.0, &[&[(1, C), (11, D), (12, D), (21, S)], &[(1, C), (11, D), (13, D), (21, S)]]);
+        let packet = fragment(1, &[1, 11, 12, 21]);
+
+        fixture.send(&packet);
+        let repaired = forwarded(&with_hops(&packet, &[1, 11, 13, 21]));
+        fixture.assert_packet(13, &repaired);
+        fixture.assert_event(&DroneEvent::PacketSent(repaired));
+        // A successful repair is not reported to the source with a nack.
+        fixture.assert_no_packet(1);
+        assert_eq!(topology.repairs(), vec![RouteRepair {
+            session_id: packet.session_id,
+            missing: 12,
+            original: vec![1, 11, 12, 21],
+            repaired: vec![1, 11, 13, 21],
+        }]);
+    }
+
+    #[test]
+    fn test_repair_to_missing_destination() {
+        let (fixture, _) = repairing_drone(0.0, &[&[(1, C), (11, D), (13, D), (14, D), (21, S)]]);
+        let packet = fragment(1, &[1, 11, 21]);
+
+        fixture.send(&packet);
+        fixture.assert_packet(13, &forwarded(&with_hops(&packet, &[1, 11, 13, 14, 21])));
+    }
+
+    #[test]
+    fn test_no_usable_detour() {
+        let (fixture, topology) = repairing_drone(0.0, &[
+            &[(1, C), (11, D), (13, D), (14, D), (15, D), (16, D), (21, S)],
+            &[(1, C), (11, D), (12, D), (22, S)],
+        ]);
+
+        fixture.send(&fragment(1, &[1, 11, 12, 21]));
+        fixture.assert_packet(1, &nack(1, &[11, 1], NackType::ErrorInRouting(12)));
+        fixture.send(&fragment(1, &[1, 11, 12, 23]));
+        fixture.assert_packet(1, &nack(1, &[11, 1], NackType::ErrorInRouting(12)));
+        assert!(topology.repairs().is_empty());
+    }
+
+    #[test]
+    fn test_dropped_fragment_is_not_repaired() {
+        let (fixture, topology) = repairing_drone(1.0, &[&[(1, C), (11, D), (13, D), (21, S)]]);
+        let packet = fragment(1, &[1, 11, 12, 21]);
+
+        fixture.send(&packet);
+        fixture.assert_packet(1, &nack(1, &[11, 1], NackType::ErrorInRouting(12)));
+        fixture.assert_no_packet(13);
+        assert!(topology.repairs().is_empty());
+    }
+
+    #[test]
+    fn test_repair_ack_route() {
+        let (fixture, topology) = repairing_drone(0.0, &[&[(1, C), (11, D), (13, D), (21, S)]]);
+        let packet = ack(2, &[21, 13, 11, 12, 1]);
+
+        fixture.send(&packet);
+        let repaired = forwarded(&with_hops(&packet, &[21, 13, 11, 1]));
+        fixture.assert_packet(1, &repaired);
+        fixture.assert_event(&DroneEvent::PacketSent(repaired));
+        assert_eq!(topology.repairs().len(), 1);
+    }
+
+    fn wait_for(events: &Receiver<ClientEvent>, accept: impl Fn(&ClientEvent) -> bool) {
+        let deadline = Instant::now() + TIMEOUT;
+        while let Ok(event) = events.recv_deadline(deadline) {
+            if accept(&event) {
+                return;
+            }
+        }
+        panic!("Timeout: expected event not received");
+    }
+
+    #[test]
+    fn test_repaired_fragment_is_delivered_once() {
+        // Client 1 reaches server 21 through 11 and 12, or through the longer 11, 13 and 12 or 14.
+        let drone = |id, connected_node_ids: &[NodeId]| DroneConfig { id, connected_node_ids: connected_node_ids.to_vec(), pdr: 0.0 };
+        let config = Config {
+            drone: vec![drone(11, &[1, 12, 13]), drone(12, &[11, 13, 21]), drone(13, &[11, 12, 14]), drone(14, &[13, 21])],
+            client: vec![ClientConfig { id: 1, connected_drone_ids: vec![11] }],
+            server: vec![ServerConfig { id: 21, connected_drone_ids: vec![12, 14] }],
+        };
+        let mut simulation = Simulation::spawn(config).unwrap();
+        simulation.set_learning(11, true).unwrap();
+        simulation.set_route_repair(11, true).unwrap();
+        let client = simulation.spawn_client(1, C).unwrap();
+        let server = simulation.spawn_client(21, S).unwrap();
+
+        client.commands.send(ClientCommand::DiscoverTopology).unwrap();
+        wait_for(&client.events, |e| matches!(e, ClientEvent::TopologyChanged(_)));
+        // The client still routes through 11 and 12, drone 11 repairs the route through 13.
+        simulation.remove_link(11, 12).unwrap();
+        client.commands.send(ClientCommand::SendMessage { destination: 21, data: b"detour".to_vec() }).unwrap();
+        wait_for(&client.events, |e| *e == ClientEvent::MessageDelivered { session_id: 0 });
+        wait_for(&server.events, |e| matches!(e, ClientEvent::MessageReceived { .. }));
+
+        let repairs = simulation.route_repairs(11).unwrap();
+        assert_eq!(repairs.len(), 1, "{repairs:?}");
+        assert_eq!(repairs[0].repaired, vec![1, 11, 13, 12, 21]);
+        let to_server = std::iter::from_fn(|| simulation.events().recv_timeout(Duration::from_millis(300)).ok())
+            .filter(|event| matches!(event, DroneEvent::PacketSent(p)
+                if matches!(p.pack_type, PacketType::MsgFragment(_)) && p.routing_header.hops.get(p.routing_header.hop_index) == Some(&21)))
+            .count();
+        assert_eq!(to_server, 1);
+        let stats = client.stop();
+        assert_eq!((stats.fragments_sent, stats.retransmissions, stats.nacks), (1, 0, 0));
+        assert_eq!(server.stop().messages_received, 1);
+    }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod routing_tests {
+    use wg_2024::network::SourceRoutingHeader;
+    use wg_2024::packet::{Nack, NackType, NodeType};
+    use drone_bettercalldrone::discovery::NetworkGraph;
+    use drone_bettercalldrone::routing::{k_most_reliable_routes, most_reliable_route, LinkQuality};
+
+    /// Client 1 reaches server 21 through 11 - 12 (short) or 13 - 14 - 15 (long).
+    fn graph() -> NetworkGraph {
+        let mut graph = NetworkGraph::new();
+        graph.add_path(&[(1, NodeType::Client), (11, NodeType::Drone), (12, NodeType::Drone), (21, NodeType::Server)]);
+        graph.add_path(&[(1, NodeType::Client), (13, NodeType::Drone), (14, NodeType::Drone), (15, NodeType::Drone), (21, NodeType::Server)]);
+        graph
+    }
+
+    fn hops(routes: &[SourceRoutingHeader]) -> Vec<Vec<u8>> {
+        routes.iter().map(|r| r.hops.clone()).collect()
+    }
+
+    #[test]
+    fn test_prefers_shortest_without_observations() {
+        let route = most_reliable_route(&graph(), &LinkQuality::new(), 1, 21).unwrap();
+        assert_eq!(route, SourceRoutingHeader { hop_index: 1, hops: vec![1, 11, 12, 21] });
+    }
+
+    #[test]
+    fn test_avoids_lossy_drone() {
+        let mut quality = LinkQuality::new();
+        for _ in 0..20 {
+            quality.record_nack(
+                &SourceRoutingHeader { hop_index: 2, hops: vec![12, 11, 1] },
+                &Nack { fragment_index: 0, nack_type: NackType::Dropped },
+            );
+        }
+        assert!(quality.estimated_pdr(12) > 0.6);
+        assert!(quality.estimated_pdr(11) < 0.1);
+        let route = most_reliable_route(&graph(), &quality, 1, 21).unwrap();
+        assert_eq!(route.hops, vec![1, 13, 14, 15, 21]);
+        assert!(quality.reliability(&route.hops) > quality.reliability(&[1, 11, 12, 21]));
+    }
+
+    #[test]
+    fn test_excludes_crashed_and_clients() {
+        let mut graph = graph();
+        graph.add_path(&[(1, NodeType::Client), (2, NodeType::Client), (21, NodeType::Server)]);
+        let mut quality = LinkQuality::new();
+        quality.mark_crashed(11);
+        assert_eq!(most_reliable_route(&graph, &quality, 1, 21).unwrap().hops, vec![1, 13, 14, 15, 21]);
+        quality.mark_crashed(14);
+        assert_eq!(most_reliable_route(&graph, &quality, 1, 21), None);
+        quality.mark_alive(11);
+        assert_eq!(most_reliable_route(&graph, &quality, 1, 21).unwrap().hops, vec![1, 11, 12, 21]);
+    }
+
+    #[test]
+    fn test_k_routes() {
+        let mut graph = graph();
+        graph.add_link(11, 14);
+        let quality = LinkQuality::new();
+        let routes = k_most_reliable_routes(&graph, &quality, 1, 21, 10);
+        let mut found = hops(&routes);
+        assert_eq!(found[0], vec![1, 11, 12, 21]);
+        assert_eq!(found[3], vec![1, 13, 14, 11, 12, 21]);
+        found.sort();
+        assert_eq!(found, vec![
+            vec![1, 11, 12, 21],
+            vec![1, 11, 14, 15, 21],
+            vec![1, 13, 14, 11, 12, 21],
+            vec![1, 13, 14, 15, 21],
+        ]);
+        assert!(routes.iter().all(|r| r.hop_index == 1));
+        assert_eq!(k_most_reliable_routes(&graph, &quality, 1, 21, 2).len(), 2);
+        assert!(k_most_reliable_routes(&graph, &quality, 1, 42, 3).is_empty());
+    }
+}
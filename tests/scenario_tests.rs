@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod scenario_tests {
+    use std::fs;
+    use drone_bettercalldrone::testing::scenario::{Scenario, ScenarioError};
+
+    const TOPOLOGY: &str = r#"
+        name = "two drones"
+
+        [[topology.drone]]
+        id = 11
+        connected_node_ids = [1, 12, 21]
+        pdr = 0.0
+
+        [[topology.drone]]
+        id = 12
+        connected_node_ids = [11, 21]
+        pdr = 0.0
+
+        [[topology.client]]
+        id = 1
+        connected_drone_ids = [11]
+
+        [[topology.server]]
+        id = 21
+        connected_drone_ids = [11, 12]
+    "#;
+
+    #[test]
+    fn test_scenario_files() {
+        let mut paths: Vec<_> = fs::read_dir("tests/scenarios").unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+        for path in paths {
+            let scenario = Scenario::load(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+            let report = scenario.run();
+            assert!(report.passed(), "{report}");
+        }
+    }
+
+    #[test]
+    fn test_failure_report() {
+        let scenario = Scenario::parse(&format!(r#"{TOPOLOGY}
+            [[step]]
+            inject = {{ node = 11, packet = {{ type = "fragment", session = 9, hops = [1, 11, 13] }} }}
+            expect = [
+                {{ node = 1, packet = {{ type = "nack", nack = "Dropped" }} }},
+                {{ node = 1, packet = {{ type = "nack" }}, absent = true }},
+            ]
+        "#)).unwrap();
+        let report = scenario.run();
+
+        assert_eq!(report.failures.len(), 1);
+        let text = report.to_string();
+        assert!(text.contains("step 1:"), "{text}");
+        assert!(text.contains("nack_type: expected Dropped, got ErrorInRouting(13)"), "{text}");
+        assert!(text.contains("closest: (9:0) Nack(ErrorInRouting(13))"), "{text}");
+        assert!(text.contains("✗ nothing like: node 1 receives Nack"), "{text}");
+    }
+
+    #[test]
+    fn test_invalid_scenarios() {
+        let missing_hops = format!(r#"{TOPOLOGY}
+            [[step]]
+            inject = {{ node = 11, packet = {{ type = "ack" }} }}
+        "#);
+        assert!(matches!(Scenario::parse(&missing_hops), Err(ScenarioError::InvalidStep { step: 1, .. })));
+
+        let unknown_field = format!(r#"{TOPOLOGY}
+            [[step]]
+            expect = [{{ node = 1, packet = {{ type = "nack", nack_type = "Dropped" }} }}]
+        "#);
+        assert!(matches!(Scenario::parse(&unknown_field), Err(ScenarioError::Parse(_))));
+
+        let bad_command = format!(r#"{TOPOLOGY}
+            [[step]]
+            command = {{ type = "crash", drone = 12 }}
+        "#);
+        let report = Scenario::parse(&bad_command).unwrap().run();
+        assert!(report.failures[0].lines[0].starts_with("action failed:"), "{report}");
+    }
+}
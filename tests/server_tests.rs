@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod server_tests {
+    use std::collections::HashMap;
+    use std::thread;
+    use std::time::Duration;
+    use crossbeam_channel::unbounded;
+    use wg_2024::network::SourceRoutingHeader;
+    use wg_2024::packet::{Ack, FloodRequest, FloodResponse, Fragment, NodeType, Packet, PacketType};
+    use drone_bettercalldrone::client::{Client, ClientCommand, ClientEvent};
+    use drone_bettercalldrone::fragmentation::fragment_message;
+    use drone_bettercalldrone::server::{Server, ServerCommand, ServerEvent};
+    use drone_bettercalldrone::simulation::Simulation;
+    use drone_bettercalldrone::topology::{generators, GeneratorOptions};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_reassembles_and_acks() {
+        let (d_send, d_recv) = unbounded();
+        let (_s_command_send, s_command_recv) = unbounded();
+        let (s_event_send, s_event_recv) = unbounded();
+        let (_s_packet_send, s_packet_recv) = unbounded();
+
+        let mut server = Server::new(21, s_event_send, s_command_recv, s_packet_recv, HashMap::from([(12, d_send)]));
+
+        let message: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        for fragment in fragment_message(&message).into_iter().rev() {
+            let index = fragment.fragment_index;
+            server.handle_packet(Packet::new_fragment(SourceRoutingHeader { hop_index: 3, hops: vec![1, 11, 12, 21] }, 5, fragment));
+            assert_eq!(
+                d_recv.try_recv().unwrap(),
+                Packet {
+                    pack_type: PacketType::Ack(Ack { fragment_index: index }),
+                    routing_header: SourceRoutingHeader { hop_index: 1, hops: vec![21, 12, 11, 1] },
+                    session_id: 5,
+                }
+            );
+        }
+
+        let received = s_event_recv.try_iter().find(|e| matches!(e, ServerEvent::MessageReceived { .. }));
+        assert_eq!(received, Some(ServerEvent::MessageReceived { source: 1, session_id: 5, data: message }));
+    }
+
+    #[test]
+    fn test_late_fragment_is_acked_once_more() {
+        let (d_send, d_recv) = unbounded();
+        let (_s_command_send, s_command_recv) = unbounded();
+        let (s_event_send, s_event_recv) = unbounded();
+        let (_s_packet_send, s_packet_recv) = unbounded();
+        let mut server = Server::new(21, s_event_send, s_command_recv, s_packet_recv, HashMap::from([(12, d_send)]));
+
+        let fragment = fragment_message(b"once").remove(0);
+        for _ in 0..2 {
+            server.handle_packet(Packet::new_fragment(SourceRoutingHeader { hop_index: 3, hops: vec![1, 11, 12, 21] }, 5, fragment.clone()));
+            assert!(matches!(d_recv.try_recv().unwrap().pack_type, PacketType::Ack(Ack { fragment_index: 0 })));
+        }
+
+        let received: Vec<ServerEvent> = s_event_recv.try_iter().filter(|e| matches!(e, ServerEvent::MessageReceived { .. })).collect();
+        assert_eq!(received, vec![ServerEvent::MessageReceived { source: 1, session_id: 5, data: b"once".to_vec() }]);
+    }
+
+    #[test]
+    fn test_huge_fragment_count() {
+        let (d_send, d_recv) = unbounded();
+        let (_s_command_send, s_command_recv) = unbounded();
+        let (s_event_send, _s_event_recv) = unbounded();
+        let (_s_packet_send, s_packet_recv) = unbounded();
+        let mut server = Server::new(21, s_event_send, s_command_recv, s_packet_recv, HashMap::from([(12, d_send)]));
+
+        let fragment = Fragment { fragment_index: 0, total_n_fragments: u64::MAX >> 8, length: 128, data: [1; 128] };
+        server.handle_packet(Packet::new_fragment(SourceRoutingHeader { hop_index: 3, hops: vec![1, 11, 12, 21] }, 5, fragment));
+        assert!(matches!(d_recv.try_recv().unwrap().pack_type, PacketType::Ack(Ack { fragment_index: 0 })));
+    }
+
+    #[test]
+    fn test_answers_flood_as_server() {
+        let (d_send, d_recv) = unbounded();
+        let (_s_command_send, s_command_recv) = unbounded();
+        let (s_event_send, _s_event_recv) = unbounded();
+        let (s_packet_send, s_packet_recv) = unbounded();
+
+        let mut server = Server::new(21, s_event_send, s_command_recv, s_packet_recv, HashMap::from([(12, d_send)]));
+        thread::spawn(move || server.run());
+
+        let request = FloodRequest {
+            flood_id: 3,
+            initiator_id: 1,
+            path_trace: vec![(1, NodeType::Client), (12, NodeType::Drone)],
+        };
+        s_packet_send.send(Packet::new_flood_request(SourceRoutingHeader::empty_route(), 9, request)).unwrap();
+
+        assert_eq!(
+            d_recv.recv_timeout(TIMEOUT).unwrap(),
+            Packet {
+                pack_type: PacketType::FloodResponse(FloodResponse {
+                    flood_id: 3,
+                    path_trace: vec![(1, NodeType::Client), (12, NodeType::Drone), (21, NodeType::Server)],
+                }),
+                routing_header: SourceRoutingHeader { hop_index: 1, hops: vec![21, 12, 1] },
+                session_id: 9,
+            }
+        );
+    }
+
+    #[test]
+    fn test_client_to_server() {
+        let options = GeneratorOptions { pdr: (0.2, 0.2), ..GeneratorOptions::default() };
+        let mut simulation = Simulation::spawn(generators::grid(3, 3, &options)).unwrap();
+        let (client_id, server_id) = (10, 11);
+
+        let endpoint = simulation.take_endpoint(server_id).unwrap();
+        let (s_command_send, s_command_recv) = unbounded();
+        let (s_event_send, s_event_recv) = unbounded();
+        let mut server = Server::new(server_id, s_event_send, s_command_recv, endpoint.packet_recv, endpoint.packet_send);
+        thread::spawn(move || server.run());
+
+        let endpoint = simulation.take_endpoint(client_id).unwrap();
+        let (c_command_send, c_command_recv) = unbounded();
+        let (c_event_send, c_event_recv) = unbounded();
+        let mut client = Client::new(client_id, c_event_send, c_command_recv, endpoint.packet_recv, endpoint.packet_send);
+        thread::spawn(move || client.run());
+
+        let data = vec![42; 1000];
+        c_command_send.send(ClientCommand::SendMessage { destination: server_id, data: data.clone() }).unwrap();
+
+        let received = s_event_recv.iter().find(|e| matches!(e, ServerEvent::MessageReceived { .. }));
+        assert_eq!(received, Some(ServerEvent::MessageReceived { source: client_id, session_id: 0, data }));
+        let delivered = c_event_recv.iter().find(|e| matches!(e, ClientEvent::MessageDelivered { .. }));
+        assert_eq!(delivered, Some(ClientEvent::MessageDelivered { session_id: 0 }));
+
+        c_command_send.send(ClientCommand::Crash).unwrap();
+        s_command_send.send(ServerCommand::Crash).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod simulation_tests {
+    use std::time::Duration;
+    use wg_2024::controller::DroneEvent;
+    use wg_2024::packet::{NodeType, PacketType};
+    use drone_bettercalldrone::repl::ReplCommand;
+    use drone_bettercalldrone::simulation::{Simulation, SimulationError};
+    use drone_bettercalldrone::topology::{generators, GeneratorOptions, TopologyError};
+
+    const TIMEOUT: Duration = Duration::from_millis(400);
+
+    /// Ring of drones 1..=5, client 6 on drone 1, server 7 on drones 5 and 4.
+    fn ring() -> Simulation {
+        Simulation::spawn(generators::ring(5, &GeneratorOptions::default())).unwrap()
+    }
+
+    fn run(simulation: &mut Simulation, line: &str) -> Result<String, String> {
+        ReplCommand::parse(line)?.execute(simulation)
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(ReplCommand::parse("add 1 2"), Ok(ReplCommand::AddLink(1, 2)));
+        assert_eq!(ReplCommand::parse("  pdr 3 0.5 "), Ok(ReplCommand::SetPdr(3, 0.5)));
+        assert_eq!(
+            ReplCommand::parse("send 6,1,2 7 0 hello drones"),
+            Ok(ReplCommand::Send { hops: vec![6, 1, 2], session_id: 7, fragment_index: 0, message: "hello drones".to_string() })
+        );
+        assert!(ReplCommand::parse("crash").is_err());
+        assert!(ReplCommand::parse("crash one").is_err());
+        assert!(ReplCommand::parse("send 6 7 0 hi").is_err());
+        assert!(ReplCommand::parse("jump 1").is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_commands() {
+        let mut simulation = ring();
+        assert_eq!(simulation.set_pdr(1, 2.0), Err(SimulationError::InvalidTopology(vec![TopologyError::InvalidPdr { drone: 1, pdr: 2.0 }])));
+        assert_eq!(simulation.set_pdr(6, 0.5), Err(SimulationError::NotADrone(6)));
+        assert_eq!(simulation.crash(42), Err(SimulationError::UnknownNode(42)));
+        // Drone 1 is the only drone of client 6.
+        assert!(matches!(simulation.crash(1), Err(SimulationError::InvalidTopology(_))));
+        assert!(matches!(simulation.add_link(6, 7), Err(SimulationError::InvalidTopology(_))));
+        assert!(run(&mut simulation, "remove 1 2").is_ok());
+        // The ring is now a line, removing another link would split it.
+        assert!(run(&mut simulation, "remove 3 4").is_err());
+        assert_eq!(simulation.config().drone[0].connected_node_ids, vec![5, 6]);
+    }
+
+    #[test]
+    fn test_flood_from_client() {
+        let mut simulation = ring();
+        run(&mut simulation, "flood 6 99").unwrap();
+
+        let client = &simulation.endpoint_packets()[&6];
+        let mut traces = Vec::new();
+        while let Ok(packet) = client.recv_timeout(TIMEOUT) {
+            if let PacketType::FloodResponse(response) = packet.pack_type {
+                assert_eq!(response.flood_id, 99);
+                traces.push(response.path_trace);
+            }
+        }
+        assert!(traces.iter().any(|trace| trace.contains(&(3, NodeType::Drone))));
+    }
+
+    #[test]
+    fn test_send_and_crash() {
+        let mut simulation = ring();
+        run(&mut simulation, "send 6,1,2,3 1 0 hi").unwrap();
+        // Drone 2 may report its own `PacketSent` before drone 1 does.
+        let sent_by_drone_1 = std::iter::from_fn(|| simulation.events().recv_timeout(TIMEOUT).ok())
+            .find(|event| matches!(event, DroneEvent::PacketSent(packet) if packet.routing_header.hop_index == 2));
+        assert!(sent_by_drone_1.is_some(), "drone 1 did not report PacketSent");
+
+        run(&mut simulation, "crash 3").unwrap();
+        assert!(simulation.config().drone.iter().all(|d| d.id != 3 && !d.connected_node_ids.contains(&3)));
+        assert!(run(&mut simulation, "pdr 3 0.1").is_err());
+    }
+
+    #[test]
+    fn test_learned_topology() {
+        let mut simulation = ring();
+        run(&mut simulation, "flood 6 98").unwrap();
+        while simulation.endpoint_packets()[&6].recv_timeout(TIMEOUT).is_ok() {}
+        // Learning is off until turned on.
+        assert_eq!(simulation.is_learning(1), Ok(false));
+        assert!(simulation.learned_topology(1).unwrap().links().is_empty());
+        assert!(run(&mut simulation, "learned 1").unwrap().contains("learning is off"));
+
+        assert_eq!(ReplCommand::parse("learn 1 on"), Ok(ReplCommand::Learn(1, true)));
+        assert!(ReplCommand::parse("learn 1 maybe").is_err());
+        assert!(run(&mut simulation, "learn 6 on").is_err());
+        run(&mut simulation, "learn 1 on").unwrap();
+        run(&mut simulation, "flood 6 99").unwrap();
+        while simulation.endpoint_packets()[&6].recv_timeout(TIMEOUT).is_ok() {}
+
+        let learned = simulation.learned_topology(1).unwrap();
+        assert!(learned.links().contains(&(1, 6)));
+        assert!(learned.links().contains(&(2, 3)));
+        assert_eq!(simulation.learned_topology(6), Err(SimulationError::NotADrone(6)));
+
+        run(&mut simulation, "crash 3").unwrap();
+        let report = run(&mut simulation, "learned 1").unwrap();
+        assert!(report.starts_with("drone 1 learned"), "{report}");
+        assert!(report.contains("not in the topology: nodes {3}"), "{report}");
+        assert!(!report.contains("learning is off"), "{report}");
+        // Turning learning off keeps the learned graph.
+        run(&mut simulation, "learn 1 off").unwrap();
+        assert!(simulation.learned_topology(1).unwrap().links().contains(&(1, 6)));
+
+        assert_eq!(ReplCommand::parse("repair 2 on"), Ok(ReplCommand::Repair(2, true)));
+        assert!(ReplCommand::parse("repair 2 maybe").is_err());
+        run(&mut simulation, "repair 2 on").unwrap();
+        assert!(run(&mut simulation, "repair 3 on").is_err());
+        assert_eq!(simulation.route_repairs(2), Ok(Vec::new()));
+
+        assert!(ReplCommand::parse("floodlimit 2 0 1 drop").is_err());
+        assert!(ReplCommand::parse("floodlimit 2 1 1 ignore").is_err());
+        assert_eq!(ReplCommand::parse("floodlimit 2 off"), Ok(ReplCommand::FloodLimit(2, None)));
+        run(&mut simulation, "floodlimit 2 3 0.5 respond").unwrap();
+        let report = run(&mut simulation, "floods 2").unwrap();
+        assert!(report.starts_with("drone 2 forwarded"), "{report}");
+        assert!(run(&mut simulation, "floods 3").is_err());
+    }
+}
@@ -0,0 +1,229 @@
+#[cfg(test)]
+mod topology_tests {
+    use wg_2024::config::{Client, Config, Drone, Server};
+    use drone_bettercalldrone::topology::{generators, parse_config, validate_config, GeneratorOptions, Placement, TopologyError};
+
+    fn drone(id: u8, connected_node_ids: Vec<u8>, pdr: f32) -> Drone {
+        Drone { id, connected_node_ids, pdr }
+    }
+
+    /// Client 1 - drones 11, 12, 13 in a ring - server 21.
+    fn valid_config() -> Config {
+        Config {
+            drone: vec![
+                drone(11, vec![12, 13, 1], 0.1),
+                drone(12, vec![11, 13, 21], 0.0),
+                drone(13, vec![11, 12, 21], 1.0),
+            ],
+            client: vec![Client { id: 1, connected_drone_ids: vec![11] }],
+            server: vec![Server { id: 21, connected_drone_ids: vec![12, 13] }],
+        }
+    }
+
+    #[test]
+    fn test_valid_config() {
+        assert_eq!(validate_config(&valid_config()), Ok(()));
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let content = r#"
+            [[drone]]
+            id = 11
+            connected_node_ids = [12, 1]
+            pdr = 0.05
+
+            [[drone]]
+            id = 12
+            connected_node_ids = [11, 13, 21]
+            pdr = 0.05
+
+            [[drone]]
+            id = 13
+            connected_node_ids = [12, 21]
+            pdr = 0.05
+
+            [[client]]
+            id = 1
+            connected_drone_ids = [11]
+
+            [[server]]
+            id = 21
+            connected_drone_ids = [12, 13]
+        "#;
+        let config = parse_config(content).unwrap();
+        assert_eq!(config.drone.len(), 3);
+        assert_eq!(validate_config(&config), Ok(()));
+    }
+
+    #[test]
+    fn test_duplicate_id() {
+        let mut config = valid_config();
+        config.client[0].id = 21;
+        assert_eq!(validate_config(&config), Err(vec![TopologyError::DuplicateId(21)]));
+    }
+
+    #[test]
+    fn test_self_neighbor() {
+        let mut config = valid_config();
+        config.drone[0].connected_node_ids.push(11);
+        assert_eq!(validate_config(&config), Err(vec![TopologyError::SelfNeighbor(11)]));
+    }
+
+    #[test]
+    fn test_not_bidirectional() {
+        let mut config = valid_config();
+        config.drone[1].connected_node_ids.retain(|&id| id != 11);
+        assert_eq!(
+            validate_config(&config),
+            Err(vec![TopologyError::NotBidirectional { from: 11, to: 12 }])
+        );
+    }
+
+    #[test]
+    fn test_unknown_neighbor() {
+        let mut config = valid_config();
+        config.drone[2].connected_node_ids.push(42);
+        assert_eq!(
+            validate_config(&config),
+            Err(vec![TopologyError::UnknownNeighbor { node: 13, neighbor: 42 }])
+        );
+    }
+
+    #[test]
+    fn test_invalid_pdr() {
+        let mut config = valid_config();
+        config.drone[1].pdr = 1.5;
+        assert_eq!(
+            validate_config(&config),
+            Err(vec![TopologyError::InvalidPdr { drone: 12, pdr: 1.5 }])
+        );
+    }
+
+    #[test]
+    fn test_invalid_degrees() {
+        let mut config = valid_config();
+        config.client[0].connected_drone_ids = vec![11, 12, 13];
+        config.drone[1].connected_node_ids.push(1);
+        config.drone[2].connected_node_ids.push(1);
+        config.server[0].connected_drone_ids = vec![12];
+        config.drone[2].connected_node_ids.retain(|&id| id != 21);
+        assert_eq!(
+            validate_config(&config),
+            Err(vec![
+                TopologyError::InvalidClientDegree { client: 1, degree: 3 },
+                TopologyError::InvalidServerDegree { server: 21, degree: 1 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_client_connected_to_server() {
+        let mut config = valid_config();
+        config.client[0].connected_drone_ids.push(21);
+        config.server[0].connected_drone_ids.push(1);
+        assert_eq!(
+            validate_config(&config),
+            Err(vec![
+                TopologyError::NotADrone { node: 1, neighbor: 21 },
+                TopologyError::NotADrone { node: 21, neighbor: 1 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_disconnected() {
+        let mut config = valid_config();
+        config.drone.push(drone(14, vec![15], 0.0));
+        config.drone.push(drone(15, vec![14], 0.0));
+        assert_eq!(
+            validate_config(&config),
+            Err(vec![TopologyError::Disconnected { unreachable: vec![14, 15] }])
+        );
+    }
+
+    #[test]
+    fn test_disconnected_through_server() {
+        let config = Config {
+            drone: vec![
+                drone(11, vec![1, 21], 0.0),
+                drone(12, vec![21], 0.0),
+            ],
+            client: vec![Client { id: 1, connected_drone_ids: vec![11] }],
+            server: vec![Server { id: 21, connected_drone_ids: vec![11, 12] }],
+        };
+        assert_eq!(
+            validate_config(&config),
+            Err(vec![TopologyError::Disconnected { unreachable: vec![12] }])
+        );
+    }
+
+    fn generated(options: &GeneratorOptions) -> Vec<Config> {
+        vec![
+            generators::line(6, options),
+            generators::ring(7, options),
+            generators::grid(3, 4, options),
+            generators::star(6, options),
+            generators::double_chain(5, options),
+            generators::butterfly(2, options),
+            generators::tree(3, 2, options),
+            generators::erdos_renyi(12, 0.15, options),
+            generators::scale_free(15, 2, options),
+        ]
+    }
+
+    #[test]
+    fn test_generated_topologies_are_valid() {
+        for seed in 0..50 {
+            for placement in [Placement::Random, Placement::Ends] {
+                let options = GeneratorOptions { pdr: (0.0, 0.3), clients: 3, servers: 2, placement, seed };
+                for config in generated(&options) {
+                    assert_eq!(validate_config(&config), Ok(()), "{config:?}");
+                    assert_eq!(config.client.len(), 3);
+                    assert_eq!(config.server.len(), 2);
+                    assert!(config.drone.iter().all(|d| (0.0..=0.3).contains(&d.pdr)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_shapes() {
+        let options = GeneratorOptions { clients: 0, servers: 0, ..GeneratorOptions::default() };
+        let links = |config: &Config| config.drone.iter().map(|d| d.connected_node_ids.len()).sum::<usize>() / 2;
+
+        assert_eq!(links(&generators::line(5, &options)), 4);
+        assert_eq!(links(&generators::ring(5, &options)), 5);
+        assert_eq!(links(&generators::grid(3, 3, &options)), 12);
+        assert_eq!(links(&generators::star(5, &options)), 4);
+        assert_eq!(links(&generators::double_chain(4, &options)), 10);
+        assert_eq!(links(&generators::butterfly(3, &options)), 48);
+        assert_eq!(generators::tree(2, 3, &options).drone.len(), 13);
+        assert_eq!(links(&generators::scale_free(10, 2, &options)), 1 + 8 * 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid PDR range")]
+    fn test_generator_rejects_empty_pdr_range() {
+        let options = GeneratorOptions { pdr: (0.5, 0.2), ..GeneratorOptions::default() };
+        let _ = generators::line(3, &options);
+    }
+
+    #[test]
+    #[should_panic(expected = "servers need at least two drones")]
+    fn test_generator_rejects_servers_on_one_drone() {
+        let _ = generators::star(1, &GeneratorOptions::default());
+    }
+
+    #[test]
+    fn test_generators_are_seeded() {
+        let options = GeneratorOptions { pdr: (0.0, 1.0), placement: Placement::Random, seed: 42, ..GeneratorOptions::default() };
+        let a = generators::erdos_renyi(20, 0.2, &options);
+        let b = generators::erdos_renyi(20, 0.2, &options);
+        assert_eq!(format!("{a:?}"), format!("{b:?}"));
+
+        let other = GeneratorOptions { seed: 43, ..options };
+        let c = generators::erdos_renyi(20, 0.2, &other);
+        assert_ne!(format!("{a:?}"), format!("{c:?}"));
+    }
+}
@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod transport_tests {
+    use std::time::{Duration, Instant};
+    use drone_bettercalldrone::fragmentation::fragment_message;
+    use drone_bettercalldrone::transport::{Transport, TransportConfig};
+
+    const CONFIG: TransportConfig = TransportConfig { window: 4, timeout: Duration::from_millis(100) };
+
+    fn indexes(transport: &mut Transport, now: Instant) -> Vec<u64> {
+        transport.poll_transmit(now).iter().map(|f| f.fragment.fragment_index).collect()
+    }
+
+    #[test]
+    fn test_window() {
+        let now = Instant::now();
+        let mut transport = Transport::new(CONFIG);
+        let session_id = transport.send_message(7, &[1; 128 * 10]);
+
+        assert_eq!(indexes(&mut transport, now), vec![0, 1, 2, 3]);
+        assert!(indexes(&mut transport, now).is_empty());
+
+        transport.handle_ack(session_id, 1);
+        transport.handle_ack(session_id, 1);
+        assert_eq!(indexes(&mut transport, now), vec![4]);
+
+        for index in 0..10 {
+            transport.handle_ack(session_id, index);
+            transport.poll_transmit(now);
+        }
+        assert_eq!(transport.delivered_message(), Some(session_id));
+        assert_eq!(transport.delivered_message(), None);
+        assert_eq!(transport.pending_messages(), 0);
+        assert_eq!(transport.stats().fragments_sent, 10);
+        assert_eq!(transport.stats().retransmissions, 0);
+    }
+
+    #[test]
+    fn test_selective_repeat() {
+        let now = Instant::now();
+        let mut transport = Transport::new(CONFIG);
+        let session_id = transport.send_message(7, &[1; 128 * 3]);
+        assert_eq!(indexes(&mut transport, now), vec![0, 1, 2]);
+
+        transport.handle_ack(session_id, 0);
+        transport.handle_nack(session_id, 2);
+        transport.handle_nack(session_id, 2);
+        assert_eq!(indexes(&mut transport, now), vec![2]);
+
+        // Only fragment 1 is late: fragment 2 was sent again at `later`.
+        let later = now + Duration::from_millis(60);
+        transport.handle_nack(session_id, 2);
+        assert_eq!(indexes(&mut transport, later), vec![2]);
+        assert_eq!(indexes(&mut transport, now + CONFIG.timeout), vec![1]);
+        assert_eq!(transport.next_timeout(), Some(later + CONFIG.timeout));
+
+        let stats = transport.stats();
+        assert_eq!(stats.nacks, 2);
+        assert_eq!(stats.timeouts, 1);
+        assert_eq!(stats.retransmissions, 3);
+    }
+
+    #[test]
+    fn test_duplicate_fragments() {
+        let mut transport = Transport::new(CONFIG);
+        let message: Vec<u8> = (0..=255).collect();
+        let fragments = fragment_message(&message);
+
+        assert!(!transport.handle_fragment(3, 9, &fragments[1]));
+        assert!(transport.handle_fragment(3, 9, &fragments[0]));
+        for fragment in &fragments {
+            assert!(!transport.handle_fragment(3, 9, fragment));
+        }
+
+        let received = transport.receive_message().unwrap();
+        assert_eq!((received.source, received.session_id, received.data), (3, 9, message));
+        assert_eq!(transport.receive_message(), None);
+        assert_eq!(transport.stats().messages_received, 1);
+    }
+}